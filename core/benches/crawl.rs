@@ -0,0 +1,119 @@
+//! Benchmarks for the CPU bound parts of the crawl loop, executed once per crawled block.
+//!
+//! Run with:
+//!
+//! ```sh
+//! cargo bench -p avail-light-core --features crawl --bench crawl
+//! ```
+//!
+//! Criterion keeps the results of the previous run in `target/criterion`,
+//! so regressions are reported when benchmarks are run again after the change.
+
+use avail_light_core::crawl_client::{
+	count_fetched_rows, partition_positions, CrawlConfig, CrawlStats, PartitionSampling,
+	SampleCache, SamplingStrategy, ENTIRE_BLOCK,
+};
+use avail_rust::kate_recovery::matrix::{Dimensions, Partition, Position};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::collections::HashSet;
+
+/// Representative block matrices (original rows, columns)
+const MATRICES: [(&str, u16, u16); 3] =
+	[("small", 16, 64), ("medium", 64, 256), ("large", 256, 256)];
+
+const HALF_BLOCK: Partition = Partition {
+	number: 1,
+	fraction: 2,
+};
+
+fn dimensions(rows: u16, cols: u16) -> Dimensions {
+	Dimensions::new(rows, cols).expect("Valid dimensions")
+}
+
+fn bench_positions(c: &mut Criterion) {
+	let mut group = c.benchmark_group("crawl_positions");
+	for (name, rows, cols) in MATRICES {
+		let dimensions = dimensions(rows, cols);
+		for (partition_name, partition) in [("1/1", ENTIRE_BLOCK), ("1/2", HALF_BLOCK)] {
+			let id = BenchmarkId::new(name, partition_name);
+			group.bench_with_input(id, &dimensions, |b, dimensions| {
+				b.iter(|| partition_positions(black_box(dimensions), black_box(&partition)))
+			});
+		}
+	}
+	group.finish();
+}
+
+/// Sample computed from the dimensions for each block, compared to the sample reused from the cache
+fn bench_sample(c: &mut Criterion) {
+	let mut group = c.benchmark_group("crawl_sample");
+	let config = CrawlConfig {
+		crawl_columns: vec![0, 1],
+		..Default::default()
+	};
+	for (name, rows, cols) in MATRICES {
+		let dimensions = dimensions(rows, cols);
+		group.bench_with_input(
+			BenchmarkId::new(name, "computed"),
+			&dimensions,
+			|b, dimensions| b.iter(|| PartitionSampling.sample(black_box(dimensions), &config)),
+		);
+		let mut cache = SampleCache::new();
+		group.bench_with_input(
+			BenchmarkId::new(name, "cached"),
+			&dimensions,
+			|b, dimensions| b.iter(|| cache.sample(black_box(dimensions), &config)),
+		);
+	}
+	group.finish();
+}
+
+fn bench_dedup(c: &mut Criterion) {
+	let mut group = c.benchmark_group("crawl_dedup");
+	for (name, rows, cols) in MATRICES {
+		let positions = partition_positions(&dimensions(rows, cols), &ENTIRE_BLOCK);
+		group.bench_with_input(name, &positions, |b, positions| {
+			b.iter(|| {
+				black_box(positions)
+					.iter()
+					.copied()
+					.collect::<HashSet<Position>>()
+			})
+		});
+	}
+	group.finish();
+}
+
+fn bench_success_rate(c: &mut Criterion) {
+	let mut group = c.benchmark_group("crawl_success_rate");
+	for (name, rows, cols) in MATRICES {
+		let dimensions = dimensions(rows, cols);
+		let row_indexes: Vec<u32> = (0..dimensions.extended_rows()).step_by(2).collect();
+		// Every other requested row is fetched
+		let fetched_rows: Vec<Option<Vec<u8>>> = (0..dimensions.extended_rows())
+			.map(|index| (index % 4 == 0).then(|| vec![0; dimensions.width::<usize>() * 32]))
+			.collect();
+
+		group.bench_with_input(name, &(row_indexes, fetched_rows), |b, (indexes, rows)| {
+			b.iter(|| {
+				let fetched = count_fetched_rows(black_box(indexes), black_box(rows), |_| true);
+				let stats = CrawlStats {
+					total: indexes.len(),
+					fetched,
+				};
+				let matrix_cells = dimensions.extended_size() as usize;
+				(stats.success_rate(), stats.confidence(matrix_cells))
+			})
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(
+	benches,
+	bench_positions,
+	bench_sample,
+	bench_dedup,
+	bench_success_rate
+);
+criterion_main!(benches);
@@ -1,19 +1,126 @@
 use crate::{
-	network::{p2p::Client, rpc},
+	data::{CrawlCheckpoint, CrawlCheckpointBatchKey, CrawlCheckpointKey, Database},
+	network::{p2p, rpc},
+	shutdown::Controller,
 	telemetry::{otlp::Record, MetricName, Value},
-	types::{self, BlockVerified, Delay, Origin},
+	types::{self, block_matrix_partition_format, BlockVerified, Delay, Origin},
+	utils::{self, spawn_in_span},
 };
-use avail_rust::kate_recovery::matrix::Partition;
+use async_trait::async_trait;
+use avail_rust::{
+	avail_core::kate::CHUNK_SIZE,
+	kate_recovery::{
+		com::reconstruct_columns,
+		data::Cell,
+		matrix::{Dimensions, Partition, Position},
+	},
+	subxt::config::substrate::DigestItem,
+	AvailHeader, H256,
+};
+use codec::Encode;
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
+use libp2p::PeerId;
+use mockall::automock;
+use rand::seq::{index, SliceRandom};
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, Instant};
-use tokio::sync::{broadcast, mpsc::UnboundedSender};
-use tracing::{error, info};
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	future::Future,
+	pin::Pin,
+	sync::{Arc, RwLock},
+	time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+	sync::{broadcast, mpsc::UnboundedSender, watch},
+	task::{JoinError, JoinHandle},
+};
+use tracing::{error, info, info_span, warn, Instrument, Span};
+
+pub mod assignment;
+mod cache;
+mod clock;
+mod config;
+pub mod csv;
+mod filter;
+pub mod fleet;
+mod gaps;
+pub mod heatmap;
+pub mod manifest;
+mod offenders;
+pub mod openmetrics;
+mod order;
+mod peers;
+pub mod reconcile;
+mod ring;
+mod sampling;
+#[cfg(unix)]
+pub mod socket;
+pub mod summary;
+pub mod webhook;
+
+pub use cache::FetchCache;
+use clock::SleepTracker;
+pub use clock::{Clock, MockClock, TokioClock};
+use config::log_config_changes;
+pub use config::{
+	AvailabilityCriteria, CellCrawlOpts, ChaosOpts, CrawlConfig, EscalationOpts, FetchCacheOpts,
+	ForwardOpts, GapScanOpts, HeatmapOpts, OffendersOpts, RingOpts, RowCrawlOpts, ZeroRateOpts,
+};
+pub use filter::BlockNumbers;
+pub use fleet::{fleet_coverage, FleetCoverage};
+use gaps::CrawledBlocks;
+use heatmap::Heatmap;
+pub use offenders::Offenders;
+use order::OrderedBlocks;
+pub use peers::{PeerFetchCounts, PeerStats};
+pub use ring::Ring;
+pub use sampling::{
+	partition_positions, FetchPriority, PartitionSampling, RandomSampling, Sample, SampleCache,
+	Sampling, SamplingStrategy,
+};
+use summary::History;
 
+/// Crawl metrics recorded by the crawler, described by the [`manifest`] of the metrics,
+/// which lists each variant in the [`manifest::METRICS`]
 #[derive(Clone)]
 pub enum CrawlMetricValue {
 	CellsSuccessRate(f64),
 	RowsSuccessRate(f64),
 	BlockDelay(f64),
+	AvailableBlocks,
+	CellsCoverage(f64),
+	RowsCoverage(f64),
+	RefetchedCells(u64),
+	Reconstructable(bool),
+	RowsDerivedRatio(f64),
+	DimensionAnomalies,
+	AvailabilityConfidence(f64),
+	CellsServedLocally(u64),
+	CellsServedDht(u64),
+	TooOldBlocks,
+	QueriesPerCell(f64),
+	DuplicateHeaders,
+	ClockSkew,
+	RecentGapBlocks(u64),
+	FetchCacheHits,
+	FetchCacheMisses,
+	EmptyPartitions,
+	DimensionChanges,
+	SampleRate(f64),
+	Escalations,
+	ReconstructionCells(u64),
+	BytesFetched(u64),
+	FastForwardedBlocks(u64),
+	IdleTime(u64),
+	SleepingTime(u64),
+	FetchingTime(u64),
+	DeniedBlocks,
+	ZeroRateEscalations,
+	IntraBlockRateVariance(f64),
+	ReconciliationDelta(f64),
 }
 
 impl MetricName for CrawlMetricValue {
@@ -23,6 +130,37 @@ impl MetricName for CrawlMetricValue {
 			CellsSuccessRate(_) => "avail.light.crawl.cells_success_rate",
 			RowsSuccessRate(_) => "avail.light.crawl.rows_success_rate",
 			BlockDelay(_) => "avail.light.crawl.block_delay",
+			AvailableBlocks => "avail.light.crawl.available_blocks",
+			CellsCoverage(_) => "avail.light.crawl.cells_coverage",
+			RowsCoverage(_) => "avail.light.crawl.rows_coverage",
+			RefetchedCells(_) => "avail.light.crawl.refetched_cells",
+			Reconstructable(_) => "avail.light.crawl.reconstructable",
+			RowsDerivedRatio(_) => "avail.light.crawl.rows_derived_ratio",
+			DimensionAnomalies => "avail.light.crawl.dimension_anomaly",
+			AvailabilityConfidence(_) => "avail.light.crawl.availability_confidence",
+			CellsServedLocally(_) => "avail.light.crawl.cells_served_locally",
+			CellsServedDht(_) => "avail.light.crawl.cells_served_dht",
+			TooOldBlocks => "avail.light.crawl.too_old",
+			DuplicateHeaders => "avail.light.crawl.duplicate_headers",
+			ClockSkew => "avail.light.crawl.clock_skew",
+			RecentGapBlocks(_) => "avail.light.crawl.recent_gap_blocks",
+			FetchCacheHits => "avail.light.crawl.fetch_cache_hits",
+			FetchCacheMisses => "avail.light.crawl.fetch_cache_misses",
+			EmptyPartitions => "avail.light.crawl.empty_partition",
+			DimensionChanges => "avail.light.crawl.dimension_change",
+			SampleRate(_) => "avail.light.crawl.sample_rate",
+			Escalations => "avail.light.crawl.escalations",
+			ReconstructionCells(_) => "avail.light.crawl.reconstruction_cells",
+			BytesFetched(_) => "avail.light.crawl.bytes_fetched",
+			FastForwardedBlocks(_) => "avail.light.crawl.fast_forwarded",
+			IdleTime(_) => "avail.light.crawl.idle_time",
+			SleepingTime(_) => "avail.light.crawl.sleeping_time",
+			FetchingTime(_) => "avail.light.crawl.fetching_time",
+			DeniedBlocks => "avail.light.crawl.denied_blocks",
+			ZeroRateEscalations => "avail.light.crawl.zero_rate_escalations",
+			IntraBlockRateVariance(_) => "avail.light.crawl.intra_block_rate_variance",
+			ReconciliationDelta(_) => "avail.light.crawl.reconciliation_delta",
+			QueriesPerCell(_) => "avail.light.crawl.queries_per_cell",
 		}
 	}
 }
@@ -37,6 +175,37 @@ impl From<CrawlMetricValue> for Record {
 			CellsSuccessRate(number) => AvgF64(name, number),
 			RowsSuccessRate(number) => AvgF64(name, number),
 			BlockDelay(number) => AvgF64(name, number),
+			AvailableBlocks => SumU64(name, 1),
+			CellsCoverage(number) => AvgF64(name, number),
+			RowsCoverage(number) => AvgF64(name, number),
+			RefetchedCells(number) => SumU64(name, number),
+			Reconstructable(value) => AvgF64(name, if value { 1.0 } else { 0.0 }),
+			RowsDerivedRatio(number) => AvgF64(name, number),
+			DimensionAnomalies => SumU64(name, 1),
+			AvailabilityConfidence(number) => AvgF64(name, number),
+			CellsServedLocally(number) => SumU64(name, number),
+			CellsServedDht(number) => SumU64(name, number),
+			TooOldBlocks => SumU64(name, 1),
+			DuplicateHeaders => SumU64(name, 1),
+			ClockSkew => SumU64(name, 1),
+			RecentGapBlocks(number) => MaxU64(name, number),
+			FetchCacheHits => SumU64(name, 1),
+			FetchCacheMisses => SumU64(name, 1),
+			EmptyPartitions => SumU64(name, 1),
+			DimensionChanges => SumU64(name, 1),
+			SampleRate(number) => AvgF64(name, number),
+			Escalations => SumU64(name, 1),
+			ReconstructionCells(number) => SumU64(name, number),
+			BytesFetched(number) => SumU64(name, number),
+			FastForwardedBlocks(number) => SumU64(name, number),
+			IdleTime(number) => SumU64(name, number),
+			SleepingTime(number) => SumU64(name, number),
+			FetchingTime(number) => SumU64(name, number),
+			DeniedBlocks => SumU64(name, 1),
+			ZeroRateEscalations => SumU64(name, 1),
+			IntraBlockRateVariance(number) => AvgF64(name, number),
+			ReconciliationDelta(number) => AvgF64(name, number),
+			QueriesPerCell(number) => AvgF64(name, number),
 		}
 	}
 }
@@ -49,10 +218,203 @@ pub enum CrawlMode {
 	Both,
 }
 
+/// Order of fetching cells and rows in the `both` mode
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum FetchOrder {
+	/// Cells are fetched first, for the faster sampling signal
+	#[default]
+	CellsFirst,
+	/// Rows are fetched first, for the faster reconstruction
+	RowsFirst,
+}
+
+/// Handling of the headers received in the future, which happens if the clocks are skewed
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClockSkew {
+	/// Receive time is clamped to the current time, so the block is crawled after the full crawl delay
+	#[default]
+	Clamp,
+	/// Block is crawled immediately, without the crawl delay
+	Immediate,
+}
+
+/// Order of crawling the block and forwarding it to the block verified subscribers
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForwardOrder {
+	/// Block is forwarded once it is crawled, so the crawl result is sent before the block
+	#[default]
+	CrawlThenSend,
+	/// Block is forwarded before the crawl delay and the crawling, for the lower forwarding latency
+	SendThenCrawl,
+}
+
+/// Handling of the first block received after the startup, which has no previous block to derive the metrics from
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum FirstBlock {
+	/// Block is crawled, and it initializes the accumulators without recording the metrics derived from the previous block
+	#[default]
+	Initialize,
+	/// Block is crawled, and the metrics derived from the time since the startup are recorded as well
+	Record,
+	/// Block only initializes the accumulators, and it is not crawled, e.g. while the peers are discovered after the startup
+	Skip,
+}
+
+/// Action on the consecutive blocks with the zero success rate, which indicates the DHT failure or misconfiguration
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ZeroRateAction {
+	/// Error is logged and the escalation is recorded, and the crawl continues
+	#[default]
+	Log,
+	/// Same as `log`, and the crawl loop is stopped with the error, so the host application can exit
+	Exit,
+}
+
 pub enum OutputEvent {
 	RecordBlockDelay(f64),
 	RecordCellSuccessRate(f64),
 	RecordRowsSuccessRate(f64),
+	CountAvailableBlocks,
+	PartitionUpdate(Vec<Partition>),
+	RecordCoverage(Coverage),
+	CountRefetchedCells(usize),
+	RecordReconstructable(bool),
+	RecordRowsDerivedRatio(f64),
+	CountDimensionAnomalies,
+	RecordAvailabilityConfidence(f64),
+	CountServedCells { local: usize, dht: usize },
+	CountTooOldBlocks,
+	RecordQueriesPerCell(f64),
+	CountDuplicateHeaders,
+	CountClockSkew,
+	RecordRecentGapBlocks(usize),
+	CountFetchCacheHit,
+	CountFetchCacheMiss,
+	CountEmptyPartitions,
+	CountDimensionChanges,
+	RecordSampleRate(f64),
+	CountEscalations,
+	CountReconstructionCells(usize),
+	RecordBytesFetched(usize),
+	CountFastForwardedBlocks(usize),
+	RecordLoopTimes(LoopTimes),
+	CountDeniedBlocks,
+	CountZeroRateEscalations,
+	RecordIntraBlockRateVariance(f64),
+	RecordReconciliationDelta(f64),
+}
+
+impl OutputEvent {
+	/// Returns the name of the event variant, used in the logs
+	fn name(&self) -> &'static str {
+		match self {
+			OutputEvent::RecordBlockDelay(..) => "RecordBlockDelay",
+			OutputEvent::RecordCellSuccessRate(..) => "RecordCellSuccessRate",
+			OutputEvent::RecordRowsSuccessRate(..) => "RecordRowsSuccessRate",
+			OutputEvent::CountAvailableBlocks => "CountAvailableBlocks",
+			OutputEvent::PartitionUpdate(..) => "PartitionUpdate",
+			OutputEvent::RecordCoverage(..) => "RecordCoverage",
+			OutputEvent::CountRefetchedCells(..) => "CountRefetchedCells",
+			OutputEvent::RecordReconstructable(..) => "RecordReconstructable",
+			OutputEvent::RecordRowsDerivedRatio(..) => "RecordRowsDerivedRatio",
+			OutputEvent::CountDimensionAnomalies => "CountDimensionAnomalies",
+			OutputEvent::RecordAvailabilityConfidence(..) => "RecordAvailabilityConfidence",
+			OutputEvent::CountServedCells { .. } => "CountServedCells",
+			OutputEvent::CountTooOldBlocks => "CountTooOldBlocks",
+			OutputEvent::RecordQueriesPerCell(..) => "RecordQueriesPerCell",
+			OutputEvent::CountDuplicateHeaders => "CountDuplicateHeaders",
+			OutputEvent::CountClockSkew => "CountClockSkew",
+			OutputEvent::RecordRecentGapBlocks(..) => "RecordRecentGapBlocks",
+			OutputEvent::CountFetchCacheHit => "CountFetchCacheHit",
+			OutputEvent::CountFetchCacheMiss => "CountFetchCacheMiss",
+			OutputEvent::CountEmptyPartitions => "CountEmptyPartitions",
+			OutputEvent::CountDimensionChanges => "CountDimensionChanges",
+			OutputEvent::RecordSampleRate(..) => "RecordSampleRate",
+			OutputEvent::CountEscalations => "CountEscalations",
+			OutputEvent::CountReconstructionCells(..) => "CountReconstructionCells",
+			OutputEvent::RecordBytesFetched(..) => "RecordBytesFetched",
+			OutputEvent::CountFastForwardedBlocks(..) => "CountFastForwardedBlocks",
+			OutputEvent::RecordLoopTimes(..) => "RecordLoopTimes",
+			OutputEvent::CountDeniedBlocks => "CountDeniedBlocks",
+			OutputEvent::CountZeroRateEscalations => "CountZeroRateEscalations",
+			OutputEvent::RecordIntraBlockRateVariance(..) => "RecordIntraBlockRateVariance",
+			OutputEvent::RecordReconciliationDelta(..) => "RecordReconciliationDelta",
+		}
+	}
+}
+
+#[async_trait]
+#[automock]
+pub trait Client {
+	async fn fetch_cells_from_dht(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+	) -> (Vec<Cell>, Vec<Position>, Vec<(Option<PeerId>, bool)>);
+	async fn fetch_rows_from_dht(
+		&self,
+		block_number: u32,
+		dimensions: Dimensions,
+		row_indexes: &[u32],
+	) -> Vec<Option<Vec<u8>>>;
+}
+
+#[async_trait]
+impl Client for p2p::Client {
+	async fn fetch_cells_from_dht(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+	) -> (Vec<Cell>, Vec<Position>, Vec<(Option<PeerId>, bool)>) {
+		self.fetch_cells_from_dht_with_peers(block_number, positions)
+			.await
+	}
+
+	async fn fetch_rows_from_dht(
+		&self,
+		block_number: u32,
+		dimensions: Dimensions,
+		row_indexes: &[u32],
+	) -> Vec<Option<Vec<u8>>> {
+		p2p::Client::fetch_rows_from_dht(self, block_number, dimensions, row_indexes).await
+	}
+}
+
+/// Hook invoked with the outcome of each crawled block and the crawl mode, so the host application can
+/// compute and record its own metrics from the raw fetch counts.
+///
+/// Hook is invoked on the crawl loop task, after the availability is evaluated and before the result is sent,
+/// so it can be called from different threads, and it should return quickly without blocking the crawl loop.
+pub type BlockHook = Arc<dyn Fn(&CrawlResult, CrawlMode) + Send + Sync>;
+
+/// Extension points of the crawl loop, for the host applications and the sampling experiments
+#[derive(Clone, Default)]
+pub struct CrawlExtensions {
+	/// Invoked with the outcome of each crawled block (see [`BlockHook`])
+	pub hook: Option<BlockHook>,
+	/// Sampling strategy used instead of the configured `crawl_sampling`, which is the partition sampling by default.
+	/// Samples of the custom strategy are computed for each block and they are not checkpointed,
+	/// since the strategy is not expected to be deterministic.
+	pub sampling: Option<Arc<dyn SamplingStrategy + Send + Sync>>,
+}
+
+/// Source of the headers of the blocks crawled by hash
+#[async_trait]
+#[automock]
+pub trait HeaderClient {
+	async fn get_header_by_hash(&self, block_hash: H256) -> Result<AvailHeader>;
+}
+
+#[async_trait]
+impl<T: Database + Sync> HeaderClient for rpc::Client<T> {
+	async fn get_header_by_hash(&self, block_hash: H256) -> Result<AvailHeader> {
+		rpc::Client::get_header_by_hash(self, block_hash).await
+	}
 }
 
 impl Value for CrawlMetricValue {
@@ -63,107 +425,4939 @@ impl Value for CrawlMetricValue {
 	}
 }
 
-pub async fn run(
-	mut message_rx: broadcast::Receiver<rpc::OutputEvent>,
-	network_client: Client,
-	delay: u64,
-	mode: CrawlMode,
-	partition: Partition,
-	block_sender: broadcast::Sender<BlockVerified>,
-	event_sender: UnboundedSender<OutputEvent>,
-) {
-	info!("Starting crawl client...");
+pub const ENTIRE_BLOCK: Partition = Partition {
+	number: 1,
+	fraction: 1,
+};
 
-	let delay = Delay(Some(Duration::from_secs(delay)));
+/// Every n-th row of the extended matrix is crawled, which selects the original data rows
+const ROWS_STEP: usize = 2;
 
-	while let Ok(rpc::OutputEvent::HeaderUpdate {
-		header,
-		received_at,
-	}) = message_rx.recv().await
-	{
-		let block = match types::BlockVerified::try_from((header, None)) {
-			Ok(block) => block,
-			Err(error) => {
-				error!("Header is not valid: {error}");
-				continue;
-			},
+/// Recommended minimum capacity of the channel of the forwarded blocks, see [`block_channel`]
+pub const MIN_BLOCK_CHANNEL_CAPACITY: usize = 16;
+
+/// Fractions of the extended matrix cells and rows crawled, if crawled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coverage {
+	pub cells: Option<f64>,
+	pub rows: Option<f64>,
+}
+
+/// Time spent by the crawl loop on a received header, from the end of the previous header handling
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoopTimes {
+	/// Waiting for the header
+	pub idle: Duration,
+	/// Sleeping for the crawl delays
+	pub sleeping: Duration,
+	/// Crawling the due blocks, without the sleeps
+	pub fetching: Duration,
+}
+
+/// Number of requested and fetched cells or rows
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct CrawlStats {
+	pub total: usize,
+	pub fetched: usize,
+}
+
+impl CrawlStats {
+	pub fn success_rate(&self) -> f64 {
+		self.fetched as f64 / self.total as f64
+	}
+
+	/// Confidence in percents that the block with `matrix_cells` cells of the extended matrix is available,
+	/// given the requested and fetched cells (see [`availability_confidence`])
+	pub fn confidence(&self, matrix_cells: usize) -> f64 {
+		availability_confidence(self.total, self.fetched, matrix_cells)
+	}
+}
+
+/// Outcome of the block crawling
+#[derive(Clone, Debug, Serialize)]
+pub struct CrawlResult {
+	pub block_number: u32,
+	/// Block hash, which identifies the block unambiguously across forks
+	pub block_hash: H256,
+	/// Cells stats, if cells are crawled
+	pub cells: Option<CrawlStats>,
+	/// Rows stats, if rows are crawled
+	pub rows: Option<CrawlStats>,
+	/// Number of failed cells fetched on re-fetch attempts, included in the cells stats
+	pub refetched_cells: usize,
+	/// Whether the block can be reconstructed from the fetched cells, if reconstruction is enabled
+	pub reconstructable: Option<bool>,
+	/// Number of requested rows which are not fetched, but can be derived from the fetched cells,
+	/// if reconstruction is enabled. Derived rows are not included in the rows stats.
+	pub derived_rows: usize,
+	pub elapsed: Duration,
+	/// Wall-clock time the header is received at, since the header doesn't carry the block timestamp
+	pub received_at: SystemTime,
+	/// Wall-clock time the crawling is completed at
+	pub crawled_at: SystemTime,
+	/// Availability verdict, based on configured [`AvailabilityCriteria`]
+	pub available: bool,
+}
+
+impl CrawlResult {
+	/// Returns `true` if cells or rows are requested, and none of them are fetched
+	pub fn is_zero_rate(&self) -> bool {
+		let stats = [self.cells, self.rows].into_iter().flatten();
+		let (total, fetched) = stats.fold((0, 0), |(total, fetched), stats| {
+			(total + stats.total, fetched + stats.fetched)
+		});
+		total > 0 && fetched == 0
+	}
+
+	/// Returns the fraction of the available rows which are derived from the fetched cells,
+	/// instead of being fetched directly. Returns `None` if rows are not crawled or none are available.
+	pub fn rows_derived_ratio(&self) -> Option<f64> {
+		let rows = self.rows?;
+		let available = rows.fetched + self.derived_rows;
+		(available > 0).then(|| self.derived_rows as f64 / available as f64)
+	}
+}
+
+/// Natural logarithm of the binomial coefficient
+fn ln_choose(n: usize, k: usize) -> f64 {
+	(1..=k).map(|i| ((n - k + i) as f64 / i as f64).ln()).sum()
+}
+
+/// Returns the confidence in percents that the block is available, given that `successful`
+/// out of `sampled` distinct random positions of the extended matrix with `total` cells are fetched.
+///
+/// Block is considered unavailable if at most half of the extended matrix cells are available,
+/// since the 2x extension cannot be decoded from less. Confidence is the probability of fetching
+/// fewer than `successful` cells if the block is unavailable, i.e. `1 - P(X >= successful)`,
+/// where `X` follows the hypergeometric distribution of sampling `sampled` out of `total` cells,
+/// of which only half are available. If all sampled cells are fetched, confidence is
+/// `1 - (A / N) * ((A - 1) / (N - 1)) * ...` for `A` available out of `N` cells,
+/// which approaches the light client confidence `1 - 0.5^n` for the large matrices.
+/// Cells of the partition are not random positions, so the confidence applies to the random sampling only.
+pub fn availability_confidence(sampled: usize, successful: usize, total: usize) -> f64 {
+	let sampled = sampled.min(total);
+	let successful = successful.min(sampled);
+	let available = total / 2;
+	let unavailable = total - available;
+
+	// Range of the possible number of fetched cells if only half of the cells are available
+	let min = sampled.saturating_sub(unavailable);
+	let max = sampled.min(available);
+	let from = successful.max(min);
+	if from > max {
+		return 100.0;
+	}
+
+	let ln_probability = ln_choose(available, from) + ln_choose(unavailable, sampled - from)
+		- ln_choose(total, sampled);
+	let mut probability = ln_probability.exp();
+	let mut tail = probability;
+	for x in from..max {
+		let (x, sampled) = (x as f64, sampled as f64);
+		probability *= (available as f64 - x) * (sampled - x)
+			/ ((x + 1.0) * (unavailable as f64 - sampled + x + 1.0));
+		tail += probability;
+	}
+	100.0 * (1.0 - tail.min(1.0))
+}
+
+/// Counts requested rows which are present in the fetched rows, and satisfy the `is_fetched` predicate.
+/// Fetched rows are indexed by the row index, so the count doesn't depend on
+/// the order of requested rows, nor on the rows which were not requested.
+pub fn count_fetched_rows(
+	row_indexes: &[u32],
+	fetched_rows: &[Option<Vec<u8>>],
+	is_fetched: impl Fn(&[u8]) -> bool,
+) -> usize {
+	row_indexes
+		.iter()
+		.filter_map(|&index| fetched_rows.get(index as usize)?.as_deref())
+		.filter(|row| is_fetched(row))
+		.count()
+}
+
+/// Returns indexes of the requested rows which are not present in the fetched rows, or don't satisfy the `is_fetched` predicate
+fn unfetched_rows(
+	row_indexes: &[u32],
+	fetched_rows: &[Option<Vec<u8>>],
+	is_fetched: impl Fn(&[u8]) -> bool,
+) -> Vec<u32> {
+	row_indexes
+		.iter()
+		.copied()
+		.filter(|&index| {
+			let row = fetched_rows.get(index as usize).and_then(Option::as_deref);
+			!row.is_some_and(&is_fetched)
+		})
+		.collect()
+}
+
+/// Merges the re-fetched rows which satisfy the `is_fetched` predicate into the fetched rows, both indexed by the row index
+fn merge_rows(
+	fetched_rows: &mut Vec<Option<Vec<u8>>>,
+	refetched_rows: Vec<Option<Vec<u8>>>,
+	is_fetched: impl Fn(&[u8]) -> bool,
+) {
+	for (index, row) in refetched_rows.into_iter().enumerate() {
+		let Some(row) = row.filter(|row| is_fetched(row)) else {
+			continue;
 		};
+		if fetched_rows.len() <= index {
+			fetched_rows.resize(index + 1, None);
+		}
+		fetched_rows[index] = Some(row);
+	}
+}
+
+/// Returns predicate which accepts rows of the expected length, which is the matrix width times the chunk size
+pub fn is_valid_row(dimensions: Dimensions) -> impl Fn(&[u8]) -> bool {
+	let length = dimensions.width::<usize>() * CHUNK_SIZE;
+	move |row| row.len() == length
+}
+
+/// Splits positions into the random sample of the given fraction, and the remaining positions.
+/// Order of the positions is kept. All positions are sampled if fraction is not set.
+fn escalation_sample(
+	positions: Vec<Position>,
+	fraction: Option<f64>,
+) -> (Vec<Position>, Vec<Position>) {
+	let Some(fraction) = fraction else {
+		return (positions, vec![]);
+	};
+	let amount = (positions.len() as f64 * fraction.clamp(0.0, 1.0)).ceil() as usize;
+	let mut sampled = vec![false; positions.len()];
+	for index in index::sample(&mut utils::rng(), positions.len(), amount) {
+		sampled[index] = true;
+	}
+	let (sample, remaining): (Vec<_>, Vec<_>) = positions
+		.into_iter()
+		.zip(sampled)
+		.partition(|(_, sampled)| *sampled);
+	let into_positions = |pairs: Vec<_>| pairs.into_iter().map(|(position, _)| position).collect();
+	(into_positions(sample), into_positions(remaining))
+}
+
+/// Returns `true` if extended rows are not twice the original rows, or if there is not
+/// a commitment for each extended row. Crawling original rows only relies on the 2x extension.
+fn is_dimension_anomaly(dimensions: Dimensions, commitments: usize) -> bool {
+	let extended_rows = dimensions.extended_rows();
+	extended_rows != dimensions.height::<u32>() * ROWS_STEP as u32
+		|| commitments != extended_rows as usize
+}
+
+/// Returns `true` if the number of the matrix cells grows or shrinks at least by the `factor`,
+/// or `false` if the factor is 0
+fn is_dimension_change(previous: Dimensions, current: Dimensions, factor: f64) -> bool {
+	let (previous, current) = (
+		f64::from(previous.extended_size()),
+		f64::from(current.extended_size()),
+	);
+	factor > 0.0 && previous.max(current) >= factor * previous.min(current)
+}
+
+/// Creates stats with the fetched count clamped to the total,
+/// so a fetch layer returning more items than requested cannot result in success rate above 1.0.
+fn clamped_stats(block_number: u32, kind: &str, total: usize, fetched: usize) -> CrawlStats {
+	if fetched > total {
+		warn!(
+			block_number,
+			total, fetched, "Fetched more {kind} than requested, clamping to the requested count"
+		);
+	}
+	CrawlStats {
+		total,
+		fetched: fetched.min(total),
+	}
+}
+
+/// Checks if the block can be reconstructed from the fetched cells.
+/// Reconstruction is attempted only if every column has enough fetched cells,
+/// which is at least the number of original rows.
+fn is_reconstructable(block_number: u32, dimensions: Dimensions, cells: &[Cell]) -> bool {
+	let mut column_cells = HashMap::<u16, usize>::new();
+	for cell in cells {
+		*column_cells.entry(cell.position.col).or_default() += 1;
+	}
+
+	let height: usize = dimensions.height();
+	let cols = dimensions.cols().get();
+	let insufficient = (0..cols)
+		.filter(|col| column_cells.get(col).copied().unwrap_or_default() < height)
+		.count();
+	if insufficient > 0 {
+		info!(
+			block_number,
+			"Skipping block reconstruction, {insufficient} of {cols} columns have insufficient cells"
+		);
+		return false;
+	}
+
+	match reconstruct_columns(dimensions, cells) {
+		Ok(columns) => columns.len() == cols as usize,
+		Err(error) => {
+			warn!(block_number, "Failed to reconstruct block: {error}");
+			false
+		},
+	}
+}
+
+/// Returns positions of the cells missing for the reconstruction, which are the first cells
+/// not fetched in each column with fewer fetched cells than the number of original rows.
+fn reconstruction_positions(dimensions: Dimensions, cells: &[Cell]) -> Vec<Position> {
+	let fetched = cells
+		.iter()
+		.map(|cell| cell.position)
+		.collect::<HashSet<_>>();
+	let height: usize = dimensions.height();
+	let mut positions = vec![];
+	for col in 0..dimensions.cols().get() {
+		let column = (0..dimensions.extended_rows()).map(|row| Position { row, col });
+		let (fetched, missing): (Vec<_>, Vec<_>) =
+			column.partition(|position| fetched.contains(position));
+		let needed = height.saturating_sub(fetched.len());
+		positions.extend(missing.into_iter().take(needed));
+	}
+	positions
+}
+
+/// Rounds the value to the given number of decimal places, if precision is set.
+fn round(value: f64, precision: Option<u8>) -> f64 {
+	let Some(precision) = precision else {
+		return value;
+	};
+	let factor = 10f64.powi(precision.into());
+	(value * factor).round() / factor
+}
+
+/// Category of the crawl failures, tracked in the status
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FailureCategory {
+	/// Cells or rows which failed to be fetched
+	Fetch,
+	/// Events and messages which failed to be sent
+	Send,
+	/// Headers which failed to be verified
+	Header,
+	/// Metrics which failed to be flushed
+	Metrics,
+}
+
+/// Last failure of the category
+#[derive(Clone, Debug, PartialEq)]
+pub struct LastError {
+	pub message: String,
+	pub timestamp: SystemTime,
+}
+
+/// Block which was being crawled when the crawl loop was shut down
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FinalBlock {
+	pub block_number: u32,
+	/// Whether crawling finished within the shutdown drain, or it was aborted
+	pub completed: bool,
+}
+
+/// Crawl client status, shared with the host application
+#[derive(Clone, Debug)]
+pub struct CrawlStatus {
+	/// Records served by peers, which can be used to down-rank unreliable peers
+	pub peers: PeerStats,
+	/// Number of the block which is being crawled, if any
+	pub crawling: Option<u32>,
+	/// Block which was being crawled on shutdown, if any
+	pub final_block: Option<FinalBlock>,
+	/// Last error of each failure category, for an at-a-glance health view
+	pub last_errors: HashMap<FailureCategory, LastError>,
+	/// Extended matrix rows with the most failed cells, with the old failures decayed
+	pub failing_rows: Offenders<u32>,
+	/// Recently crawled blocks, summarized by [`summary::Summary`]
+	pub history: History,
+	/// Cells recently fetched for the blocks, reused when the block is crawled again
+	pub fetch_cache: FetchCache,
+	/// Fetch counts of the extended matrix regions, accumulated over the crawled blocks
+	pub heatmap: Heatmap,
+	/// Number of failed metrics flushes since the start
+	pub metrics_failures: u64,
+}
+
+impl CrawlStatus {
+	pub fn new(config: &CrawlConfig) -> Self {
+		Self {
+			peers: PeerStats::new(config.crawl_peer_stats_limit),
+			crawling: None,
+			final_block: None,
+			last_errors: HashMap::new(),
+			failing_rows: Offenders::new(
+				config.offenders.crawl_offenders_limit,
+				config.offenders.crawl_offenders_decay,
+			),
+			history: History::new(config.crawl_summary_history),
+			fetch_cache: FetchCache::new(
+				config.fetch_cache.crawl_fetch_cache_cells,
+				Duration::from_secs(config.fetch_cache.crawl_fetch_cache_ttl),
+			),
+			heatmap: Heatmap::new(config.heatmap.crawl_heatmap_bucket_size),
+			metrics_failures: 0,
+		}
+	}
 
-		let Some(extension) = &block.extension else {
-			info!("Skipping block without header extension");
+	pub fn record_error(&mut self, category: FailureCategory, message: String) {
+		let timestamp = SystemTime::now();
+		self.last_errors
+			.insert(category, LastError { message, timestamp });
+	}
+}
+
+/// Removes and returns the pending headers which are delayed by `blocks` blocks behind the `head` block,
+/// in the order they are received. All pending headers are returned if the delay in blocks is not set.
+fn due_headers(
+	pending: &mut VecDeque<(AvailHeader, Instant)>,
+	head: u32,
+	blocks: Option<u32>,
+) -> Vec<(AvailHeader, Instant)> {
+	let Some(blocks) = blocks else {
+		return pending.drain(..).collect();
+	};
+	let (due, delayed): (VecDeque<_>, _) = pending
+		.drain(..)
+		.partition(|(header, _)| header.number.saturating_add(blocks) <= head);
+	*pending = delayed;
+	due.into()
+}
+
+/// Returns `true` if the hash is one of the recently seen hashes, otherwise it is recorded,
+/// and the oldest hashes are evicted to keep at most `capacity` hashes.
+fn is_duplicate(seen: &mut VecDeque<H256>, hash: H256, capacity: usize) -> bool {
+	if seen.contains(&hash) {
+		return true;
+	}
+	seen.push_back(hash);
+	while seen.len() > capacity {
+		seen.pop_front();
+	}
+	false
+}
+
+/// Logs the error, and records it as the last error of the category
+fn log_failure(status: &RwLock<CrawlStatus>, category: FailureCategory, message: String) {
+	error!("{message}");
+	let mut status = status.write().expect("Lock acquired");
+	status.record_error(category, message);
+}
+
+/// Sends the event to the host application, and logs the failure if the receiver is dropped
+fn send_event(
+	event_sender: &UnboundedSender<OutputEvent>,
+	status: &RwLock<CrawlStatus>,
+	event: OutputEvent,
+) {
+	let name = event.name();
+	if let Err(error) = event_sender.send(event) {
+		let message = format!("Failed to send {name} event: {error}");
+		log_failure(status, FailureCategory::Send, message);
+	}
+}
+
+/// Formats crawled partitions as configured (e.g. `3/20` or `3-5/20`)
+pub fn format_partitions(partitions: &[Partition]) -> String {
+	block_matrix_partition_format::range::format(partitions)
+		.unwrap_or_else(|_| format!("{partitions:?}"))
+}
+
+/// Number of the issued DHT queries, and of the fetched cells served from the local store and from the DHT peers.
+/// Fetch layer issues a DHT query for each requested cell.
+#[derive(Clone, Copy, Debug, Default)]
+struct FetchCounts {
+	queries: usize,
+	local: usize,
+	dht: usize,
+	bytes: usize,
+}
+
+/// Returns the payload size of the fetched cells, in bytes
+fn cells_payload_size(cells: &[Cell]) -> usize {
+	cells.iter().map(|cell| cell.content.len()).sum()
+}
+
+/// Returns the payload size of the fetched rows, in bytes
+fn rows_payload_size(rows: &[Option<Vec<u8>>]) -> usize {
+	rows.iter().flatten().map(Vec::len).sum()
+}
+
+/// Returns the number of the DHT queries issued per fetched cell, or `None` if no cells are fetched
+fn queries_per_cell(queries: usize, fetched: usize) -> Option<f64> {
+	(fetched > 0).then(|| queries as f64 / fetched as f64)
+}
+
+/// Records peers which served the cells, and counts the valid cells by the source
+fn record_peers(
+	status: &RwLock<CrawlStatus>,
+	peers: Vec<(Option<PeerId>, bool)>,
+	counts: &mut FetchCounts,
+) {
+	let mut status = status.write().expect("Lock acquired");
+	for (peer, success) in peers {
+		let Some(peer) = peer else {
+			counts.local += usize::from(success);
 			continue;
 		};
+		status.peers.record(peer, success);
+		counts.dht += usize::from(success);
+	}
+}
 
-		if let Some(seconds) = delay.sleep_duration(received_at) {
-			info!("Sleeping for {seconds:?} seconds");
-			if let Err(error) =
-				event_sender.send(OutputEvent::RecordBlockDelay(seconds.as_secs_f64()))
-			{
-				error!("Failed to send RecordBlockDelay event: {error}");
-			}
-			tokio::time::sleep(seconds).await;
+fn warn_chaos(config: &CrawlConfig) {
+	if let Some(fraction) = config.chaos.crawl_chaos_drop_fraction {
+		warn!(
+			fraction,
+			"CHAOS TESTING IS ENABLED: Fetched cells and rows are dropped, availability is NOT REAL"
+		);
+	}
+}
+
+/// Returns the number of the fetched cells or rows dropped for chaos testing
+fn chaos_dropped(fetched: usize, fraction: Option<f64>) -> usize {
+	fraction.map_or(0, |fraction| {
+		(fetched as f64 * fraction.clamp(0.0, 1.0)).round() as usize
+	})
+}
+
+/// Fetches the cells in batches, and checkpoints the positions fetched in each batch,
+/// so the crawl interrupted between the batches can be resumed. Cells are fetched at once without a checkpoint.
+async fn fetch_cells(
+	network_client: &impl Client,
+	db: &impl Database,
+	block_number: u32,
+	positions: &[Position],
+	checkpoint: &mut Option<(CrawlCheckpoint, usize)>,
+	status: &RwLock<CrawlStatus>,
+	counts: &mut FetchCounts,
+) -> (Vec<Cell>, Vec<Position>) {
+	let Some((checkpoint, batch_size)) = checkpoint else {
+		let (fetched, unfetched, peers) = network_client
+			.fetch_cells_from_dht(block_number, positions)
+			.await;
+		counts.queries += positions.len();
+		counts.bytes += cells_payload_size(&fetched);
+		record_peers(status, peers, counts);
+		return (fetched, unfetched);
+	};
+
+	let mut fetched = vec![];
+	let mut unfetched = vec![];
+	for batch in positions.chunks((*batch_size).max(1)) {
+		let (batch_fetched, batch_unfetched, peers) = network_client
+			.fetch_cells_from_dht(block_number, batch)
+			.await;
+		counts.queries += batch.len();
+		counts.bytes += cells_payload_size(&batch_fetched);
+		record_peers(status, peers, counts);
+
+		// Only the batch is stored, so the checkpoint cost doesn't grow with the fetched cells
+		let positions = batch_fetched.iter().map(|cell| cell.position);
+		let positions = positions.map(|Position { row, col }| (row, col)).collect();
+		db.put(CrawlCheckpointBatchKey(checkpoint.batches), positions);
+		checkpoint.batches += 1;
+		db.put(CrawlCheckpointKey, checkpoint.clone());
+		fetched.extend(batch_fetched);
+		unfetched.extend(batch_unfetched);
+	}
+	(fetched, unfetched)
+}
+
+/// Returns the positions of the cells fetched in the checkpointed batches
+fn checkpointed_positions(db: &impl Database, checkpoint: &CrawlCheckpoint) -> HashSet<Position> {
+	(0..checkpoint.batches)
+		.filter_map(|batch| db.get(CrawlCheckpointBatchKey(batch)))
+		.flatten()
+		.map(|(row, col)| Position { row, col })
+		.collect()
+}
+
+/// Deletes the checkpoint with its batches
+fn delete_checkpoint(db: &impl Database, checkpoint: &CrawlCheckpoint) {
+	for batch in 0..checkpoint.batches {
+		db.delete(CrawlCheckpointBatchKey(batch));
+	}
+	db.delete(CrawlCheckpointKey);
+}
+
+/// Assigns the ring partitions if the ring node is configured,
+/// and sets the entire block partition if no partition is configured
+fn default_partition(config: &mut CrawlConfig) {
+	if let Some(node) = &config.ring.crawl_ring_node {
+		let nodes = config.ring.crawl_ring_nodes.iter().map(String::as_str);
+		let ring = Ring::new(nodes.chain([node.as_str()]));
+		let partitions = ring.partitions(node, config.ring.crawl_ring_fraction);
+		info!(
+			node,
+			nodes = config.ring.crawl_ring_nodes.len(),
+			partition = format_partitions(&partitions),
+			"Assigned block matrix partitions by the ring"
+		);
+		if partitions.is_empty() {
+			warn!(node, "No block matrix partitions are assigned by the ring");
 		}
-		let block_number = block.block_num;
-		info!(block_number, "Crawling block...");
+		config.crawl_block_matrix_partition = partitions;
+	}
+	if config.crawl_block_matrix_partition.is_empty() {
+		info!("No block matrix partition is configured, crawling the entire block");
+		config.crawl_block_matrix_partition = vec![ENTIRE_BLOCK];
+	}
+}
 
-		let start = Instant::now();
+fn log_coverage(config: &CrawlConfig) {
+	let Coverage { cells, rows } = config.coverage();
+	let partition = format_partitions(&config.crawl_block_matrix_partition);
+	info!(
+		partition,
+		cells_coverage = cells,
+		rows_coverage = rows,
+		"Crawling {:?} block matrix coverage",
+		config.crawl_block_mode
+	);
+}
 
-		if matches!(mode, CrawlMode::Cells | CrawlMode::Both) {
-			let positions = extension
-				.dimensions
-				.iter_extended_partition_positions(&partition)
-				.collect::<Vec<_>>();
+/// Creates the channel for the blocks forwarded by the crawl client to the block verified subscribers.
+///
+/// Blocks are forwarded without waiting for the subscribers, so a subscriber which lags behind by more
+/// than the channel capacity loses the oldest blocks, and it receives [`broadcast::error::RecvError::Lagged`]
+/// with the number of the lost blocks instead. Capacity of the broadcast sender cannot be read afterwards,
+/// so it is checked here: zero capacity, which is not supported by the broadcast channel, is raised to 1,
+/// and a warning is logged if the capacity is below [`MIN_BLOCK_CHANNEL_CAPACITY`].
+pub fn block_channel(
+	capacity: usize,
+) -> (
+	broadcast::Sender<BlockVerified>,
+	broadcast::Receiver<BlockVerified>,
+) {
+	if capacity < MIN_BLOCK_CHANNEL_CAPACITY {
+		warn!(
+			capacity,
+			minimum = MIN_BLOCK_CHANNEL_CAPACITY,
+			"Block channel capacity is below the recommended minimum, lagging subscribers will lose blocks"
+		);
+	}
+	broadcast::channel(capacity.max(1))
+}
 
-			let total = positions.len();
-			let fetched = network_client
-				.fetch_cells_from_dht(block_number, &positions)
-				.await
-				.0
-				.len();
+/// Channels of the crawl loop
+pub struct CrawlChannels {
+	/// Headers received from the RPC client
+	pub rpc_event_receiver: broadcast::Receiver<rpc::OutputEvent>,
+	/// Configuration changes, applied on the next block
+	pub config_receiver: watch::Receiver<CrawlConfig>,
+	pub block_sender: broadcast::Sender<BlockVerified>,
+	pub result_sender: broadcast::Sender<CrawlResult>,
+	pub event_sender: UnboundedSender<OutputEvent>,
+}
 
-			let success_rate = fetched as f64 / total as f64;
-			let partition = format!("{}/{}", partition.number, partition.fraction);
-			info!(
-				block_number,
-				partition, success_rate, total, fetched, "Fetched block cells",
-			);
+/// Configuration, clients and channels the block is crawled with
+pub struct CrawlContext<'a, D, N, C> {
+	pub config: &'a CrawlConfig,
+	pub db: &'a D,
+	pub network_client: &'a N,
+	pub block_sender: &'a broadcast::Sender<BlockVerified>,
+	pub result_sender: &'a broadcast::Sender<CrawlResult>,
+	pub event_sender: &'a UnboundedSender<OutputEvent>,
+	pub status: &'a RwLock<CrawlStatus>,
+	pub clock: &'a C,
+	/// Custom sampling strategy (see [`CrawlExtensions::sampling`])
+	pub sampling: Option<&'a (dyn SamplingStrategy + Send + Sync)>,
+}
+
+/// Runs crawl client.
+///
+/// Blocks are forwarded to the block sender of the `channels` without waiting for the subscribers,
+/// so the channel should be created with enough capacity, e.g. with [`block_channel`].
+///
+/// Configuration changes received on the config receiver of the `channels` are applied on the next block,
+/// so crawling of a block is never done with a mix of configurations.
+/// Partition change is reported with [`OutputEvent::PartitionUpdate`] before the block is crawled,
+/// so the metrics of the previous partition can be flushed separately.
+/// All sleeps and the crawl duration measurement are done using the given `clock`.
+/// Hook and the custom sampling strategy are plugged in with the `extensions` (see [`CrawlExtensions`]).
+/// If checkpoints are enabled, block crawl interrupted by the restart is resumed from the checkpoint
+/// stored in the `db`, before the received headers are crawled.
+/// Returns the error if the crawl loop is stopped by the [`ZeroRateAction::Exit`], and `Ok(())`
+/// once the RPC messages sender is dropped.
+pub async fn run(
+	db: impl Database,
+	channels: CrawlChannels,
+	network_client: impl Client,
+	status: Arc<RwLock<CrawlStatus>>,
+	extensions: CrawlExtensions,
+	clock: impl Clock,
+) -> Result<(), String> {
+	info!("Starting crawl client...");
+
+	let CrawlChannels {
+		rpc_event_receiver: mut message_rx,
+		mut config_receiver,
+		block_sender,
+		result_sender,
+		event_sender,
+	} = channels;
+
+	let mut config = config_receiver.borrow_and_update().clone();
+	default_partition(&mut config);
+	log_coverage(&config);
+	warn_chaos(&config);
 
-			if let Err(error) = event_sender.send(OutputEvent::RecordCellSuccessRate(success_rate))
+	let mut crawled_blocks = CrawledBlocks::new(config.gap_scan.crawl_gap_scan_window);
+	let mut zero_rate_blocks = 0;
+	let mut ordered_blocks = OrderedBlocks::new(config.forward.crawl_ordered_forward_buffer);
+	let mut sample_cache = SampleCache::new();
+	let checkpoint = db
+		.get(CrawlCheckpointKey)
+		.filter(|_| config.crawl_checkpoint_batch_size.is_some());
+	if let Some(CrawlCheckpoint { header, batches }) = checkpoint {
+		info!(
+			block_number = header.number,
+			batches, "Resuming interrupted block crawl"
+		);
+		// Block was already delayed before the interruption
+		let received_at = delayed_since(&config, &clock);
+		let span = block_span(header.number, &config);
+		let ctx = CrawlContext {
+			config: &config,
+			db: &db,
+			network_client: &network_client,
+			block_sender: &block_sender,
+			result_sender: &result_sender,
+			event_sender: &event_sender,
+			status: &status,
+			clock: &clock,
+			sampling: extensions.sampling.as_deref(),
+		};
+		let result = crawl_block(
+			header,
+			received_at,
+			&ctx,
+			&mut ordered_blocks,
+			&mut sample_cache,
+			extensions.hook.as_ref(),
+		)
+		.instrument(span)
+		.await;
+		if let Some(result) = result {
+			crawled_blocks.record(result.block_number);
+			if zero_rate_action(
+				&result,
+				&mut zero_rate_blocks,
+				&config,
+				&event_sender,
+				&status,
+			) {
+				return Err(ZERO_RATE_EXIT.to_string());
+			}
+		}
+	}
+
+	let mut seen_hashes = VecDeque::new();
+	let mut pending = VecDeque::new();
+	let mut head = 0;
+	let mut last_dimensions = None;
+	let sleep_tracker = SleepTracker::new(&clock);
+	let mut idle_since = clock.now();
+	let mut stop_reason = None;
+	let mut first_header = true;
+	'headers: while let Ok(rpc::OutputEvent::HeaderUpdate {
+		header,
+		received_at,
+	}) = message_rx.recv().await
+	{
+		let woken_at = clock.now();
+		let is_first = std::mem::replace(&mut first_header, false);
+
+		// Error means that the sender is dropped, so the last configuration is kept
+		if config_receiver.has_changed().unwrap_or(false) {
+			let mut new_config = config_receiver.borrow_and_update().clone();
+			default_partition(&mut new_config);
+			let partition_changed = log_config_changes(&config, &new_config);
+			let peer_stats_limit = new_config.crawl_peer_stats_limit;
+			if peer_stats_limit != config.crawl_peer_stats_limit {
+				let mut status = status.write().expect("Lock acquired");
+				status.peers.set_capacity(peer_stats_limit);
+			}
+			let (limit, decay) = (
+				new_config.offenders.crawl_offenders_limit,
+				new_config.offenders.crawl_offenders_decay,
+			);
+			if limit != config.offenders.crawl_offenders_limit
+				|| decay != config.offenders.crawl_offenders_decay
+			{
+				let mut status = status.write().expect("Lock acquired");
+				status.failing_rows.set_limits(limit, decay);
+			}
+			let history = new_config.crawl_summary_history;
+			if history != config.crawl_summary_history {
+				let mut status = status.write().expect("Lock acquired");
+				status.history.set_capacity(history);
+			}
+			let (cells, ttl) = (
+				new_config.fetch_cache.crawl_fetch_cache_cells,
+				new_config.fetch_cache.crawl_fetch_cache_ttl,
+			);
+			if cells != config.fetch_cache.crawl_fetch_cache_cells
+				|| ttl != config.fetch_cache.crawl_fetch_cache_ttl
 			{
-				error!("Failed to send RecordCellSuccessRate event: {error}");
+				let mut status = status.write().expect("Lock acquired");
+				status
+					.fetch_cache
+					.set_limits(cells, Duration::from_secs(ttl));
+			}
+			let bucket_size = new_config.heatmap.crawl_heatmap_bucket_size;
+			if bucket_size != config.heatmap.crawl_heatmap_bucket_size {
+				let mut status = status.write().expect("Lock acquired");
+				status.heatmap.set_bucket_size(bucket_size);
+			}
+			let max_buffered = new_config.forward.crawl_ordered_forward_buffer;
+			if max_buffered != config.forward.crawl_ordered_forward_buffer {
+				let blocks = ordered_blocks.set_max_buffered(max_buffered);
+				forward_blocks(blocks, &block_sender, &status);
+			}
+			if new_config.gap_scan.crawl_gap_scan_window != config.gap_scan.crawl_gap_scan_window {
+				crawled_blocks.set_window(new_config.gap_scan.crawl_gap_scan_window);
+			}
+			config = new_config;
+			log_coverage(&config);
+			warn_chaos(&config);
+
+			if partition_changed {
+				let partitions = config.crawl_block_matrix_partition.clone();
+				send_event(
+					&event_sender,
+					&status,
+					OutputEvent::PartitionUpdate(partitions),
+				);
 			}
 		}
 
-		if matches!(mode, CrawlMode::Cells | CrawlMode::Both) {
-			let dimensions = extension.dimensions;
-			let rows: Vec<u32> = (0..dimensions.extended_rows()).step_by(2).collect();
-			let total = rows.len();
-			let fetched = network_client
-				.fetch_rows_from_dht(block_number, dimensions, &rows)
-				.await
-				.iter()
-				.step_by(2)
-				.flatten()
-				.count();
+		let (header, received_at) = match config.crawl_fast_forward_backlog {
+			Some(backlog) if message_rx.len() > backlog => {
+				let from = header.number;
+				let latest =
+					fast_forward(&mut message_rx, header, received_at, &event_sender, &status);
+				for block_number in from..latest.0.number {
+					let blocks = ordered_blocks.skip(block_number);
+					forward_blocks(blocks, &block_sender, &status);
+				}
+				latest
+			},
+			_ => (header, received_at),
+		};
 
-			let success_rate = fetched as f64 / total as f64;
+		let block_hash: H256 = Encode::using_encoded(&header, utils::blake2_256).into();
+		let capacity = config.crawl_duplicate_headers_cache;
+		if is_duplicate(&mut seen_hashes, block_hash, capacity) {
 			info!(
-				block_number,
-				success_rate, total, fetched, "Fetched block rows"
+				block_number = header.number,
+				%block_hash,
+				"Skipping duplicated header"
 			);
+			send_event(&event_sender, &status, OutputEvent::CountDuplicateHeaders);
+			continue;
+		}
 
-			if let Err(error) = event_sender.send(OutputEvent::RecordRowsSuccessRate(success_rate))
-			{
-				error!("Failed to send RecordRowsSuccessRate event: {error}");
+		let block_number = header.number;
+		let dimensions = utils::extract_kate(&header.extension)
+			.and_then(|(rows, cols, _, _)| Dimensions::new(rows, cols));
+		if let Some(dimensions) = dimensions {
+			let factor = config.crawl_dimension_change_factor;
+			let previous = last_dimensions.replace(dimensions);
+			if previous.is_some_and(|previous| is_dimension_change(previous, dimensions, factor)) {
+				reset_dimension_state(block_number, previous, dimensions, &event_sender, &status);
+			}
+		}
+		head = head.max(block_number);
+		let (allow, deny) = (
+			config.crawl_allow_blocks.as_ref(),
+			&config.crawl_deny_blocks,
+		);
+		if is_first && config.crawl_first_block == FirstBlock::Skip {
+			info!(block_number, "Skipping first block after the startup");
+			crawled_blocks.record(block_number);
+			let blocks = ordered_blocks.skip(block_number);
+			forward_blocks(blocks, &block_sender, &status);
+		} else if filter::is_denied(allow, deny, block_number) {
+			info!(block_number, "Skipping denied block");
+			// Denied blocks are skipped on purpose, so they are not reported by the gap scan
+			crawled_blocks.record(block_number);
+			send_event(&event_sender, &status, OutputEvent::CountDeniedBlocks);
+			let blocks = ordered_blocks.skip(block_number);
+			forward_blocks(blocks, &block_sender, &status);
+		} else {
+			pending.push_back((header, received_at));
+		}
+		let delay_blocks = config.crawl_block_delay_blocks;
+		for (header, received_at) in due_headers(&mut pending, head, delay_blocks) {
+			let due_block_number = header.number;
+			let span = block_span(due_block_number, &config);
+			let ctx = CrawlContext {
+				config: &config,
+				db: &db,
+				network_client: &network_client,
+				block_sender: &block_sender,
+				result_sender: &result_sender,
+				event_sender: &event_sender,
+				status: &status,
+				clock: &sleep_tracker,
+				sampling: extensions.sampling.as_deref(),
+			};
+			let result = crawl_block(
+				header,
+				received_at,
+				&ctx,
+				&mut ordered_blocks,
+				&mut sample_cache,
+				extensions.hook.as_ref(),
+			)
+			.instrument(span)
+			.await;
+			let Some(result) = result else {
+				// Blocks which are not crawled are not forwarded either
+				let blocks = ordered_blocks.skip(due_block_number);
+				forward_blocks(blocks, &block_sender, &status);
+				continue;
+			};
+			crawled_blocks.record(result.block_number);
+			if zero_rate_action(
+				&result,
+				&mut zero_rate_blocks,
+				&config,
+				&event_sender,
+				&status,
+			) {
+				stop_reason = Some(ZERO_RATE_EXIT.to_string());
+				break 'headers;
 			}
 		}
 
+		let interval = config.gap_scan.crawl_gap_scan_interval;
+		if interval > 0 && block_number % interval == 0 {
+			scan_gaps(&crawled_blocks, &event_sender, &status);
+		}
+
+		// Skipped duplicated headers are counted as idle time of the next header,
+		// and the wait for the first header includes the startup, so it is not counted by default
+		let now = clock.now();
+		let sleeping = sleep_tracker.take_slept();
+		let idle = if is_first && config.crawl_first_block != FirstBlock::Record {
+			Duration::ZERO
+		} else {
+			woken_at.saturating_duration_since(idle_since)
+		};
+		let times = LoopTimes {
+			idle,
+			sleeping,
+			fetching: now
+				.saturating_duration_since(woken_at)
+				.saturating_sub(sleeping),
+		};
+		idle_since = now;
+		send_event(&event_sender, &status, OutputEvent::RecordLoopTimes(times));
+	}
+
+	if !pending.is_empty() {
+		info!(
+			pending = pending.len(),
+			"Pending blocks are not crawled before the crawl delay in blocks"
+		);
+	}
+	if !ordered_blocks.is_empty() {
+		info!(
+			buffered = ordered_blocks.len(),
+			"Forwarding buffered blocks without the missing previous blocks"
+		);
+		forward_blocks(ordered_blocks.drain(), &block_sender, &status);
+	}
+	stop_reason.map_or(Ok(()), Err)
+}
+
+/// Reason of the crawl loop stop by the [`ZeroRateAction::Exit`]
+pub const ZERO_RATE_EXIT: &str =
+	"Crawl loop stopped after consecutive blocks with zero success rate";
+
+/// Counts the consecutive zero rate blocks, and takes the zero rate action once the count reaches the threshold.
+/// Blocks without the requested cells and rows neither extend nor reset the count.
+/// Returns `true` if the crawl loop should be stopped.
+fn zero_rate_action(
+	result: &CrawlResult,
+	zero_rate_blocks: &mut u32,
+	config: &CrawlConfig,
+	event_sender: &UnboundedSender<OutputEvent>,
+	status: &RwLock<CrawlStatus>,
+) -> bool {
+	let Some(threshold) = config
+		.zero_rate
+		.crawl_zero_rate_blocks
+		.filter(|&threshold| threshold > 0)
+	else {
+		return false;
+	};
+	if !result.is_zero_rate() {
+		let mut stats = [result.cells, result.rows].into_iter().flatten();
+		if stats.any(|stats| stats.total > 0) {
+			*zero_rate_blocks = 0;
+		}
+		return false;
+	}
+	*zero_rate_blocks += 1;
+	if *zero_rate_blocks < threshold {
+		return false;
+	}
+	// Count is restarted, so the action is repeated if the zero rate persists
+	*zero_rate_blocks = 0;
+
+	error!(
+		block_number = result.block_number,
+		zero_rate_blocks = threshold,
+		"None of the cells and rows are fetched for the consecutive blocks, DHT is failing or misconfigured"
+	);
+	send_event(event_sender, status, OutputEvent::CountZeroRateEscalations);
+	config.zero_rate.crawl_zero_rate_action == ZeroRateAction::Exit
+}
+
+/// Sends the crawled blocks to the block verified subscribers
+fn forward_blocks(
+	blocks: Vec<BlockVerified>,
+	block_sender: &broadcast::Sender<BlockVerified>,
+	status: &RwLock<CrawlStatus>,
+) {
+	for block in blocks {
 		if let Err(error) = block_sender.send(block) {
-			error!("Cannot send block verified message: {error}");
-			continue;
+			let message = format!("Cannot send block verified message: {error}");
+			log_failure(status, FailureCategory::Send, message);
 		}
+	}
+}
+
+/// Sleeps until the crawl delay of the crawled part of the block passes since the header is received.
+/// Block is already delayed by the shortest delay of the crawled parts, so only the longer delays are slept.
+async fn sleep_part_delay(
+	block_number: u32,
+	part: &str,
+	delay: u64,
+	received_at: Instant,
+	config: &CrawlConfig,
+	clock: &impl Clock,
+) {
+	if config.crawl_block_delay_blocks.is_some() || delay <= config.block_delay() {
+		return;
+	}
+	let delay = Delay(Some(Duration::from_secs(delay)));
+	if let Some(duration) = delay.sleep_duration_at(received_at, clock.now()) {
+		info!(
+			block_number,
+			"Sleeping for {duration:?} before crawling block {part}"
+		);
+		clock.sleep(duration).await;
+	}
+}
 
-		let elapsed = start.elapsed();
-		info!(block_number, "Crawling block finished in {elapsed:?}");
+/// Skips the received header and the queued headers, and returns the latest header, so the crawl continues from the recent blocks.
+/// Number of the skipped headers is recorded, including the headers lost by the lagging receiver.
+fn fast_forward(
+	message_rx: &mut broadcast::Receiver<rpc::OutputEvent>,
+	header: AvailHeader,
+	received_at: Instant,
+	event_sender: &UnboundedSender<OutputEvent>,
+	status: &RwLock<CrawlStatus>,
+) -> (AvailHeader, Instant) {
+	let backlog = message_rx.len();
+	let from = header.number;
+	let mut latest = (header, received_at);
+	let mut skipped = 0;
+	loop {
+		match message_rx.try_recv() {
+			Ok(rpc::OutputEvent::HeaderUpdate {
+				header,
+				received_at,
+			}) => {
+				latest = (header, received_at);
+				skipped += 1;
+			},
+			Ok(rpc::OutputEvent::ConnectedHost(_)) => continue,
+			Err(broadcast::error::TryRecvError::Lagged(lost)) => {
+				skipped += usize::try_from(lost).unwrap_or(usize::MAX);
+			},
+			Err(_) => break,
+		}
+	}
+	warn!(
+		backlog,
+		skipped,
+		from,
+		to = latest.0.number,
+		"Headers backlog is too large, fast-forwarding to the latest header"
+	);
+	send_event(
+		event_sender,
+		status,
+		OutputEvent::CountFastForwardedBlocks(skipped),
+	);
+	latest
+}
+
+/// Resets the accumulators which are invalid after the dimensions change, and records the change
+fn reset_dimension_state(
+	block_number: u32,
+	previous: Option<Dimensions>,
+	dimensions: Dimensions,
+	event_sender: &UnboundedSender<OutputEvent>,
+	status: &RwLock<CrawlStatus>,
+) {
+	warn!(
+		block_number,
+		?previous,
+		?dimensions,
+		"Block dimensions changed unexpectedly, resetting failing rows and heatmap"
+	);
+	{
+		let mut status = status.write().expect("Lock acquired");
+		status.failing_rows.clear();
+		status.heatmap.clear();
+	}
+	send_event(event_sender, status, OutputEvent::CountDimensionChanges);
+}
+
+/// Logs the blocks of the recent window which are not crawled, and records their number
+fn scan_gaps(
+	crawled_blocks: &CrawledBlocks,
+	event_sender: &UnboundedSender<OutputEvent>,
+	status: &RwLock<CrawlStatus>,
+) {
+	let gaps = crawled_blocks.gaps();
+	if gaps.is_empty() {
+		info!("No gaps found in the recently crawled blocks");
+	} else {
+		warn!(
+			gaps = gaps.len(),
+			"Recent blocks which are not crawled: {gaps:?}"
+		);
+	}
+	send_event(
+		event_sender,
+		status,
+		OutputEvent::RecordRecentGapBlocks(gaps.len()),
+	);
+}
+
+/// Returns the time since which the crawl delay has already passed
+fn delayed_since(config: &CrawlConfig, clock: &impl Clock) -> Instant {
+	let delay = Duration::from_secs(config.cells_delay().max(config.rows_delay()));
+	let now = clock.now();
+	now.checked_sub(delay).unwrap_or(now)
+}
+
+/// Crawls the block with the given hash, with the header resolved using the `header_client`.
+/// Block is crawled immediately, without the crawl delay, and the outcome is also sent to the `result_sender`.
+/// Returns an error if the header is not found, or if the block is skipped (e.g. if the header is not valid).
+/// Resulting [`CrawlResult`] carries both the hash and the resolved block number.
+/// Sample of the block is reused from the given `sample_cache` if it is sampled for the same dimensions,
+/// so the repeated re-crawls don't generate the positions again, or computed from the dimensions if no cache is given.
+pub async fn crawl_block_by_hash(
+	block_hash: H256,
+	header_client: &impl HeaderClient,
+	ctx: &CrawlContext<'_, impl Database, impl Client, impl Clock>,
+	sample_cache: Option<&mut SampleCache>,
+) -> Result<CrawlResult> {
+	let header = header_client
+		.get_header_by_hash(block_hash)
+		.await
+		.wrap_err(format!("Block {block_hash:?} not found"))?;
+	let block_number = header.number;
+	info!(block_number, %block_hash, "Resolved block hash");
+
+	let mut uncached = SampleCache::new();
+	let span = block_span(block_number, ctx.config);
+	crawl_block(
+		header,
+		delayed_since(ctx.config, ctx.clock),
+		ctx,
+		&mut OrderedBlocks::new(0),
+		sample_cache.unwrap_or(&mut uncached),
+		None,
+	)
+	.instrument(span)
+	.await
+	.ok_or_else(|| eyre!("Block {block_number} ({block_hash:?}) is skipped"))
+}
+
+/// Outcome of the DHT availability probe
+#[derive(Clone, Debug, Serialize)]
+pub struct ProbeResult {
+	pub block_number: u32,
+	/// Stats of the probed cells
+	pub cells: CrawlStats,
+	/// Positions of the probed cells which are not fetched, as `(row, col)` pairs
+	pub unfetched: Vec<(u32, u16)>,
+	pub elapsed: Duration,
+}
+
+impl ProbeResult {
+	pub fn success_rate(&self) -> f64 {
+		self.cells.success_rate()
+	}
+}
+
+/// Probes the DHT availability of the given cells of the block, without waiting for the header.
+/// Probe is a standalone health check, so it doesn't affect the crawl status and metrics.
+/// Block should be recent, since the cells of the old blocks can be expired from the DHT.
+/// Returns an error if no positions are given.
+pub async fn probe(
+	network_client: &impl Client,
+	block_number: u32,
+	positions: &[Position],
+	clock: &impl Clock,
+) -> Result<ProbeResult> {
+	if positions.is_empty() {
+		return Err(eyre!("No cells to probe in block {block_number}"));
+	}
+	let start = clock.now();
+	let (fetched, unfetched, _) = network_client
+		.fetch_cells_from_dht(block_number, positions)
+		.await;
+	let elapsed = clock.now().duration_since(start);
+	let cells = clamped_stats(block_number, "cells", positions.len(), fetched.len());
+	let result = ProbeResult {
+		block_number,
+		cells,
+		unfetched: unfetched
+			.iter()
+			.map(|&Position { row, col }| (row, col))
+			.collect(),
+		elapsed,
+	};
+	info!(
+		block_number,
+		total = cells.total,
+		fetched = cells.fetched,
+		success_rate = result.success_rate(),
+		"Probed block cells in {elapsed:?}"
+	);
+	Ok(result)
+}
+
+fn block_span(block_number: u32, config: &CrawlConfig) -> Span {
+	info_span!(
+		"crawl_block",
+		block_number,
+		partition = %format_partitions(&config.crawl_block_matrix_partition),
+		mode = ?config.crawl_block_mode,
+	)
+}
+
+/// Slot duration of the Avail BABE consensus
+const SLOT_DURATION: Duration = Duration::from_secs(20);
+
+/// Returns the block timestamp, derived from the BABE slot of the header pre-runtime digest.
+/// Slots are counted from the UNIX epoch, so the block is authored within the slot duration since the timestamp.
+fn block_timestamp(header: &AvailHeader) -> Option<SystemTime> {
+	header.digest.logs.iter().find_map(|log| match log {
+		// Pre-digest is encoded as the variant index, the authority index (u32) and the slot (u64)
+		DigestItem::PreRuntime([b'B', b'A', b'B', b'E'], data) => {
+			let slot = u64::from_le_bytes(data.get(5..13)?.try_into().ok()?);
+			let millis = slot.checked_mul(SLOT_DURATION.as_millis() as u64)?;
+			SystemTime::UNIX_EPOCH.checked_add(Duration::from_millis(millis))
+		},
+		_ => None,
+	})
+}
+
+/// Crawls the block of the received header.
+/// Returns the outcome of the block, or `None` if the block is skipped.
+async fn crawl_block(
+	header: AvailHeader,
+	received_at: Instant,
+	ctx: &CrawlContext<'_, impl Database, impl Client, impl Clock>,
+	ordered_blocks: &mut OrderedBlocks<BlockVerified>,
+	sample_cache: &mut SampleCache,
+	hook: Option<&BlockHook>,
+) -> Option<CrawlResult> {
+	let CrawlContext {
+		config,
+		db,
+		network_client,
+		block_sender,
+		result_sender,
+		event_sender,
+		status,
+		clock,
+		sampling,
+	} = *ctx;
+	// Block delayed in blocks is already delayed before it is crawled
+	let delay = Delay(
+		config
+			.crawl_block_delay_blocks
+			.is_none()
+			.then(|| Duration::from_secs(config.block_delay())),
+	);
+	let mode = config.crawl_block_mode;
+	let checkpoint_header = config
+		.crawl_checkpoint_batch_size
+		.is_some()
+		.then(|| header.clone());
+	let timestamp = block_timestamp(&header);
+
+	let block = match types::BlockVerified::try_from((header, None)) {
+		Ok(block) => block,
+		Err(error) => {
+			log_failure(
+				status,
+				FailureCategory::Header,
+				format!("Header is not valid: {error}"),
+			);
+			return None;
+		},
+	};
+
+	let Some(extension) = &block.extension else {
+		info!("Skipping block without header extension");
+		return None;
+	};
+
+	let now = clock.now();
+	let received_at = if received_at > now {
+		let skew = received_at.duration_since(now);
+		warn!(
+			block_number = block.block_num,
+			"Header is received {skew:?} in the future, clocks are skewed"
+		);
+		send_event(event_sender, status, OutputEvent::CountClockSkew);
+		match config.crawl_clock_skew {
+			ClockSkew::Clamp => now,
+			ClockSkew::Immediate => delayed_since(config, clock),
+		}
+	} else {
+		received_at
+	};
+
+	if let Some(max_age) = config.crawl_max_block_age {
+		// Header without the block timestamp is as old as the time since it is received
+		let age = match timestamp {
+			Some(timestamp) => clock
+				.system_now()
+				.duration_since(timestamp)
+				.unwrap_or_default(),
+			None => now.saturating_duration_since(received_at),
+		};
+		if age > max_age {
+			info!(
+				block_number = block.block_num,
+				"Skipping block which is {age:?} old"
+			);
+			send_event(event_sender, status, OutputEvent::CountTooOldBlocks);
+			return None;
+		}
+	}
+
+	let forward_first = config.forward.crawl_forward_blocks
+		&& config.forward.crawl_forward_order == ForwardOrder::SendThenCrawl;
+	if forward_first {
+		let blocks = ordered_blocks.push(block.block_num, block.clone());
+		forward_blocks(blocks, block_sender, status);
+	}
+
+	let dimensions = extension.dimensions;
+	let commitments = extension.commitments.len();
+	if is_dimension_anomaly(dimensions, commitments) {
+		warn!(
+			extended_rows = dimensions.extended_rows(),
+			commitments, "Block dimensions are inconsistent with the 2x extension"
+		);
+		send_event(event_sender, status, OutputEvent::CountDimensionAnomalies);
+	}
+
+	if let Some(seconds) = delay.sleep_duration_at(received_at, clock.now()) {
+		info!("Sleeping for {seconds:?} seconds");
+		send_event(
+			event_sender,
+			status,
+			OutputEvent::RecordBlockDelay(seconds.as_secs_f64()),
+		);
+		clock.sleep(seconds).await;
+	}
+	let block_number = block.block_num;
+	let block_hash = block.header_hash;
+	info!(block_number, %block_hash, "Crawling block...");
+	status.write().expect("Lock acquired").crawling = Some(block_number);
+
+	send_event(
+		event_sender,
+		status,
+		OutputEvent::RecordCoverage(config.coverage()),
+	);
+
+	// Wall-clock times are derived from a single wall-clock reading paired with the monotonic time,
+	// so they stay ordered even if the system time is adjusted while crawling
+	let start = clock.now();
+	let started_at = SystemTime::now();
+	let delay = start.saturating_duration_since(received_at);
+
+	let dimensions = &extension.dimensions;
+	let Sample {
+		mut positions,
+		row_indexes,
+	} = match sampling {
+		Some(strategy) => {
+			let mut sample = strategy.sample(dimensions, config);
+			config.crawl_fetch_priority.order(dimensions, &mut sample);
+			sample
+		},
+		None => sample_cache.sample(dimensions, config),
+	};
+
+	if matches!(mode, CrawlMode::Cells | CrawlMode::Both) && positions.is_empty() {
+		let dimensions = extension.dimensions;
+		warn!(
+			block_number,
+			partition = format_partitions(&config.crawl_block_matrix_partition),
+			rows = dimensions.rows().get(),
+			cols = dimensions.cols().get(),
+			"Block matrix partition has no cells in the block, check the configured partition"
+		);
+		send_event(event_sender, status, OutputEvent::CountEmptyPartitions);
+	}
+
+	// Random and custom samples are different after the restart, so they cannot be resumed
+	let mut checkpoint = checkpoint_header
+		.zip(config.crawl_checkpoint_batch_size)
+		.filter(|_| sampling.is_none() && config.crawl_sampling == Sampling::Partition)
+		.map(|(header, batch_size)| {
+			let checkpoint = match db.get(CrawlCheckpointKey) {
+				Some(checkpoint) if checkpoint.header.number == block_number => checkpoint,
+				stale => {
+					if let Some(stale) = stale {
+						delete_checkpoint(db, &stale);
+					}
+					CrawlCheckpoint { header, batches: 0 }
+				},
+			};
+			(checkpoint, batch_size)
+		});
+
+	// Payload of the cells and rows fetched from the network, without the resumed and cached cells
+	let mut bytes_fetched = 0;
+	// Rows fetched first are processed after the cells, since derived rows depend on the reconstruction
+	let mut prefetched_rows = None;
+	if mode == CrawlMode::Both && config.crawl_fetch_order == FetchOrder::RowsFirst {
+		sleep_part_delay(
+			block_number,
+			"rows",
+			config.rows_delay(),
+			received_at,
+			config,
+			clock,
+		)
+		.await;
+		let dimensions = extension.dimensions;
+		let fetched_rows = network_client
+			.fetch_rows_from_dht(block_number, dimensions, &row_indexes)
+			.await;
+		prefetched_rows = Some(fetched_rows);
+	}
+
+	let mut cells = None;
+	let mut refetched_cells = 0;
+	let mut reconstructable = None;
+	let mut derived_rows = 0;
+	if matches!(mode, CrawlMode::Cells | CrawlMode::Both) {
+		let cells_delay = config.cells_delay();
+		sleep_part_delay(
+			block_number,
+			"cells",
+			cells_delay,
+			received_at,
+			config,
+			clock,
+		)
+		.await;
+		let resumed_positions = checkpoint
+			.as_ref()
+			.map_or_else(HashSet::new, |(checkpoint, _)| {
+				checkpointed_positions(db, checkpoint)
+			});
+		let requested = positions.len();
+		positions.retain(|position| !resumed_positions.contains(position));
+		let resumed = requested - positions.len();
+		if resumed > 0 {
+			info!(
+				block_number,
+				resumed, "Skipping block cells fetched before the restart"
+			);
+		}
+
+		let mut cached_cells = vec![];
+		if config.fetch_cache.crawl_fetch_cache_cells > 0 {
+			let now = clock.now();
+			let cached = {
+				let mut status = status.write().expect("Lock acquired");
+				status.fetch_cache.get(block_hash, now)
+			};
+			let event = match cached {
+				Some(cells) => {
+					let requested = positions.iter().collect::<HashSet<_>>();
+					cached_cells = cells;
+					cached_cells.retain(|cell| requested.contains(&cell.position));
+					OutputEvent::CountFetchCacheHit
+				},
+				None => OutputEvent::CountFetchCacheMiss,
+			};
+			send_event(event_sender, status, event);
+		}
+		let cached_positions = cached_cells
+			.iter()
+			.map(|cell| cell.position)
+			.collect::<HashSet<_>>();
+		positions.retain(|position| !cached_positions.contains(position));
+		let cached = cached_cells.len();
+		if cached > 0 {
+			info!(block_number, cached, "Reusing cached block cells");
+		}
+
+		let mut counts = FetchCounts::default();
+		let (sample, remaining) =
+			escalation_sample(positions, config.escalation.crawl_escalation_sample);
+		let mut total = resumed + cached + sample.len();
+		let (mut fetched, mut unfetched) = fetch_cells(
+			network_client,
+			db,
+			block_number,
+			&sample,
+			&mut checkpoint,
+			status,
+			&mut counts,
+		)
+		.await;
+
+		let mut escalated = false;
+		if !remaining.is_empty() {
+			// Empty sample has no fetched cells, so the block is escalated
+			let sample_rate = match sample.len() {
+				0 => 0.0,
+				sampled => fetched.len() as f64 / sampled as f64,
+			};
+			send_event(
+				event_sender,
+				status,
+				OutputEvent::RecordSampleRate(sample_rate),
+			);
+			if sample_rate < config.escalation.crawl_escalation_trigger {
+				info!(
+					block_number,
+					sample_rate,
+					remaining = remaining.len(),
+					"Escalating to the remaining block cells"
+				);
+				send_event(event_sender, status, OutputEvent::CountEscalations);
+				escalated = true;
+				let (escalated, escalated_unfetched) = fetch_cells(
+					network_client,
+					db,
+					block_number,
+					&remaining,
+					&mut checkpoint,
+					status,
+					&mut counts,
+				)
+				.await;
+				fetched.extend(escalated);
+				unfetched.extend(escalated_unfetched);
+				total += remaining.len();
+			} else {
+				info!(block_number, sample_rate, "Skipping remaining block cells");
+			}
+		}
+
+		for attempt in 1..=config.cells_refetch_attempts() {
+			if unfetched.is_empty() {
+				break;
+			}
+			let (refetched, still_unfetched, peers) = network_client
+				.fetch_cells_from_dht(block_number, &unfetched)
+				.await;
+			counts.queries += unfetched.len();
+			counts.bytes += cells_payload_size(&refetched);
+			record_peers(status, peers, &mut counts);
+
+			info!(
+				block_number,
+				attempt,
+				refetched = refetched.len(),
+				unfetched = still_unfetched.len(),
+				"Re-fetched failed block cells"
+			);
+			refetched_cells += refetched.len();
+			fetched.extend(refetched);
+			unfetched = still_unfetched;
+		}
+
+		{
+			let mut status = status.write().expect("Lock acquired");
+			for position in &unfetched {
+				status.failing_rows.record(position.row, 1);
+			}
+			let fetched = fetched.iter().map(|cell| &cell.position);
+			status.heatmap.record(fetched, &unfetched);
+		}
+
+		let positions = fetched.iter().map(|cell| &cell.position);
+		if let Some(variance) =
+			heatmap::region_rate_variance(config.heatmap.crawl_region_size, positions, &unfetched)
+		{
+			info!(
+				block_number,
+				variance, "Cells success rate variance across matrix regions"
+			);
+			let event = OutputEvent::RecordIntraBlockRateVariance(variance);
+			send_event(event_sender, status, event);
+		}
+
+		let FetchCounts {
+			queries,
+			local,
+			dht,
+			bytes,
+		} = counts;
+		bytes_fetched += bytes;
+		if let Some(ratio) = queries_per_cell(queries, fetched.len()) {
+			info!(block_number, queries, ratio, "DHT queries per fetched cell");
+			send_event(
+				event_sender,
+				status,
+				OutputEvent::RecordQueriesPerCell(ratio),
+			);
+		}
+
+		if local + dht > 0 {
+			info!(block_number, local, dht, "Served block cells");
+			send_event(
+				event_sender,
+				status,
+				OutputEvent::CountServedCells { local, dht },
+			);
+		}
+
+		if refetched_cells > 0 {
+			let event = OutputEvent::CountRefetchedCells(refetched_cells);
+			send_event(event_sender, status, event);
+		}
+
+		fetched.extend(cached_cells);
+		if config.fetch_cache.crawl_fetch_cache_cells > 0 {
+			let mut status = status.write().expect("Lock acquired");
+			let now = clock.now();
+			status.fetch_cache.insert(block_hash, fetched.clone(), now);
+		}
+
+		let dropped = chaos_dropped(fetched.len(), config.chaos.crawl_chaos_drop_fraction);
+		if dropped > 0 {
+			warn!(
+				block_number,
+				dropped, "CHAOS TESTING: Dropping fetched block cells"
+			);
+			fetched.shuffle(&mut utils::rng());
+			fetched.truncate(fetched.len() - dropped);
+		}
+
+		if config.crawl_reconstruct {
+			let dimensions = extension.dimensions;
+			let crawled = fetched.len();
+			let escalation_passed =
+				config.escalation.crawl_escalation_sample.is_none() || escalated;
+			if config.crawl_reconstruct_fetch && escalation_passed {
+				let positions = reconstruction_positions(dimensions, &fetched);
+				if !positions.is_empty() {
+					let (cells, _, peers) = network_client
+						.fetch_cells_from_dht(block_number, &positions)
+						.await;
+					record_peers(status, peers, &mut FetchCounts::default());
+					bytes_fetched += cells_payload_size(&cells);
+					info!(
+						block_number,
+						requested = positions.len(),
+						fetched = cells.len(),
+						"Fetched block cells missing for the reconstruction"
+					);
+					let event = OutputEvent::CountReconstructionCells(cells.len());
+					send_event(event_sender, status, event);
+					fetched.extend(cells);
+				}
+			}
+			let is_reconstructable = is_reconstructable(block_number, dimensions, &fetched);
+			// Cells fetched for the reconstruction are not counted as the crawled cells
+			fetched.truncate(crawled);
+			info!(
+				block_number,
+				reconstructable = is_reconstructable,
+				"Block reconstruction"
+			);
+			let event = OutputEvent::RecordReconstructable(is_reconstructable);
+			send_event(event_sender, status, event);
+			reconstructable = Some(is_reconstructable);
+		}
+
+		let fetched = fetched.len() + resumed;
+		let stats = clamped_stats(block_number, "cells", total, fetched);
+		if stats.fetched < total {
+			let failed = total - stats.fetched;
+			let message = format!("Failed to fetch {failed} of {total} block {block_number} cells");
+			let mut status = status.write().expect("Lock acquired");
+			status.record_error(FailureCategory::Fetch, message);
+		}
+		let fetched = stats.fetched;
+		let success_rate = stats.success_rate();
+		let partition = format_partitions(&config.crawl_block_matrix_partition);
+		info!(
+			block_number,
+			%block_hash,
+			partition,
+			success_rate,
+			total,
+			fetched,
+			"Fetched block cells",
+		);
+
+		// Confidence model assumes the random positions, which the partition cells are not
+		if sampling.is_none() && config.crawl_sampling == Sampling::Random {
+			let total_cells = extension.dimensions.extended_size() as usize;
+			let confidence = availability_confidence(total, fetched, total_cells);
+			info!(block_number, confidence, "Block availability confidence");
+			let event = OutputEvent::RecordAvailabilityConfidence(confidence);
+			send_event(event_sender, status, event);
+		}
+
+		if total < config.crawl_min_cells_for_rate {
+			info!(
+				block_number,
+				total, "Skipping cells success rate of a small block"
+			);
+		} else {
+			let rounded = round(success_rate, config.crawl_success_rate_precision);
+			send_event(
+				event_sender,
+				status,
+				OutputEvent::RecordCellSuccessRate(rounded),
+			);
+		}
+		cells = Some(stats);
+	}
+
+	let mut rows = None;
+	if matches!(mode, CrawlMode::Rows | CrawlMode::Both) {
+		let dimensions = extension.dimensions;
+		let total = row_indexes.len();
+		let mut fetched_rows = match prefetched_rows {
+			Some(fetched_rows) => fetched_rows,
+			None => {
+				let rows_delay = config.rows_delay();
+				sleep_part_delay(block_number, "rows", rows_delay, received_at, config, clock)
+					.await;
+				network_client
+					.fetch_rows_from_dht(block_number, dimensions, &row_indexes)
+					.await
+			},
+		};
+		bytes_fetched += rows_payload_size(&fetched_rows);
+		let valid_row = is_valid_row(dimensions);
+		let is_fetched = |row: &[u8]| !config.crawl_validate_rows || valid_row(row);
+		for attempt in 1..=config.rows_refetch_attempts() {
+			let unfetched = unfetched_rows(&row_indexes, &fetched_rows, is_fetched);
+			if unfetched.is_empty() {
+				break;
+			}
+			let refetched = network_client
+				.fetch_rows_from_dht(block_number, dimensions, &unfetched)
+				.await;
+			bytes_fetched += rows_payload_size(&refetched);
+			merge_rows(&mut fetched_rows, refetched, is_fetched);
+			info!(
+				block_number,
+				attempt,
+				requested = unfetched.len(),
+				"Re-fetched failed block rows"
+			);
+		}
+		let mut fetched = count_fetched_rows(&row_indexes, &fetched_rows, is_fetched);
+		let dropped = chaos_dropped(fetched, config.chaos.crawl_chaos_drop_fraction);
+		if dropped > 0 {
+			warn!(
+				block_number,
+				dropped, "CHAOS TESTING: Dropping fetched block rows"
+			);
+			fetched -= dropped;
+		}
+
+		let stats = clamped_stats(block_number, "rows", total, fetched);
+		if stats.fetched < total {
+			let failed = total - stats.fetched;
+			let message = format!("Failed to fetch {failed} of {total} block {block_number} rows");
+			let mut status = status.write().expect("Lock acquired");
+			status.record_error(FailureCategory::Fetch, message);
+		}
+		let fetched = stats.fetched;
+		let success_rate = stats.success_rate();
+		info!(
+			block_number,
+			%block_hash,
+			success_rate,
+			total,
+			fetched,
+			"Fetched block rows"
+		);
+
+		let rounded = round(success_rate, config.crawl_success_rate_precision);
+		send_event(
+			event_sender,
+			status,
+			OutputEvent::RecordRowsSuccessRate(rounded),
+		);
+		rows = Some(stats);
+
+		// Missing rows can be derived only if the entire block is reconstructable
+		if reconstructable == Some(true) {
+			derived_rows = total - fetched;
+		}
+	}
+
+	info!(block_number, bytes = bytes_fetched, "Fetched block payload");
+	send_event(
+		event_sender,
+		status,
+		OutputEvent::RecordBytesFetched(bytes_fetched),
+	);
+
+	let elapsed = clock.now().duration_since(start);
+	let mut result = CrawlResult {
+		block_number,
+		block_hash,
+		cells,
+		rows,
+		refetched_cells,
+		reconstructable,
+		derived_rows,
+		elapsed,
+		received_at: started_at.checked_sub(delay).unwrap_or(started_at),
+		crawled_at: started_at + elapsed,
+		available: false,
+	};
+
+	if let Some(ratio) = result
+		.rows_derived_ratio()
+		.filter(|_| reconstructable.is_some())
+	{
+		info!(block_number, derived_rows, ratio, "Derived block rows");
+		send_event(
+			event_sender,
+			status,
+			OutputEvent::RecordRowsDerivedRatio(ratio),
+		);
+	}
+	let matrix_cells = extension.dimensions.extended_size() as usize;
+	result.available = config.availability.is_available(&result, matrix_cells);
+	info!(
+		block_number,
+		%block_hash,
+		available = result.available,
+		"Block availability"
+	);
+
+	if result.available {
+		send_event(event_sender, status, OutputEvent::CountAvailableBlocks);
+	}
+
+	if let Some(hook) = hook {
+		hook(&result, mode);
+	}
+
+	// Sending fails only if there are no subscribers, which is valid
+	let _ = result_sender.send(result.clone());
+	{
+		let mut status = status.write().expect("Lock acquired");
+		status.crawling = None;
+		status.history.record(result.clone(), delay);
+		let interval = config.offenders.crawl_offenders_compaction_interval;
+		if interval > 0 && block_number % interval == 0 {
+			status.failing_rows.compact();
+		}
+	}
+	if let Some((checkpoint, _)) = &checkpoint {
+		delete_checkpoint(db, checkpoint);
+	}
+
+	if config.forward.crawl_forward_blocks && !forward_first {
+		let blocks = ordered_blocks.push(block_number, block);
+		forward_blocks(blocks, block_sender, status);
+	}
+
+	let elapsed = clock.now().duration_since(start);
+	info!(
+		block_number,
+		%block_hash,
+		"Crawling block finished in {elapsed:?}"
+	);
+
+	if config.crawl_post_block_cooldown_ms > 0 {
+		let cooldown = Duration::from_millis(config.crawl_post_block_cooldown_ms);
+		info!(
+			block_number,
+			"Cooling down for {cooldown:?} after crawled block"
+		);
+		clock.sleep(cooldown).await;
+	}
+
+	Some(result)
+}
+
+/// Handle of the crawl loop spawned with [`spawn`].
+///
+/// Crawl loop is stopped either by the shutdown of the host application controller,
+/// or by the [`CrawlHandle::shutdown`], which stops only the crawl loop.
+/// On stop, crawling of the current block is given `crawl_shutdown_drain_ms` to finish,
+/// and then it is cancelled on the next await point and the result of the block is not sent.
+/// Outcome of the current block is recorded in [`CrawlStatus::final_block`]. Futures of the in-flight fetches are dropped,
+/// so DHT queries which are already started are completed by the network event loop,
+/// but their results are discarded.
+pub struct CrawlHandle {
+	join_handle: JoinHandle<Result<(), String>>,
+	shutdown: Controller<String>,
+	status: Arc<RwLock<CrawlStatus>>,
+	result_sender: broadcast::Sender<CrawlResult>,
+}
+
+impl CrawlHandle {
+	/// Triggers the shutdown of the crawl loop, without triggering the host application shutdown.
+	/// Shutdown is triggered only once, subsequent calls are ignored.
+	pub fn shutdown(&self, reason: String) {
+		let _ = self.shutdown.trigger_shutdown(reason);
+	}
+
+	/// Aborts the crawl loop task immediately, same as the shutdown, except that [`CrawlHandle::join`]
+	/// returns cancelled [`JoinError`] instead of the shutdown reason.
+	pub fn abort(&self) {
+		self.join_handle.abort();
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.join_handle.is_finished()
+	}
+
+	/// Waits for the crawl loop to finish.
+	/// Returns `Ok(Err(reason))` if the crawl loop is stopped by the shutdown or by the [`ZeroRateAction::Exit`],
+	/// and `Ok(Ok(()))` if the crawl loop finished because RPC messages sender is dropped.
+	pub async fn join(self) -> Result<Result<(), String>, JoinError> {
+		self.join_handle.await
+	}
+}
+
+/// Observable surface of the spawned crawl client, implemented by the [`CrawlHandle`] returned by [`spawn`].
+/// Host applications can depend on the trait instead of the handle, and replace the crawler
+/// with the [`MockCrawlClient`] in their tests, without the network.
+#[async_trait]
+#[automock]
+pub trait CrawlClient: Send + Sync {
+	/// Returns the snapshot of the crawl status
+	fn status(&self) -> CrawlStatus;
+	/// Subscribes to the results of the blocks crawled after the subscription
+	fn subscribe_results(&self) -> broadcast::Receiver<CrawlResult>;
+	/// Returns the results of the recently crawled blocks, from the oldest to the newest, up to `crawl_summary_history`.
+	/// Only the recent results are cloned under the status lock, so it is cheaper than the [`CrawlClient::status`].
+	fn recent_results(&self) -> Vec<CrawlResult>;
+	/// Triggers the shutdown of the crawl loop, see [`CrawlHandle::shutdown`]
+	fn shutdown(&self, reason: String);
+	fn is_finished(&self) -> bool;
+	/// Waits for the crawl loop to finish, and returns the stop reason if it is stopped by the shutdown or by the zero rate action.
+	/// Failure of the crawl loop task is returned as the error message.
+	async fn wait(self) -> Result<(), String>;
+}
+
+#[async_trait]
+impl CrawlClient for CrawlHandle {
+	fn status(&self) -> CrawlStatus {
+		self.status.read().expect("Lock acquired").clone()
+	}
+
+	fn subscribe_results(&self) -> broadcast::Receiver<CrawlResult> {
+		self.result_sender.subscribe()
+	}
+
+	fn recent_results(&self) -> Vec<CrawlResult> {
+		let status = self.status.read().expect("Lock acquired");
+		status
+			.history
+			.iter()
+			.map(|block| block.result.clone())
+			.collect()
+	}
+
+	fn shutdown(&self, reason: String) {
+		CrawlHandle::shutdown(self, reason);
+	}
+
+	fn is_finished(&self) -> bool {
+		CrawlHandle::is_finished(self)
+	}
+
+	async fn wait(self) -> Result<(), String> {
+		self.join()
+			.await
+			.unwrap_or_else(|error| Err(format!("Crawl loop failed: {error}")))
+	}
+}
+
+/// Spawns crawl loop (see [`run`]), which is cancelled on the `shutdown` of the host application,
+/// and returns a [`CrawlHandle`] to manage the crawl loop lifecycle.
+pub fn spawn(
+	db: impl Database + Send + Sync + 'static,
+	channels: CrawlChannels,
+	network_client: impl Client + Send + Sync + 'static,
+	status: Arc<RwLock<CrawlStatus>>,
+	extensions: CrawlExtensions,
+	clock: impl Clock + 'static,
+	shutdown: Controller<String>,
+) -> CrawlHandle {
+	let crawl_shutdown = Controller::new();
+	let stopped = crawl_shutdown.clone();
+	let drain_config = channels.config_receiver.clone();
+	let results = channels.result_sender.subscribe();
+	let crawl_status = status.clone();
+	let handle_results = channels.result_sender.clone();
+	let handle_status = status.clone();
+	let crawl = run(db, channels, network_client, status, extensions, clock);
+
+	let join_handle = spawn_in_span(async move {
+		tokio::pin!(crawl);
+		// Shutdown is checked first, so the crawl loop doesn't progress after the shutdown
+		let reason = tokio::select! {
+			biased;
+			reason = shutdown.triggered_shutdown() => reason,
+			reason = stopped.triggered_shutdown() => reason,
+			result = &mut crawl => return result,
+		};
+		let drain = Duration::from_millis(drain_config.borrow().crawl_shutdown_drain_ms);
+		drain_final_block(crawl, results, &crawl_status, drain).await;
+		Err(reason)
+	});
+
+	CrawlHandle {
+		join_handle,
+		shutdown: crawl_shutdown,
+		status: handle_status,
+		result_sender: handle_results,
+	}
+}
+
+/// Gives the block being crawled up to `drain` to finish, and records its outcome in the status.
+/// Crawl loop is dropped afterwards, so the next blocks are not crawled.
+async fn drain_final_block(
+	mut crawl: Pin<&mut impl Future<Output = Result<(), String>>>,
+	mut results: broadcast::Receiver<CrawlResult>,
+	status: &RwLock<CrawlStatus>,
+	drain: Duration,
+) {
+	let Some(block_number) = status.read().expect("Lock acquired").crawling else {
+		return;
+	};
+
+	let finished = async {
+		loop {
+			tokio::select! {
+				_ = &mut crawl => return,
+				result = results.recv() => match result {
+					Ok(result) if result.block_number == block_number => return,
+					Err(broadcast::error::RecvError::Closed) => return,
+					_ => continue,
+				},
+			}
+		}
+	};
+	let completed = tokio::time::timeout(drain, finished).await.is_ok();
+	if completed {
+		info!(block_number, "Crawling final block completed on shutdown");
+	} else {
+		warn!(
+			block_number,
+			"Crawling final block aborted on shutdown after {drain:?}"
+		);
+	}
+
+	let mut status = status.write().expect("Lock acquired");
+	status.final_block = Some(FinalBlock {
+		block_number,
+		completed,
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data::MemoryDB;
+	use avail_rust::{
+		avail::runtime_types::avail_core::{
+			data_lookup::compact::CompactDataLookup,
+			header::extension::{v3::HeaderExtension, HeaderExtension::V3},
+			kate_commitment::v3::KateCommitment,
+		},
+		subxt::config::substrate::Digest,
+		AvailHeader, H256,
+	};
+	use std::{collections::HashSet, time::Instant};
+	use test_case::test_case;
+	use tokio::sync::mpsc;
+	use tracing::instrument::WithSubscriber;
+
+	fn result(cells: Option<(usize, usize)>, rows: Option<(usize, usize)>) -> CrawlResult {
+		let stats = |(total, fetched)| CrawlStats { total, fetched };
+		CrawlResult {
+			block_number: 1,
+			block_hash: H256::zero(),
+			cells: cells.map(stats),
+			rows: rows.map(stats),
+			refetched_cells: 0,
+			reconstructable: None,
+			derived_rows: 0,
+			elapsed: Duration::from_secs(1),
+			received_at: SystemTime::UNIX_EPOCH,
+			crawled_at: SystemTime::UNIX_EPOCH + Duration::from_secs(1),
+			available: false,
+		}
+	}
+
+	#[test_case(Some((100, 50)), None => true; "cells at threshold")]
+	#[test_case(Some((100, 49)), None => false; "cells below threshold")]
+	#[test_case(None, Some((10, 5)) => true; "rows at threshold")]
+	#[test_case(None, Some((10, 4)) => false; "rows below threshold")]
+	#[test_case(Some((100, 100)), Some((10, 4)) => false; "rows below threshold in both mode")]
+	#[test_case(Some((100, 49)), Some((10, 10)) => false; "cells below threshold in both mode")]
+	#[test_case(Some((100, 50)), Some((10, 5)) => true; "both at threshold")]
+	#[test_case(None, None => true; "nothing crawled")]
+	fn test_default_availability_criteria(
+		cells: Option<(usize, usize)>,
+		rows: Option<(usize, usize)>,
+	) -> bool {
+		AvailabilityCriteria::default().is_available(&result(cells, rows), 16)
+	}
+
+	#[test_case(Some((10, 10)), 99.9 => true; "confidence above threshold")]
+	#[test_case(Some((10, 9)), 98.9 => true; "confidence with unfetched cell")]
+	#[test_case(Some((100, 50)), 50.0 => false; "confidence below threshold")]
+	#[test_case(None, 99.9 => true; "confidence not applied without cells")]
+	fn test_confidence_availability_criteria(
+		cells: Option<(usize, usize)>,
+		min_confidence: f64,
+	) -> bool {
+		let criteria = AvailabilityCriteria {
+			crawl_min_cells_success_rate: 0.0,
+			crawl_min_rows_success_rate: 0.0,
+			crawl_min_confidence: Some(min_confidence),
+		};
+		let confidence =
+			cells.map(|(total, fetched)| CrawlStats { total, fetched }.confidence(4096));
+		let expected = confidence.is_none_or(|confidence| confidence >= min_confidence);
+		// Recorded confidence and the criteria use the same model
+		assert_eq!(criteria.is_available(&result(cells, None), 4096), expected);
+		expected
+	}
+
+	fn header(number: u32, rows: u16, cols: u16) -> AvailHeader {
+		AvailHeader {
+			parent_hash: H256::zero(),
+			number,
+			state_root: H256::zero(),
+			extrinsics_root: H256::zero(),
+			digest: Digest { logs: vec![] },
+			extension: V3(HeaderExtension {
+				commitment: KateCommitment {
+					rows,
+					cols,
+					data_root: H256::zero(),
+					commitment: vec![0; 2 * rows as usize * 48],
+				},
+				app_lookup: CompactDataLookup {
+					size: 1,
+					index: vec![],
+				},
+			}),
+		}
+	}
+
+	/// BABE secondary plain pre-digest of the slot starting at the `timestamp`
+	fn babe_digest(timestamp: SystemTime) -> DigestItem {
+		let since_epoch = timestamp.duration_since(SystemTime::UNIX_EPOCH).unwrap();
+		let slot = since_epoch.as_millis() as u64 / SLOT_DURATION.as_millis() as u64;
+		let data = [&[2][..], &0u32.to_le_bytes(), &slot.to_le_bytes()].concat();
+		DigestItem::PreRuntime(*b"BABE", data)
+	}
+
+	fn header_update(number: u32, rows: u16, cols: u16) -> rpc::OutputEvent {
+		rpc::OutputEvent::HeaderUpdate {
+			header: header(number, rows, cols),
+			received_at: Instant::now(),
+		}
+	}
+
+	fn test_config() -> CrawlConfig {
+		CrawlConfig {
+			crawl_block_delay: 0,
+			..Default::default()
+		}
+	}
+
+	fn channels(
+		rpc_event_receiver: broadcast::Receiver<rpc::OutputEvent>,
+		config_receiver: watch::Receiver<CrawlConfig>,
+		block_sender: broadcast::Sender<BlockVerified>,
+		result_sender: broadcast::Sender<CrawlResult>,
+		event_sender: UnboundedSender<OutputEvent>,
+	) -> CrawlChannels {
+		CrawlChannels {
+			rpc_event_receiver,
+			config_receiver,
+			block_sender,
+			result_sender,
+			event_sender,
+		}
+	}
+
+	fn cell(position: Position) -> Cell {
+		Cell {
+			position,
+			content: [0; 80],
+		}
+	}
+
+	/// Mock client which fetches none of the requested cells and rows
+	fn unavailable_client() -> MockClient {
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(|_, positions| {
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+		client
+			.expect_fetch_rows_from_dht()
+			.returning(|_, dimensions, _| {
+				Box::pin(async move { vec![None; dimensions.extended_rows() as usize] })
+			});
+		client
+	}
+
+	/// Results, output events and the final status of the crawl loop
+	struct Crawled {
+		results: Vec<CrawlResult>,
+		events: Vec<OutputEvent>,
+		status: CrawlStatus,
+	}
+
+	/// Runs the crawl loop with the `client` over the `headers`, until all of them are handled.
+	async fn crawl_blocks(
+		config: CrawlConfig,
+		headers: impl IntoIterator<Item = rpc::OutputEvent>,
+		client: MockClient,
+	) -> Crawled {
+		crawl_blocks_with(
+			config,
+			headers,
+			client,
+			CrawlExtensions::default(),
+			TokioClock,
+		)
+		.await
+	}
+
+	/// Same as [`crawl_blocks`], with the given crawl `extensions` and `clock`.
+	async fn crawl_blocks_with(
+		config: CrawlConfig,
+		headers: impl IntoIterator<Item = rpc::OutputEvent>,
+		client: MockClient,
+		extensions: CrawlExtensions,
+		clock: impl Clock + 'static,
+	) -> Crawled {
+		let headers = headers.into_iter().collect::<Vec<_>>();
+		// Channels fit all of the headers and results, so none of them are lagged
+		let capacity = headers.len().max(1);
+		let (message_tx, message_rx) = broadcast::channel(capacity);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(capacity);
+		let (result_tx, mut result_rx) = broadcast::channel(capacity);
+		let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		let handle = tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			client,
+			status.clone(),
+			extensions,
+			clock,
+		));
+		for header in headers {
+			message_tx.send(header).unwrap();
+		}
+		drop(message_tx);
+		handle.await.unwrap().unwrap();
+
+		let status = status.read().unwrap().clone();
+		Crawled {
+			results: std::iter::from_fn(|| result_rx.try_recv().ok()).collect(),
+			events: std::iter::from_fn(|| event_rx.try_recv().ok()).collect(),
+			status,
+		}
+	}
+
+	#[tokio::test]
+	async fn test_served_cells() {
+		// Two cells are served from the local store, one by the peer, and the peer serves one invalid record
+		let peer = PeerId::random();
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				let fetched = positions[..3].iter().copied().map(cell).collect();
+				let unfetched = positions[3..].to_vec();
+				let peers = vec![
+					(None, true),
+					(None, true),
+					(Some(peer), true),
+					(Some(peer), false),
+				];
+				Box::pin(async move { (fetched, unfetched, peers) })
+			});
+
+		let crawled = crawl_blocks(test_config(), [header_update(1, 1, 2)], client).await;
+		assert_eq!(
+			crawled.results[0].cells,
+			Some(CrawlStats {
+				total: 4,
+				fetched: 3
+			})
+		);
+
+		let served = crawled
+			.events
+			.into_iter()
+			.filter_map(|event| match event {
+				OutputEvent::CountServedCells { local, dht } => Some((local, dht)),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(served, vec![(2, 1)]);
+
+		// Only the peer is recorded in the peer stats
+		assert_eq!(crawled.status.peers.len(), 1);
+		assert_eq!(crawled.status.peers.get(&peer).unwrap().total(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_max_block_age() {
+		let config = CrawlConfig {
+			crawl_max_block_age: Some(Duration::from_secs(60)),
+			..test_config()
+		};
+
+		// Virtual time is ahead of the system time, so the age is measured by the clock
+		let now = Instant::now() + Duration::from_secs(3600);
+		let system_now = SystemTime::UNIX_EPOCH + SLOT_DURATION * 1_000_000;
+		let mut clock = MockClock::new();
+		clock.expect_now().return_const(now);
+		clock.expect_system_now().return_const(system_now);
+		clock.expect_sleep().returning(|_| Box::pin(async {}));
+
+		// Cells of the old blocks are not fetched
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.withf(|block_number, _| [3, 4].contains(block_number))
+			.returning(|_, positions| {
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+
+		let header_update = |number, timestamp: Option<SystemTime>, received_at| {
+			let mut header = header(number, 1, 4);
+			if let Some(timestamp) = timestamp {
+				header.digest.logs.push(babe_digest(timestamp));
+			}
+			rpc::OutputEvent::HeaderUpdate {
+				header,
+				received_at,
+			}
+		};
+		let (old, recent) = (Duration::from_secs(120), Duration::from_secs(20));
+		// Block timestamp takes precedence over the time the header is received
+		let headers = [
+			header_update(1, Some(system_now - old), now),
+			header_update(2, None, now - old),
+			header_update(3, Some(system_now - recent), now - old),
+			header_update(4, None, now),
+		];
+		let extensions = CrawlExtensions::default();
+		let crawled = crawl_blocks_with(config, headers, client, extensions, clock).await;
+		let crawled_blocks = crawled
+			.results
+			.iter()
+			.map(|result| result.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(crawled_blocks, vec![3, 4]);
+
+		let too_old = crawled
+			.events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::CountTooOldBlocks))
+			.count();
+		assert_eq!(too_old, 2);
+	}
+
+	#[test]
+	fn test_block_timestamp() {
+		let timestamp = SystemTime::UNIX_EPOCH + SLOT_DURATION * 1_000_000;
+		let mut header = header(1, 1, 4);
+		assert_eq!(block_timestamp(&header), None);
+
+		header.digest.logs.push(babe_digest(timestamp));
+		assert_eq!(block_timestamp(&header), Some(timestamp));
+
+		// Truncated pre-digest doesn't carry the slot
+		header.digest.logs = vec![DigestItem::PreRuntime(*b"BABE", vec![2, 0, 0, 0, 0])];
+		assert_eq!(block_timestamp(&header), None);
+	}
+
+	#[test_case(ClockSkew::Clamp => vec![Duration::from_secs(20)]; "clamp")]
+	#[test_case(ClockSkew::Immediate => Vec::<Duration>::new(); "immediate")]
+	#[tokio::test]
+	async fn test_clock_skew(crawl_clock_skew: ClockSkew) -> Vec<Duration> {
+		let config = CrawlConfig {
+			crawl_block_delay: 20,
+			crawl_clock_skew,
+			..test_config()
+		};
+
+		// Clamped header is delayed by the configured delay, instead of the skew
+		let now = Instant::now();
+		let sleeps = Arc::new(std::sync::Mutex::new(vec![]));
+		let clock_sleeps = sleeps.clone();
+		let mut clock = MockClock::new();
+		clock.expect_now().return_const(now);
+		clock.expect_sleep().returning(move |duration| {
+			clock_sleeps.lock().unwrap().push(duration);
+			Box::pin(async {})
+		});
+
+		let future_header = rpc::OutputEvent::HeaderUpdate {
+			header: header(1, 1, 4),
+			received_at: now + Duration::from_secs(3600),
+		};
+		let client = unavailable_client();
+		let extensions = CrawlExtensions::default();
+		let crawled = crawl_blocks_with(config, [future_header], client, extensions, clock).await;
+
+		let skewed = crawled
+			.events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::CountClockSkew))
+			.count();
+		assert_eq!(skewed, 1);
+		let sleeps = sleeps.lock().unwrap();
+		sleeps.clone()
+	}
+
+	#[tokio::test]
+	async fn test_crawl_timestamps() {
+		let received_at = Instant::now() - Duration::from_secs(10);
+		let headers = (1..=2).map(|number| rpc::OutputEvent::HeaderUpdate {
+			header: header(number, 1, 4),
+			received_at,
+		});
+		let crawled = crawl_blocks(test_config(), headers, unavailable_client()).await;
+		let [first, second] = &crawled.results[..] else {
+			panic!("Both blocks are crawled");
+		};
+		let now = SystemTime::now();
+
+		for result in [first, second] {
+			let age = now.duration_since(result.received_at).unwrap();
+			assert!(age >= Duration::from_secs(10) && age < Duration::from_secs(60));
+			assert!(result.received_at + result.elapsed <= result.crawled_at);
+		}
+		assert!(first.crawled_at <= second.crawled_at);
+		assert!(second.crawled_at <= now);
+	}
+
+	#[tokio::test]
+	async fn test_gap_scan() {
+		let config = CrawlConfig {
+			gap_scan: GapScanOpts {
+				crawl_gap_scan_interval: 3,
+				crawl_gap_scan_window: 5,
+			},
+			crawl_max_block_age: Some(Duration::from_secs(60)),
+			..test_config()
+		};
+
+		// Block 3 is never received, and block 5 is skipped as too old
+		let old_header = rpc::OutputEvent::HeaderUpdate {
+			header: header(5, 1, 4),
+			received_at: Instant::now() - Duration::from_secs(120),
+		};
+		let headers = [1, 2, 4]
+			.map(|number| header_update(number, 1, 4))
+			.into_iter()
+			.chain([old_header])
+			.chain((6..=9).map(|number| header_update(number, 1, 4)));
+		let crawled = crawl_blocks(config, headers, unavailable_client()).await;
+
+		let gaps = crawled
+			.events
+			.into_iter()
+			.filter_map(|event| match event {
+				OutputEvent::RecordRecentGapBlocks(gaps) => Some(gaps),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		// Scans are done after the blocks 6 and 9 are received, over the blocks 2 to 6 and 5 to 9
+		assert_eq!(gaps, vec![2, 1]);
+	}
+
+	#[tokio::test]
+	async fn test_block_delay_blocks() {
+		let config = CrawlConfig {
+			crawl_block_delay: 20,
+			crawl_block_delay_blocks: Some(2),
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		// Delay in blocks takes precedence over the delay in seconds
+		let mut clock = MockClock::new();
+		clock.expect_now().returning(Instant::now);
+		clock.expect_sleep().never();
+
+		tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			clock,
+		));
+
+		// Block 2 is received out of order, and it is crawled once block 4 is received
+		for block_number in [1, 3, 2] {
+			message_tx.send(header_update(block_number, 1, 4)).unwrap();
+		}
+		let result = result_rx.recv().await.unwrap();
+		assert_eq!(result.block_number, 1);
+		assert!(result_rx.try_recv().is_err());
+
+		message_tx.send(header_update(4, 1, 4)).unwrap();
+		assert_eq!(result_rx.recv().await.unwrap().block_number, 2);
+		message_tx.send(header_update(5, 1, 4)).unwrap();
+		assert_eq!(result_rx.recv().await.unwrap().block_number, 3);
+	}
+
+	#[test]
+	fn test_due_headers() {
+		let now = Instant::now();
+		let mut pending = [1, 3, 2]
+			.map(|number| (header(number, 1, 4), now))
+			.into_iter()
+			.collect::<VecDeque<_>>();
+		let numbers = |headers: Vec<(AvailHeader, Instant)>| {
+			headers
+				.into_iter()
+				.map(|(header, _)| header.number)
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(numbers(due_headers(&mut pending, 3, Some(2))), vec![1]);
+		assert_eq!(
+			numbers(due_headers(&mut pending, 3, Some(2))),
+			Vec::<u32>::new()
+		);
+		assert_eq!(numbers(due_headers(&mut pending, 5, Some(2))), vec![3, 2]);
+
+		pending.push_back((header(6, 1, 4), now));
+		assert_eq!(numbers(due_headers(&mut pending, 6, None)), vec![6]);
+		assert!(pending.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_empty_partition() {
+		let config = CrawlConfig {
+			crawl_block_matrix_partition: vec![Partition {
+				number: 10,
+				fraction: 100,
+			}],
+			..test_config()
+		};
+
+		// 10th 1/100 part of the 2x4 extended matrix has no cells, unlike the one of the 256x256 matrix
+		let headers = [header_update(1, 1, 4), header_update(2, 128, 256)];
+		let crawled = crawl_blocks(config, headers, unavailable_client()).await;
+
+		let empty = crawled
+			.events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::CountEmptyPartitions))
+			.count();
+		assert_eq!(empty, 1);
+	}
+
+	#[tokio::test]
+	async fn test_heatmap() {
+		let config = CrawlConfig {
+			heatmap: HeatmapOpts {
+				crawl_heatmap_bucket_size: 4,
+				..Default::default()
+			},
+			..test_config()
+		};
+
+		// Cells of the first row are fetched, and the cells of the second row are not
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(|_, positions| {
+				let (fetched, unfetched): (Vec<_>, Vec<_>) =
+					positions.iter().partition(|position| position.row == 0);
+				let fetched = fetched.into_iter().map(cell).collect();
+				Box::pin(async move { (fetched, unfetched, vec![]) })
+			});
+
+		let headers = (1..=3).map(|block_number| header_update(block_number, 1, 4));
+		let crawled = crawl_blocks(config, headers, client).await;
+
+		let heatmap = crawled.status.heatmap.export();
+		assert_eq!(heatmap.bucket_size, 4);
+		assert_eq!(heatmap.buckets.len(), 1);
+		let bucket = &heatmap.buckets[0];
+		assert_eq!((bucket.row, bucket.col), (0, 0));
+		assert_eq!((bucket.fetched, bucket.unfetched), (12, 12));
+		assert_eq!(bucket.success_rate, 0.5);
+	}
+
+	#[test_case(FetchPriority::Sampled => vec![0, 1, 2, 3]; "sampled")]
+	#[test_case(FetchPriority::OriginalFirst => vec![0, 2, 1, 3]; "original first")]
+	#[test_case(FetchPriority::CenterOut => vec![1, 2, 0, 3]; "center out")]
+	#[tokio::test]
+	async fn test_fetch_priority(crawl_fetch_priority: FetchPriority) -> Vec<u32> {
+		let config = CrawlConfig {
+			crawl_fetch_priority,
+			..test_config()
+		};
+
+		let requested = Arc::new(std::sync::Mutex::new(vec![]));
+		let fetch_requested = requested.clone();
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				fetch_requested.lock().unwrap().extend_from_slice(positions);
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+
+		// Extended matrix of 4 rows and 2 columns, with the original data in the rows 0 and 2
+		crawl_blocks(config, [header_update(1, 2, 2)], client).await;
+
+		// Positions are ordered before the first fetch, and the cells of each row keep the sampled order
+		let requested = requested.lock().unwrap();
+		assert_eq!(requested.len(), 8);
+		assert!(requested
+			.chunks(2)
+			.all(|row| row[0].col == 0 && row[1].col == 1));
+		requested
+			.iter()
+			.step_by(2)
+			.map(|position| position.row)
+			.collect()
+	}
+
+	#[test_case(2, |position: &Position| position.col < 2 => Some(0.25); "skewed regions")]
+	#[test_case(2, |position: &Position| position.row == 0 => Some(0.0); "uniform regions")]
+	#[test_case(4, |position: &Position| position.col < 2 => None; "single region")]
+	#[test_case(0, |position: &Position| position.col < 2 => None; "disabled")]
+	#[tokio::test]
+	async fn test_intra_block_rate_variance(
+		crawl_region_size: u32,
+		is_fetched: fn(&Position) -> bool,
+	) -> Option<f64> {
+		let config = CrawlConfig {
+			heatmap: HeatmapOpts {
+				crawl_region_size,
+				..Default::default()
+			},
+			..test_config()
+		};
+
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				let (fetched, unfetched): (Vec<_>, Vec<_>) =
+					positions.iter().partition(|position| is_fetched(position));
+				let fetched = fetched.into_iter().map(cell).collect();
+				Box::pin(async move { (fetched, unfetched, vec![]) })
+			});
+
+		// Extended matrix of 2 rows and 4 columns, with the success rate of 0.5 in both cases
+		let crawled = crawl_blocks(config, [header_update(1, 1, 4)], client).await;
+
+		crawled.events.into_iter().find_map(|event| match event {
+			OutputEvent::RecordIntraBlockRateVariance(variance) => Some(variance),
+			_ => None,
+		})
+	}
+
+	#[test_case(Dimensions::new(1, 4).unwrap(), Dimensions::new(1, 4).unwrap(), 16.0 => false ; "same")]
+	#[test_case(Dimensions::new(1, 4).unwrap(), Dimensions::new(4, 8).unwrap(), 16.0 => false ; "below factor")]
+	#[test_case(Dimensions::new(1, 4).unwrap(), Dimensions::new(8, 8).unwrap(), 16.0 => true ; "grown")]
+	#[test_case(Dimensions::new(8, 8).unwrap(), Dimensions::new(1, 4).unwrap(), 16.0 => true ; "shrunk")]
+	#[test_case(Dimensions::new(1, 4).unwrap(), Dimensions::new(8, 8).unwrap(), 0.0 => false ; "disabled")]
+	fn test_is_dimension_change(previous: Dimensions, current: Dimensions, factor: f64) -> bool {
+		is_dimension_change(previous, current, factor)
+	}
+
+	#[tokio::test]
+	async fn test_dimension_change() {
+		let config = CrawlConfig {
+			heatmap: HeatmapOpts {
+				crawl_heatmap_bucket_size: 1024,
+				..Default::default()
+			},
+			..test_config()
+		};
+
+		// 2x4 extended matrix is followed by the 32x64 one
+		let headers = [header_update(1, 1, 4), header_update(2, 16, 64)];
+		let crawled = crawl_blocks(config, headers, unavailable_client()).await;
+
+		let changes = crawled
+			.events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::CountDimensionChanges))
+			.count();
+		assert_eq!(changes, 1);
+
+		// Only the failures of the second block are accumulated
+		assert_eq!(crawled.status.failing_rows.get(&0), Some(64.0));
+		assert_eq!(
+			crawled.status.heatmap.export().buckets[0].unfetched,
+			32 * 64
+		);
+	}
+
+	#[tokio::test]
+	async fn test_duplicate_headers() {
+		// Block 1 is delivered twice, before and after block 2
+		let headers = [1, 1, 2, 1, 3].map(|block_number| header_update(block_number, 1, 4));
+		let crawled = crawl_blocks(test_config(), headers, unavailable_client()).await;
+
+		let crawled_blocks = crawled
+			.results
+			.iter()
+			.map(|result| result.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(crawled_blocks, vec![1, 2, 3]);
+
+		let duplicates = crawled
+			.events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::CountDuplicateHeaders))
+			.count();
+		assert_eq!(duplicates, 2);
+	}
+
+	#[test]
+	fn test_is_duplicate() {
+		let mut seen = VecDeque::new();
+		let hash = H256::repeat_byte;
+		assert!(!is_duplicate(&mut seen, hash(1), 2));
+		assert!(is_duplicate(&mut seen, hash(1), 2));
+		assert!(!is_duplicate(&mut seen, hash(2), 2));
+		// Hash 1 is evicted once the cache is full
+		assert!(!is_duplicate(&mut seen, hash(3), 2));
+		assert!(!is_duplicate(&mut seen, hash(1), 2));
+		// Duplicates are not detected if the cache is disabled
+		let mut seen = VecDeque::new();
+		assert!(!is_duplicate(&mut seen, hash(1), 0));
+		assert!(!is_duplicate(&mut seen, hash(1), 0));
+	}
+
+	#[tokio::test]
+	async fn test_block_hook() {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Both,
+			..test_config()
+		};
+
+		let invocations = Arc::new(std::sync::Mutex::new(vec![]));
+		let hook_invocations = invocations.clone();
+		let hook: BlockHook = Arc::new(move |result, mode| {
+			let invocation = (result.block_number, mode, result.cells, result.rows);
+			hook_invocations.lock().unwrap().push(invocation);
+		});
+
+		let extensions = CrawlExtensions {
+			hook: Some(hook),
+			..Default::default()
+		};
+		let headers = [header_update(1, 2, 4)];
+		let client = unavailable_client();
+		crawl_blocks_with(config, headers, client, extensions, TokioClock).await;
+
+		let invocations = invocations.lock().unwrap();
+		let cells = CrawlStats {
+			total: 16,
+			fetched: 0,
+		};
+		let rows = CrawlStats {
+			total: 2,
+			fetched: 0,
+		};
+		assert_eq!(
+			*invocations,
+			vec![(1, CrawlMode::Both, Some(cells), Some(rows))]
+		);
+	}
+
+	/// Strategy sampling the given positions, regardless of the dimensions and the configuration
+	struct FixedSampling(Vec<Position>);
+
+	impl SamplingStrategy for FixedSampling {
+		fn sample(&self, _: &Dimensions, _: &CrawlConfig) -> Sample {
+			Sample {
+				positions: self.0.clone(),
+				row_indexes: vec![],
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn test_custom_sampling() {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Cells,
+			..test_config()
+		};
+
+		// Only the positions of the custom strategy are fetched, instead of the configured partition
+		let requested = Arc::new(std::sync::Mutex::new(vec![]));
+		let requested_positions = requested.clone();
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				requested_positions.lock().unwrap().push(positions.to_vec());
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+		client.expect_fetch_rows_from_dht().never();
+
+		let positions = vec![Position { row: 3, col: 2 }, Position { row: 0, col: 1 }];
+		let extensions = CrawlExtensions {
+			sampling: Some(Arc::new(FixedSampling(positions.clone()))),
+			..Default::default()
+		};
+		let headers = [header_update(1, 2, 4)];
+		crawl_blocks_with(config, headers, client, extensions, TokioClock).await;
+
+		assert_eq!(*requested.lock().unwrap(), vec![positions]);
+	}
+
+	#[tokio::test]
+	async fn test_summary() {
+		let config = CrawlConfig {
+			crawl_summary_history: 2,
+			..test_config()
+		};
+
+		// Only the last two crawled blocks are summarized
+		let headers = (1..=3).map(|block_number| header_update(block_number, 2, 4));
+		let crawled = crawl_blocks(config.clone(), headers, unavailable_client()).await;
+
+		let summary = summary::Summary::new(&crawled.status, config.coverage());
+		let blocks = summary
+			.recent
+			.iter()
+			.map(|block| block.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(blocks, vec![2, 3]);
+		assert_eq!(summary.blocks, 2);
+		assert_eq!(summary.crawling, None);
+		assert!(summary.delay.is_some());
+		assert_eq!(summary.coverage.cells, config.coverage().cells);
+		assert_eq!(summary.available_fraction, Some(0.0));
+		assert_eq!(summary.cells_success_rate, Some(0.0));
+		assert_eq!(summary.rows_success_rate, None);
+	}
+
+	#[tokio::test]
+	async fn test_crawl_block_by_hash() {
+		let config = test_config();
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = RwLock::new(CrawlStatus::new(&config));
+
+		let block_hash = BlockVerified::try_from((header(5, 1, 4), None))
+			.unwrap()
+			.header_hash;
+		let mut header_client = MockHeaderClient::new();
+		header_client
+			.expect_get_header_by_hash()
+			.returning(move |hash| {
+				let header = (hash == block_hash).then(|| header(5, 1, 4));
+				Box::pin(async move { header.ok_or_else(|| eyre!("Header not found")) })
+			});
+
+		let (db, client) = (MemoryDB::default(), unavailable_client());
+		let ctx = CrawlContext {
+			config: &config,
+			db: &db,
+			network_client: &client,
+			block_sender: &block_tx,
+			result_sender: &result_tx,
+			event_sender: &event_tx,
+			status: &status,
+			clock: &TokioClock,
+			sampling: None,
+		};
+		let crawl = |hash| crawl_block_by_hash(hash, &header_client, &ctx, None);
+
+		let result = crawl(block_hash).await.unwrap();
+		assert_eq!(result.block_number, 5);
+		assert_eq!(result.block_hash, block_hash);
+
+		let error = crawl(H256::repeat_byte(1)).await.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			format!("Block {:?} not found", H256::repeat_byte(1))
+		);
+	}
+
+	#[tokio::test]
+	async fn test_sample_cache() {
+		let config = CrawlConfig {
+			crawl_block_matrix_partition: vec![Partition {
+				number: 2,
+				fraction: 4,
+			}],
+			crawl_columns: vec![1],
+			..test_config()
+		};
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = RwLock::new(CrawlStatus::new(&config));
+
+		let block_hash = BlockVerified::try_from((header(5, 2, 4), None))
+			.unwrap()
+			.header_hash;
+		let mut header_client = MockHeaderClient::new();
+		header_client
+			.expect_get_header_by_hash()
+			.returning(|_| Box::pin(async { Ok(header(5, 2, 4)) }));
+
+		// Cells of the even columns are fetched
+		let requested = Arc::new(std::sync::Mutex::new(vec![]));
+		let mut client = MockClient::new();
+		let requested_positions = requested.clone();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				requested_positions.lock().unwrap().push(positions.to_vec());
+				let (fetched, unfetched) = positions
+					.iter()
+					.partition::<Vec<_>, _>(|position| position.col % 2 == 0);
+				let fetched = fetched.into_iter().map(cell).collect();
+				Box::pin(async move { (fetched, unfetched, vec![]) })
+			});
+
+		let db = MemoryDB::default();
+		let ctx = CrawlContext {
+			config: &config,
+			db: &db,
+			network_client: &client,
+			block_sender: &block_tx,
+			result_sender: &result_tx,
+			event_sender: &event_tx,
+			status: &status,
+			clock: &TokioClock,
+			sampling: None,
+		};
+		let mut sample_cache = SampleCache::new();
+		let mut results = vec![];
+		for cache in [None, Some(&mut sample_cache)] {
+			let result = crawl_block_by_hash(block_hash, &header_client, &ctx, cache)
+				.await
+				.unwrap();
+			results.push(result.cells);
+		}
+		let dimensions = Dimensions::new(2, 4).unwrap();
+		assert!(sample_cache.contains(&dimensions, &config));
+		let result = crawl_block_by_hash(block_hash, &header_client, &ctx, Some(&mut sample_cache))
+			.await
+			.unwrap();
+		results.push(result.cells);
+
+		// Cached sample crawls the same cells as the sample computed from the dimensions
+		let requested = requested.lock().unwrap().clone();
+		assert_eq!(requested.len(), 3);
+		assert!(requested.iter().all(|positions| *positions == requested[0]));
+		assert_eq!(results, vec![results[0]; 3]);
+		assert_eq!(results[0].unwrap().total, 7);
+	}
+
+	#[tokio::test]
+	async fn test_fetch_cache() {
+		let config = CrawlConfig {
+			fetch_cache: FetchCacheOpts {
+				crawl_fetch_cache_cells: 100,
+				..Default::default()
+			},
+			..test_config()
+		};
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+		let status = RwLock::new(CrawlStatus::new(&config));
+
+		let block_hash = BlockVerified::try_from((header(5, 1, 4), None))
+			.unwrap()
+			.header_hash;
+		let mut header_client = MockHeaderClient::new();
+		header_client
+			.expect_get_header_by_hash()
+			.returning(|_| Box::pin(async { Ok(header(5, 1, 4)) }));
+
+		let requested = Arc::new(std::sync::Mutex::new(vec![]));
+		let mut client = MockClient::new();
+		let requested_positions = requested.clone();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				requested_positions.lock().unwrap().push(positions.len());
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+
+		let db = MemoryDB::default();
+		let ctx = CrawlContext {
+			config: &config,
+			db: &db,
+			network_client: &client,
+			block_sender: &block_tx,
+			result_sender: &result_tx,
+			event_sender: &event_tx,
+			status: &status,
+			clock: &TokioClock,
+			sampling: None,
+		};
+		for _ in 0..2 {
+			let result = crawl_block_by_hash(block_hash, &header_client, &ctx, None)
+				.await
+				.unwrap();
+			assert_eq!(result.cells.unwrap().success_rate(), 1.0);
+		}
+
+		// Block crawled again within the TTL reuses the cached cells
+		let requested = requested.lock().unwrap().clone();
+		assert_eq!(requested.len(), 2);
+		assert!(requested[0] > 0);
+		assert_eq!(requested[1], 0);
+		let events = std::iter::from_fn(|| event_rx.try_recv().ok())
+			.filter_map(|event| match event {
+				OutputEvent::CountFetchCacheHit => Some(true),
+				OutputEvent::CountFetchCacheMiss => Some(false),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(events, vec![false, true]);
+	}
+
+	#[test_case(Sampling::Partition => false; "partition sampling")]
+	#[test_case(Sampling::Random => true; "random sampling")]
+	#[tokio::test]
+	async fn test_availability_confidence_sampling(crawl_sampling: Sampling) -> bool {
+		let config = CrawlConfig {
+			crawl_sampling,
+			..test_config()
+		};
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], unavailable_client()).await;
+		crawled
+			.events
+			.iter()
+			.any(|event| matches!(event, OutputEvent::RecordAvailabilityConfidence(_)))
+	}
+
+	#[tokio::test]
+	async fn test_entire_block_without_partition() {
+		let config = CrawlConfig {
+			crawl_block_matrix_partition: vec![],
+			..test_config()
+		};
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], unavailable_client()).await;
+		assert_eq!(crawled.results[0].cells.unwrap().total, 16);
+
+		let coverage = crawled
+			.events
+			.into_iter()
+			.find_map(|event| match event {
+				OutputEvent::RecordCoverage(coverage) => Some(coverage),
+				_ => None,
+			})
+			.unwrap();
+		assert_eq!(coverage.cells, Some(1.0));
+	}
+
+	#[tokio::test]
+	async fn test_config_reload() {
+		let config = test_config();
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		));
+
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		let result = result_rx.recv().await.unwrap();
+		assert_eq!(result.block_number, 1);
+		let block = BlockVerified::try_from((header(1, 1, 4), None)).unwrap();
+		assert_eq!(result.block_hash, block.header_hash);
+		assert_eq!(result.cells.unwrap().total, 8);
+
+		let partition = Partition {
+			number: 1,
+			fraction: 2,
+		};
+		config_tx
+			.send(CrawlConfig {
+				crawl_block_matrix_partition: vec![partition],
+				..config
+			})
+			.unwrap();
+
+		message_tx.send(header_update(2, 1, 4)).unwrap();
+		let result = result_rx.recv().await.unwrap();
+		assert_eq!(result.block_number, 2);
+		assert_eq!(result.cells.unwrap().total, 4);
+
+		let partition_updated = std::iter::from_fn(|| event_rx.try_recv().ok()).any(|event| {
+			matches!(
+				event,
+				OutputEvent::PartitionUpdate(partitions) if format_partitions(&partitions) == "1/2"
+			)
+		});
+		assert!(partition_updated);
+	}
+
+	/// Log lines captured by the test subscriber
+	#[derive(Clone, Default)]
+	struct Logs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+	impl std::io::Write for Logs {
+		fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+			self.0.lock().unwrap().extend_from_slice(buf);
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> std::io::Result<()> {
+			Ok(())
+		}
+	}
+
+	impl Logs {
+		fn lines(&self) -> Vec<String> {
+			let logs = self.0.lock().unwrap();
+			String::from_utf8_lossy(&logs)
+				.lines()
+				.map(String::from)
+				.collect()
+		}
+	}
+
+	#[tokio::test]
+	async fn test_block_logs_context() {
+		let config = test_config();
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		// Sending the verified block fails, since there are no block receivers
+		let (block_tx, _) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		let logs = Logs::default();
+		let writer = logs.clone();
+		let subscriber = tracing_subscriber::fmt()
+			.with_writer(move || writer.clone())
+			.with_ansi(false)
+			.finish();
+
+		let crawl = run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		);
+		tokio::spawn(crawl.with_subscriber(subscriber));
+
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		result_rx.recv().await.unwrap();
+		drop(message_tx);
+		while result_rx.recv().await.is_ok() {}
+
+		let lines = logs.lines();
+		let error = lines
+			.iter()
+			.find(|line| line.contains("Cannot send block verified message"))
+			.expect("Error is logged");
+		let block_lines = lines.iter().filter(|line| line.contains("crawl_block"));
+		for line in block_lines.chain([error]) {
+			assert!(line.contains("block_number=1"), "{line}");
+			assert!(line.contains("partition=1/1"), "{line}");
+			assert!(line.contains("mode=Cells"), "{line}");
+		}
+	}
+
+	#[test_case(CrawlMode::Cells => (Some(8), None); "cells mode")]
+	#[test_case(CrawlMode::Rows => (None, Some(2)); "rows mode")]
+	#[test_case(CrawlMode::Both => (Some(8), Some(2)); "both mode")]
+	#[tokio::test]
+	async fn test_extension_rows_excluded(
+		crawl_block_mode: CrawlMode,
+	) -> (Option<usize>, Option<usize>) {
+		let config = CrawlConfig {
+			crawl_block_mode,
+			crawl_include_extension_rows: false,
+			..test_config()
+		};
+
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.withf(|_, positions| positions.iter().all(|position| !position.is_extended()))
+			.returning(|_, positions| {
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+		client
+			.expect_fetch_rows_from_dht()
+			.withf(|_, _, row_indexes| row_indexes == [0, 2])
+			.returning(|_, dimensions, _| {
+				Box::pin(async move { vec![None; dimensions.extended_rows() as usize] })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], client).await;
+		let result = &crawled.results[0];
+		// Cells of 2 original rows out of 4 extended rows are crawled, and the same original rows are fetched as rows
+		let cells = result.cells.map(|cells| {
+			assert_eq!(cells.fetched, cells.total);
+			cells.total
+		});
+		(cells, result.rows.map(|rows| rows.total))
+	}
+
+	#[test_case(CrawlMode::Cells => (true, false); "cells mode fetches cells only")]
+	#[test_case(CrawlMode::Rows => (false, true); "rows mode fetches rows only")]
+	#[test_case(CrawlMode::Both => (true, true); "both mode fetches cells and rows")]
+	#[tokio::test]
+	async fn test_crawl_mode_fetches(crawl_block_mode: CrawlMode) -> (bool, bool) {
+		let config = CrawlConfig {
+			crawl_block_mode,
+			..test_config()
+		};
+
+		let fetched = Arc::new(std::sync::Mutex::new((false, false)));
+		let mut client = MockClient::new();
+		let cells_fetched = fetched.clone();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				cells_fetched.lock().unwrap().0 = true;
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+		let rows_fetched = fetched.clone();
+		client
+			.expect_fetch_rows_from_dht()
+			.returning(move |_, dimensions, _| {
+				rows_fetched.lock().unwrap().1 = true;
+				Box::pin(async move { vec![None; dimensions.extended_rows() as usize] })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], client).await;
+		let result = &crawled.results[0];
+		let fetched = *fetched.lock().unwrap();
+		// Result reports the stats of the fetched cells and rows only
+		assert_eq!((result.cells.is_some(), result.rows.is_some()), fetched);
+		fetched
+	}
+
+	#[tokio::test]
+	async fn test_post_block_cooldown() {
+		let config = CrawlConfig {
+			crawl_post_block_cooldown_ms: 500,
+			..test_config()
+		};
+
+		let mut clock = MockClock::new();
+		clock.expect_now().returning(Instant::now);
+		clock
+			.expect_sleep()
+			.withf(|duration| *duration == Duration::from_millis(500))
+			.times(2)
+			.returning(|_| Box::pin(async {}));
+
+		// Clock expectations are verified when the crawl loop finishes
+		let headers = [header_update(1, 1, 4), header_update(2, 1, 4)];
+		let client = unavailable_client();
+		let extensions = CrawlExtensions::default();
+		crawl_blocks_with(config, headers, client, extensions, clock).await;
+	}
+
+	#[tokio::test]
+	async fn test_invalid_block_skipped_before_delay() {
+		// Default delay requires sleeping for the blocks received now
+		let config = CrawlConfig::default();
+
+		let mut clock = MockClock::new();
+		clock.expect_now().returning(Instant::now);
+		clock
+			.expect_sleep()
+			.times(1)
+			.returning(|_| Box::pin(async {}));
+
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.withf(|block_number, _| *block_number == 2)
+			.times(1)
+			.returning(|_, positions| {
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+
+		// Block with invalid dimensions is skipped without sleeping and fetching,
+		// and the clock and client expectations are verified when the crawl loop finishes
+		let headers = [header_update(1, 0, 4), header_update(2, 1, 4)];
+		let extensions = CrawlExtensions::default();
+		let crawled = crawl_blocks_with(config, headers, client, extensions, clock).await;
+		let crawled_blocks = crawled
+			.results
+			.iter()
+			.map(|result| result.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(crawled_blocks, vec![2]);
+	}
+
+	#[tokio::test]
+	async fn test_no_fetch_when_cancelled_during_delay() {
+		let config = CrawlConfig::default();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+		let (sleep_tx, mut sleep_rx) = mpsc::unbounded_channel();
+		let spawn_crawl = |message_rx, shutdown| {
+			let (_config_tx, config_rx) = watch::channel(config.clone());
+			let (block_tx, _) = broadcast::channel(10);
+			let (result_tx, _) = broadcast::channel(10);
+			let (event_tx, _) = mpsc::unbounded_channel();
+
+			let mut clock = MockClock::new();
+			clock.expect_now().returning(Instant::now);
+			let sleep_tx = sleep_tx.clone();
+			// Sleep is reported and never finishes, so the crawl loop can only be cancelled
+			clock.expect_sleep().times(1).returning(move |_| {
+				let sleep_tx = sleep_tx.clone();
+				Box::pin(async move {
+					sleep_tx.send(()).unwrap();
+					std::future::pending().await
+				})
+			});
+
+			let mut client = MockClient::new();
+			client.expect_fetch_cells_from_dht().never();
+			client.expect_fetch_rows_from_dht().never();
+
+			spawn(
+				MemoryDB::default(),
+				channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+				client,
+				status.clone(),
+				CrawlExtensions::default(),
+				clock,
+				shutdown,
+			)
+		};
+
+		// Crawl loop is stopped by its handle
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let handle = spawn_crawl(message_rx, Controller::new());
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		sleep_rx.recv().await.unwrap();
+		handle.shutdown("Crawl stopped".to_string());
+		assert_eq!(
+			handle.join().await.unwrap(),
+			Err("Crawl stopped".to_string())
+		);
+
+		// Crawl loop is stopped by host application shutdown
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let shutdown = Controller::new();
+		let handle = spawn_crawl(message_rx, shutdown.clone());
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		sleep_rx.recv().await.unwrap();
+		shutdown.trigger_shutdown("Shutdown".to_string()).unwrap();
+		assert_eq!(handle.join().await.unwrap(), Err("Shutdown".to_string()));
+	}
+
+	#[test_case(0 => true; "no minimum")]
+	#[test_case(8 => true; "minimum reached")]
+	#[test_case(9 => false; "small block")]
+	#[tokio::test]
+	async fn test_min_cells_for_rate(crawl_min_cells_for_rate: usize) -> bool {
+		let config = CrawlConfig {
+			crawl_min_cells_for_rate,
+			..test_config()
+		};
+
+		// Block has 8 cells in the extended matrix
+		let crawled = crawl_blocks(config, [header_update(1, 1, 4)], unavailable_client()).await;
+		assert_eq!(crawled.results[0].cells.unwrap().total, 8);
+
+		crawled
+			.events
+			.iter()
+			.any(|event| matches!(event, OutputEvent::RecordCellSuccessRate(_)))
+	}
+
+	#[tokio::test]
+	async fn test_partition_positions() {
+		let partition = Partition {
+			number: 2,
+			fraction: 3,
+		};
+		let config = CrawlConfig {
+			crawl_block_matrix_partition: vec![partition],
+			..test_config()
+		};
+
+		let dimensions = Dimensions::new(2, 4).unwrap();
+		let expected = partition_positions(&dimensions, &partition);
+		assert_eq!(expected.len(), 6);
+
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.withf(move |_, positions| positions == expected)
+			.times(1)
+			.returning(|_, positions| {
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], client).await;
+		assert_eq!(crawled.results[0].cells.unwrap().total, 6);
+	}
+
+	#[test_case(true => (1, 2); "sample available")]
+	#[test_case(false => (2, 8); "escalated")]
+	#[tokio::test]
+	async fn test_escalation(available: bool) -> (usize, usize) {
+		let config = CrawlConfig {
+			escalation: EscalationOpts {
+				crawl_escalation_sample: Some(0.25),
+				..Default::default()
+			},
+			..test_config()
+		};
+
+		let requested = Arc::new(std::sync::Mutex::new(vec![]));
+		let fetch_requested = requested.clone();
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				fetch_requested.lock().unwrap().push(positions.to_vec());
+				let (fetched, unfetched) = match available {
+					true => (positions.iter().copied().map(cell).collect(), vec![]),
+					false => (vec![], positions.to_vec()),
+				};
+				Box::pin(async move { (fetched, unfetched, vec![]) })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 1, 4)], client).await;
+		let cells = crawled.results[0].cells.unwrap();
+
+		let requested = requested.lock().unwrap();
+		assert_eq!(requested[0].len(), 2);
+		// Sample and the remaining cells cover the block once
+		let unique = requested.iter().flatten().collect::<HashSet<_>>();
+		assert_eq!(unique.len(), requested.iter().map(Vec::len).sum::<usize>());
+		(requested.len(), cells.total)
+	}
+
+	#[tokio::test]
+	async fn test_empty_escalation_sample() {
+		let config = CrawlConfig {
+			escalation: EscalationOpts {
+				crawl_escalation_sample: Some(0.0),
+				..Default::default()
+			},
+			..test_config()
+		};
+
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(|_, positions| {
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+
+		// Empty sample is escalated to all cells of the block
+		let crawled = crawl_blocks(config, [header_update(1, 1, 4)], client).await;
+		let cells = crawled.results[0].cells.unwrap();
+		assert_eq!((cells.total, cells.fetched), (8, 8));
+
+		let sample_rates = crawled.events.iter().filter_map(|event| match event {
+			OutputEvent::RecordSampleRate(rate) => Some(*rate),
+			_ => None,
+		});
+		assert_eq!(sample_rates.collect::<Vec<_>>(), vec![0.0]);
+		let escalations = crawled
+			.events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::CountEscalations));
+		assert_eq!(escalations.count(), 1);
+	}
+
+	#[test_case(Some(0.5), false, true => (0, 0, false); "sample available")]
+	#[test_case(Some(0.5), true, false => (1, 0, false); "escalated without reconstruction fetch")]
+	#[test_case(Some(0.5), true, true => (1, 3, true); "escalated and reconstructed")]
+	#[test_case(None, false, true => (0, 2, true); "reconstructed without sample")]
+	#[tokio::test]
+	async fn test_reconstruct_workflow(
+		crawl_escalation_sample: Option<f64>,
+		sample_unavailable: bool,
+		crawl_reconstruct_fetch: bool,
+	) -> (usize, usize, bool) {
+		// Partition covers the first 2 cells of the 2x4 extended matrix, so at least 2 columns are missing for the reconstruction
+		let config = CrawlConfig {
+			crawl_block_matrix_partition: vec![Partition {
+				number: 1,
+				fraction: 4,
+			}],
+			escalation: EscalationOpts {
+				crawl_escalation_sample,
+				..Default::default()
+			},
+			crawl_reconstruct: true,
+			crawl_reconstruct_fetch,
+			..test_config()
+		};
+
+		// Sample is not fetched if it is unavailable, and other cells are fetched
+		let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				let call = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+				let (fetched, unfetched) = match sample_unavailable && call == 0 {
+					true => (vec![], positions.to_vec()),
+					false => (positions.iter().copied().map(cell).collect(), vec![]),
+				};
+				Box::pin(async move { (fetched, unfetched, vec![]) })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 1, 4)], client).await;
+		let (result, events) = (&crawled.results[0], &crawled.events);
+		let sample_rates = events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::RecordSampleRate(_)))
+			.count();
+		assert_eq!(sample_rates, usize::from(crawl_escalation_sample.is_some()));
+		let escalations = events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::CountEscalations))
+			.count();
+		let reconstruction_cells = events
+			.iter()
+			.filter_map(|event| match event {
+				OutputEvent::CountReconstructionCells(cells) => Some(cells),
+				_ => None,
+			})
+			.sum();
+		// Cells fetched for the reconstruction are not counted as the crawled cells
+		let sampled = crawl_escalation_sample.is_some() && escalations == 0;
+		assert_eq!(result.cells.unwrap().total, if sampled { 1 } else { 2 });
+		(
+			escalations,
+			reconstruction_cells,
+			result.reconstructable.unwrap(),
+		)
+	}
+
+	#[test_case(2 => false; "commitment per extended row")]
+	#[test_case(1 => true; "commitment per original row")]
+	#[tokio::test]
+	async fn test_dimension_anomaly(commitments_per_row: usize) -> bool {
+		let mut header = header(1, 2, 4);
+		let V3(extension) = &mut header.extension;
+		extension.commitment.commitment = vec![0; commitments_per_row * 2 * 48];
+		let header_update = rpc::OutputEvent::HeaderUpdate {
+			header,
+			received_at: Instant::now(),
+		};
+		let crawled = crawl_blocks(test_config(), [header_update], unavailable_client()).await;
+		// Anomalous block is still crawled
+		assert_eq!(crawled.results[0].block_number, 1);
+
+		crawled
+			.events
+			.iter()
+			.any(|event| matches!(event, OutputEvent::CountDimensionAnomalies))
+	}
+
+	#[test_case(FetchOrder::CellsFirst => vec!["cells", "rows"]; "cells first")]
+	#[test_case(FetchOrder::RowsFirst => vec!["rows", "cells"]; "rows first")]
+	#[tokio::test]
+	async fn test_fetch_order(crawl_fetch_order: FetchOrder) -> Vec<&'static str> {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Both,
+			crawl_fetch_order,
+			..test_config()
+		};
+
+		let calls = Arc::new(std::sync::Mutex::new(vec![]));
+		let mut client = MockClient::new();
+		let cells_calls = calls.clone();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				cells_calls.lock().unwrap().push("cells");
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+		let rows_calls = calls.clone();
+		client
+			.expect_fetch_rows_from_dht()
+			.returning(move |_, dimensions, _| {
+				rows_calls.lock().unwrap().push("rows");
+				Box::pin(async move { vec![Some(vec![]); dimensions.extended_rows() as usize] })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], client).await;
+		let result = &crawled.results[0];
+		// Results don't depend on the order
+		assert_eq!(result.cells.unwrap().fetched, 16);
+		assert_eq!(result.rows.unwrap().fetched, 2);
+		let calls = calls.lock().unwrap().clone();
+		calls
+	}
+
+	#[test_case(CrawlMode::Cells => 16 * 80; "cells")]
+	#[test_case(CrawlMode::Rows => 2 * 32; "rows")]
+	#[test_case(CrawlMode::Both => 16 * 80 + 2 * 32; "both")]
+	#[tokio::test]
+	async fn test_bytes_fetched(crawl_block_mode: CrawlMode) -> usize {
+		let config = CrawlConfig {
+			crawl_block_mode,
+			..test_config()
+		};
+
+		// Client fetches cells of 80 bytes and rows of 32 bytes
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(|_, positions| {
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+		client
+			.expect_fetch_rows_from_dht()
+			.returning(|_, _, row_indexes| {
+				let rows = vec![Some(vec![0; 32]); row_indexes.len()];
+				Box::pin(async move { rows })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], client).await;
+		let bytes = crawled
+			.events
+			.into_iter()
+			.filter_map(|event| match event {
+				OutputEvent::RecordBytesFetched(bytes) => Some(bytes),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(bytes.len(), 1);
+		bytes[0]
+	}
+
+	#[test_case(None, None => vec![20]; "same delay")]
+	#[test_case(None, Some(30) => vec![20, 30]; "longer rows delay")]
+	#[test_case(Some(30), Some(10) => vec![10, 30]; "longer cells delay")]
+	#[tokio::test]
+	async fn test_part_delays(cells_delay: Option<u64>, rows_delay: Option<u64>) -> Vec<u64> {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Both,
+			crawl_block_delay: 20,
+			crawl_cells: CellCrawlOpts {
+				delay: cells_delay,
+				..Default::default()
+			},
+			crawl_rows: RowCrawlOpts {
+				delay: rows_delay,
+				..Default::default()
+			},
+			..test_config()
+		};
+
+		// Sleeps are skipped, so each sleep lasts until the delay since the header is received
+		let sleeps = Arc::new(std::sync::Mutex::new(vec![]));
+		let clock_sleeps = sleeps.clone();
+		let mut clock = MockClock::new();
+		clock.expect_now().returning(Instant::now);
+		clock.expect_sleep().returning(move |duration| {
+			clock_sleeps.lock().unwrap().push(duration);
+			Box::pin(async {})
+		});
+
+		let headers = [header_update(1, 2, 4)];
+		let client = unavailable_client();
+		let extensions = CrawlExtensions::default();
+		crawl_blocks_with(config, headers, client, extensions, clock).await;
+		let sleeps = sleeps.lock().unwrap();
+		sleeps
+			.iter()
+			.map(|duration| duration.as_secs_f64().round() as u64)
+			.collect()
+	}
+
+	#[test_case(0, None, None => (8, 0); "no re-fetch")]
+	#[test_case(1, None, None => (16, 2); "both re-fetched")]
+	#[test_case(0, None, Some(1) => (8, 2); "rows re-fetched")]
+	#[test_case(1, Some(0), None => (8, 2); "cells not re-fetched")]
+	#[tokio::test]
+	async fn test_part_refetch_attempts(
+		crawl_refetch_attempts: u32,
+		cells_attempts: Option<u32>,
+		rows_attempts: Option<u32>,
+	) -> (usize, usize) {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Both,
+			crawl_refetch_attempts,
+			crawl_cells: CellCrawlOpts {
+				refetch_attempts: cells_attempts,
+				..Default::default()
+			},
+			crawl_rows: RowCrawlOpts {
+				refetch_attempts: rows_attempts,
+				..Default::default()
+			},
+			..test_config()
+		};
+
+		// First fetch misses the cells of the odd rows and all rows, and the re-fetches succeed
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(|_, positions| {
+				let first = positions.iter().any(|position| position.row % 2 == 0);
+				let (fetched, unfetched): (Vec<_>, Vec<_>) = positions
+					.iter()
+					.partition(|position| !first || position.row % 2 == 0);
+				let fetched = fetched.into_iter().map(cell).collect();
+				Box::pin(async move { (fetched, unfetched, vec![]) })
+			});
+		let rows_calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+		client
+			.expect_fetch_rows_from_dht()
+			.returning(move |_, dimensions, _| {
+				let call = rows_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+				let row = (call > 0).then(Vec::new);
+				Box::pin(async move { vec![row; dimensions.extended_rows() as usize] })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], client).await;
+		let result = &crawled.results[0];
+		(result.cells.unwrap().fetched, result.rows.unwrap().fetched)
+	}
+
+	#[test_case(None => (16, 2); "disabled")]
+	#[test_case(Some(0.5) => (8, 1); "half dropped")]
+	#[test_case(Some(1.0) => (0, 0); "all dropped")]
+	#[tokio::test]
+	async fn test_chaos_drop_fraction(crawl_chaos_drop_fraction: Option<f64>) -> (usize, usize) {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Both,
+			chaos: ChaosOpts {
+				crawl_chaos_drop_fraction,
+			},
+			..test_config()
+		};
+
+		// Client fetches all requested cells and rows
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(|_, positions| {
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+		client
+			.expect_fetch_rows_from_dht()
+			.returning(|_, dimensions, _| {
+				Box::pin(async move { vec![Some(vec![]); dimensions.extended_rows() as usize] })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], client).await;
+		let result = &crawled.results[0];
+		let (cells, rows) = (result.cells.unwrap(), result.rows.unwrap());
+		assert_eq!((cells.total, rows.total), (16, 2));
+		(cells.fetched, rows.fetched)
+	}
+
+	#[test_case(true => 1; "forwarded")]
+	#[test_case(false => 0; "not forwarded")]
+	#[tokio::test]
+	async fn test_forward_blocks(crawl_forward_blocks: bool) -> usize {
+		let config = CrawlConfig {
+			forward: ForwardOpts {
+				crawl_forward_blocks,
+				..Default::default()
+			},
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, mut block_rx) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		));
+
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		message_tx.send(header_update(2, 1, 4)).unwrap();
+		result_rx.recv().await.unwrap();
+		// Block is forwarded before the next block is crawled
+		result_rx.recv().await.unwrap();
+		std::iter::from_fn(|| block_rx.try_recv().ok())
+			.filter(|block| block.block_num == 1)
+			.count()
+	}
+
+	#[test_case(ForwardOrder::CrawlThenSend => vec![0, 1]; "crawl then send")]
+	#[test_case(ForwardOrder::SendThenCrawl => vec![1, 2]; "send then crawl")]
+	#[tokio::test]
+	async fn test_forward_order(crawl_forward_order: ForwardOrder) -> Vec<usize> {
+		let config = CrawlConfig {
+			forward: ForwardOpts {
+				crawl_forward_order,
+				..Default::default()
+			},
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, mut block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		// Number of the forwarded blocks is tracked when the cells are fetched
+		let forwarded = Arc::new(std::sync::Mutex::new(vec![]));
+		let fetch_forwarded = forwarded.clone();
+		let sender = block_tx.clone();
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				fetch_forwarded.lock().unwrap().push(sender.len());
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+
+		let handle = tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			client,
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		));
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		message_tx.send(header_update(2, 1, 4)).unwrap();
+		drop(message_tx);
+		handle.await.unwrap().unwrap();
+
+		// Both blocks are forwarded in either order
+		let blocks = std::iter::from_fn(|| block_rx.try_recv().ok()).count();
+		assert_eq!(blocks, 2);
+		let forwarded = forwarded.lock().unwrap();
+		forwarded.clone()
+	}
+
+	#[test_case(0 => vec![1, 3, 2]; "completion order")]
+	#[test_case(4 => vec![1, 2, 3]; "block number order")]
+	#[tokio::test]
+	async fn test_ordered_forward_buffer(crawl_ordered_forward_buffer: usize) -> Vec<u32> {
+		let config = CrawlConfig {
+			forward: ForwardOpts {
+				crawl_ordered_forward_buffer,
+				..Default::default()
+			},
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, mut block_rx) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		));
+
+		// Block 3 is crawled before block 2
+		for block_number in [1, 3, 2] {
+			message_tx.send(header_update(block_number, 1, 4)).unwrap();
+			result_rx.recv().await.unwrap();
+		}
+		std::iter::from_fn(|| block_rx.try_recv().ok())
+			.map(|block| block.block_num)
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn test_ordered_forward_skipped_block() {
+		let config = CrawlConfig {
+			forward: ForwardOpts {
+				crawl_ordered_forward_buffer: 4,
+				..Default::default()
+			},
+			crawl_deny_blocks: BlockNumbers::try_from(vec!["2".to_string()]).unwrap(),
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, mut block_rx) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		));
+
+		// Denied block 2 is never crawled, so block 3 doesn't wait for it
+		for block_number in [1, 2, 3] {
+			message_tx.send(header_update(block_number, 1, 4)).unwrap();
+		}
+		for _ in 0..2 {
+			result_rx.recv().await.unwrap();
+		}
+		let forwarded = std::iter::from_fn(|| block_rx.try_recv().ok())
+			.map(|block| block.block_num)
+			.collect::<Vec<_>>();
+		assert_eq!(forwarded, vec![1, 3]);
+	}
+
+	#[tokio::test]
+	async fn test_probe() {
+		let position = |row, col| Position { row, col };
+		let probed = [
+			position(0, 0),
+			position(0, 1),
+			position(1, 0),
+			position(1, 1),
+		];
+
+		// Client fetches the cells of the first row only
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.withf(|block_number, _| *block_number == 42)
+			.times(1)
+			.returning(|_, positions| {
+				let (fetched, unfetched): (Vec<_>, Vec<_>) =
+					positions.iter().partition(|position| position.row == 0);
+				let fetched = fetched.into_iter().map(cell).collect();
+				Box::pin(async move { (fetched, unfetched, vec![]) })
+			});
+
+		let result = probe(&client, 42, &probed, &TokioClock).await.unwrap();
+		assert_eq!(result.block_number, 42);
+		assert_eq!((result.cells.total, result.cells.fetched), (4, 2));
+		assert_eq!(result.success_rate(), 0.5);
+		assert_eq!(result.unfetched, vec![(1, 0), (1, 1)]);
+
+		assert!(probe(&MockClient::new(), 42, &[], &TokioClock)
+			.await
+			.is_err());
+	}
+
+	#[test]
+	fn test_block_channel() {
+		let block = |number| BlockVerified::try_from((header(number, 1, 4), None)).unwrap();
+
+		// Zero capacity is raised to 1, so the lagging subscriber loses the oldest block
+		let (block_tx, mut block_rx) = block_channel(0);
+		block_tx.send(block(1)).unwrap();
+		block_tx.send(block(2)).unwrap();
+		assert!(matches!(
+			block_rx.try_recv(),
+			Err(broadcast::error::TryRecvError::Lagged(1))
+		));
+		assert_eq!(block_rx.try_recv().unwrap().block_num, 2);
+
+		let (block_tx, mut block_rx) = block_channel(MIN_BLOCK_CHANNEL_CAPACITY);
+		block_tx.send(block(1)).unwrap();
+		block_tx.send(block(2)).unwrap();
+		assert_eq!(block_rx.try_recv().unwrap().block_num, 1);
+		assert_eq!(block_rx.try_recv().unwrap().block_num, 2);
+	}
+
+	#[test_case(None => (1..=10).collect::<Vec<_>>(); "disabled")]
+	#[test_case(Some(8) => vec![10]; "backlog over threshold")]
+	#[test_case(Some(9) => (1..=10).collect::<Vec<_>>(); "backlog within threshold")]
+	#[tokio::test]
+	async fn test_fast_forward(crawl_fast_forward_backlog: Option<usize>) -> Vec<u32> {
+		let config = CrawlConfig {
+			crawl_fast_forward_backlog,
+			..test_config()
+		};
+
+		// Backlog of 9 headers is queued while the first header is received
+		let headers = (1..=10).map(|block_number| header_update(block_number, 1, 4));
+		let crawled = crawl_blocks(config, headers, unavailable_client()).await;
+
+		let skipped = crawled
+			.events
+			.into_iter()
+			.filter_map(|event| match event {
+				OutputEvent::CountFastForwardedBlocks(skipped) => Some(skipped),
+				_ => None,
+			})
+			.sum::<usize>();
+		let crawled = crawled
+			.results
+			.into_iter()
+			.map(|result| result.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(skipped, 10 - crawled.len());
+		crawled
+	}
+
+	#[test_case(Some(&["2-4", "6"]), &[] => (vec![2, 3, 4, 6], 3); "allow only")]
+	#[test_case(None, &["1", "5-6"] => (vec![2, 3, 4, 7], 3); "deny only")]
+	#[test_case(Some(&["2-5"]), &["4"] => (vec![2, 3, 5], 4); "allow and deny")]
+	#[tokio::test]
+	async fn test_block_filter(allow: Option<&[&str]>, deny: &[&str]) -> (Vec<u32>, usize) {
+		let numbers = |values: &[&str]| {
+			let values = values
+				.iter()
+				.map(|value| value.to_string())
+				.collect::<Vec<_>>();
+			BlockNumbers::try_from(values).unwrap()
+		};
+		let config = CrawlConfig {
+			crawl_allow_blocks: allow.map(numbers),
+			crawl_deny_blocks: numbers(deny),
+			..test_config()
+		};
+
+		let headers = (1..=7).map(|block_number| header_update(block_number, 1, 4));
+		let crawled = crawl_blocks(config, headers, unavailable_client()).await;
+
+		let denied = crawled
+			.events
+			.iter()
+			.filter(|event| matches!(event, OutputEvent::CountDeniedBlocks))
+			.count();
+		let crawled = crawled
+			.results
+			.into_iter()
+			.map(|result| result.block_number)
+			.collect::<Vec<_>>();
+		(crawled, denied)
+	}
+
+	#[test_case(ZeroRateAction::Log, &[0, 0, 0, 0, 0, 0, 0] => (7, 2, true); "log")]
+	#[test_case(ZeroRateAction::Exit, &[0, 0, 0, 0, 0, 0, 0] => (3, 1, false); "exit")]
+	#[test_case(ZeroRateAction::Exit, &[0, 0, 1, 0, 0, 4, 0] => (7, 0, true); "interrupted")]
+	#[tokio::test]
+	async fn test_zero_rate_action(
+		crawl_zero_rate_action: ZeroRateAction,
+		fetched: &'static [usize],
+	) -> (usize, usize, bool) {
+		let config = CrawlConfig {
+			zero_rate: ZeroRateOpts {
+				crawl_zero_rate_blocks: Some(3),
+				crawl_zero_rate_action,
+			},
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		// Block N has the given number of cells fetched
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |block_number, positions| {
+				let fetched = fetched[block_number as usize - 1].min(positions.len());
+				let cells = positions[..fetched].iter().copied().map(cell).collect();
+				let unfetched = positions[fetched..].to_vec();
+				Box::pin(async move { (cells, unfetched, vec![]) })
+			});
+
+		for block_number in 1..=7 {
+			message_tx.send(header_update(block_number, 1, 4)).unwrap();
+		}
+		drop(message_tx);
+
+		let outcome = run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			client,
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		)
+		.await;
+		if let Err(reason) = &outcome {
+			assert_eq!(reason, ZERO_RATE_EXIT);
+		}
+
+		let crawled = std::iter::from_fn(|| result_rx.try_recv().ok()).count();
+		let escalations = std::iter::from_fn(|| event_rx.try_recv().ok())
+			.filter(|event| matches!(event, OutputEvent::CountZeroRateEscalations))
+			.count();
+		(crawled, escalations, outcome.is_ok())
+	}
+
+	#[test_case(FirstBlock::Initialize => (vec![1, 2], false); "initialize")]
+	#[test_case(FirstBlock::Record => (vec![1, 2], true); "record")]
+	#[test_case(FirstBlock::Skip => (vec![2], false); "skip")]
+	#[tokio::test]
+	async fn test_first_block(crawl_first_block: FirstBlock) -> (Vec<u32>, bool) {
+		let config = CrawlConfig {
+			crawl_first_block,
+			gap_scan: GapScanOpts {
+				crawl_gap_scan_interval: 2,
+				..Default::default()
+			},
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		let handle = tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		));
+
+		// First header is received after the startup wait
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		message_tx.send(header_update(2, 1, 4)).unwrap();
+		drop(message_tx);
+		handle.await.unwrap().unwrap();
+
+		let events = std::iter::from_fn(|| event_rx.try_recv().ok()).collect::<Vec<_>>();
+		let idle = events
+			.iter()
+			.filter_map(|event| match event {
+				OutputEvent::RecordLoopTimes(times) => Some(times.idle),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(idle.len(), 2);
+		assert!(idle[1] < Duration::from_millis(50));
+
+		// Skipped first block is not reported as a gap
+		let gaps = events.iter().find_map(|event| match event {
+			OutputEvent::RecordRecentGapBlocks(gaps) => Some(*gaps),
+			_ => None,
+		});
+		assert_eq!(gaps, Some(0));
+
+		let crawled = std::iter::from_fn(|| result_rx.try_recv().ok())
+			.map(|result| result.block_number)
+			.collect::<Vec<_>>();
+		(crawled, idle[0] >= Duration::from_millis(50))
+	}
+
+	#[tokio::test]
+	async fn test_loop_times() {
+		let config = CrawlConfig {
+			crawl_block_delay: 20,
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		// Virtual time advances only by the sleeps, the fetches and between the headers
+		let time = Arc::new(std::sync::Mutex::new(Instant::now()));
+		let advance = |time: &std::sync::Mutex<Instant>, duration| {
+			*time.lock().unwrap() += duration;
+		};
+		let mut clock = MockClock::new();
+		let clock_time = time.clone();
+		clock
+			.expect_now()
+			.returning(move || *clock_time.lock().unwrap());
+		let clock_time = time.clone();
+		clock.expect_sleep().returning(move |duration| {
+			advance(&clock_time, duration);
+			Box::pin(async {})
+		});
+		let mut client = MockClient::new();
+		let client_time = time.clone();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				advance(&client_time, Duration::from_secs(2));
+				let unfetched = positions.to_vec();
+				Box::pin(async move { (vec![], unfetched, vec![]) })
+			});
+
+		let start = *time.lock().unwrap();
+		let handle = tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			client,
+			status,
+			CrawlExtensions::default(),
+			clock,
+		));
+
+		let header_update = |number| rpc::OutputEvent::HeaderUpdate {
+			header: header(number, 1, 4),
+			received_at: *time.lock().unwrap(),
+		};
+		let mut loop_times = vec![];
+		message_tx.send(header_update(1)).unwrap();
+		while loop_times.is_empty() {
+			if let Some(OutputEvent::RecordLoopTimes(times)) = event_rx.recv().await {
+				loop_times.push(times);
+			}
+		}
+		advance(&time, Duration::from_secs(5));
+		message_tx.send(header_update(2)).unwrap();
+		drop(message_tx);
+		handle.await.unwrap().unwrap();
+		loop_times.extend(
+			std::iter::from_fn(|| event_rx.try_recv().ok()).filter_map(|event| match event {
+				OutputEvent::RecordLoopTimes(times) => Some(times),
+				_ => None,
+			}),
+		);
+
+		let seconds = |times: &LoopTimes| {
+			[times.idle, times.sleeping, times.fetching]
+				.map(|time| time.as_secs_f64().round() as u64)
+		};
+		let breakdown = loop_times.iter().map(seconds).collect::<Vec<_>>();
+		assert_eq!(breakdown, vec![[0, 20, 2], [5, 20, 2]]);
+
+		// Breakdown covers all the time of the loop
+		let total = loop_times
+			.iter()
+			.map(|times| times.idle + times.sleeping + times.fetching)
+			.sum::<Duration>();
+		assert_eq!(total, time.lock().unwrap().duration_since(start));
+	}
+
+	#[tokio::test]
+	async fn test_last_errors() {
+		let config = test_config();
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		// Sending the verified block fails, since there are no block receivers
+		let (block_tx, _) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		let handle = tokio::spawn(run(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status.clone(),
+			CrawlExtensions::default(),
+			TokioClock,
+		));
+
+		let start = SystemTime::now();
+		// Block with invalid dimensions fails to be verified
+		message_tx.send(header_update(1, 0, 4)).unwrap();
+		message_tx.send(header_update(2, 1, 4)).unwrap();
+		drop(message_tx);
+		handle.await.unwrap().unwrap();
+
+		let status = status.read().unwrap();
+		let message = |category| {
+			let error = &status.last_errors[&category];
+			assert!(error.timestamp >= start);
+			error.message.clone()
+		};
+		assert!(message(FailureCategory::Header).starts_with("Header is not valid"));
+		assert_eq!(
+			message(FailureCategory::Fetch),
+			"Failed to fetch 8 of 8 block 2 cells"
+		);
+		assert!(message(FailureCategory::Send).starts_with("Cannot send block verified message"));
+		assert!(!status.last_errors.contains_key(&FailureCategory::Metrics));
+	}
+
+	#[tokio::test]
+	async fn test_refetch_failed_cells() {
+		let config = CrawlConfig {
+			crawl_refetch_attempts: 2,
+			..test_config()
+		};
+
+		let mut client = MockClient::new();
+		let mut sequence = mockall::Sequence::new();
+		// Cells of the first column fail, and then they are fetched on the first re-fetch
+		client
+			.expect_fetch_cells_from_dht()
+			.times(1)
+			.in_sequence(&mut sequence)
+			.returning(move |_, positions| {
+				let (unfetched, fetched): (Vec<Position>, Vec<Position>) =
+					positions.iter().partition(|position| position.col == 0);
+				let fetched = fetched.into_iter().map(cell).collect();
+				Box::pin(async move { (fetched, unfetched, vec![]) })
+			});
+		client
+			.expect_fetch_cells_from_dht()
+			.withf(|_, positions| positions.iter().all(|position| position.col == 0))
+			.times(1)
+			.in_sequence(&mut sequence)
+			.returning(move |_, positions| {
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 2, 4)], client).await;
+		let result = &crawled.results[0];
+		let cells = result.cells.unwrap();
+		assert_eq!(cells.total, 16);
+		assert_eq!(cells.fetched, 16);
+		// Four extended rows with one failed cell each
+		assert_eq!(result.refetched_cells, 4);
+	}
+
+	#[tokio::test]
+	async fn test_oversized_fetch_is_clamped() {
+		// Buggy client which returns every requested cell twice
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(|_, positions| {
+				let fetched = positions
+					.iter()
+					.chain(positions)
+					.copied()
+					.map(cell)
+					.collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+
+		let crawled = crawl_blocks(test_config(), [header_update(1, 1, 4)], client).await;
+		let cells = crawled.results[0].cells.unwrap();
+		assert_eq!(cells.total, 8);
+		assert_eq!(cells.fetched, 8);
+
+		let success_rates = crawled
+			.events
+			.into_iter()
+			.filter_map(|event| match event {
+				OutputEvent::RecordCellSuccessRate(success_rate) => Some(success_rate),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(success_rates, vec![1.0]);
+	}
+
+	#[test]
+	fn test_is_reconstructable() {
+		let dimensions = Dimensions::new(2, 4).unwrap();
+		let positions = |rows: &[u32], cols: u16| {
+			rows.iter()
+				.flat_map(|&row| (0..cols).map(move |col| Position { row, col }))
+				.map(cell)
+				.collect::<Vec<_>>()
+		};
+
+		// Half of the extended rows in every column is sufficient
+		assert!(is_reconstructable(1, dimensions, &positions(&[0, 1], 4)));
+		assert!(is_reconstructable(1, dimensions, &positions(&[1, 3], 4)));
+		assert!(is_reconstructable(
+			1,
+			dimensions,
+			&positions(&[0, 1, 2, 3], 4)
+		));
+		// Insufficient cells in every column
+		assert!(!is_reconstructable(1, dimensions, &positions(&[0], 4)));
+		// Missing columns
+		assert!(!is_reconstructable(
+			1,
+			dimensions,
+			&positions(&[0, 1, 2, 3], 3)
+		));
+		assert!(!is_reconstructable(1, dimensions, &[]));
+	}
+
+	#[tokio::test]
+	async fn test_spawn_shutdown() {
+		let config = test_config();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+		let spawn_crawl = |message_rx, shutdown| {
+			let (_config_tx, config_rx) = watch::channel(config.clone());
+			let (block_tx, _) = broadcast::channel(10);
+			let (result_tx, _) = broadcast::channel(10);
+			let (event_tx, _) = mpsc::unbounded_channel();
+			let client = unavailable_client();
+			spawn(
+				MemoryDB::default(),
+				channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+				client,
+				status.clone(),
+				CrawlExtensions::default(),
+				TokioClock,
+				shutdown,
+			)
+		};
+
+		// Crawl loop is stopped by its handle, without host application shutdown
+		let (message_tx, message_rx) = broadcast::channel::<rpc::OutputEvent>(10);
+		let shutdown = Controller::new();
+		let handle = spawn_crawl(message_rx, shutdown.clone());
+		handle.shutdown("Crawl stopped".to_string());
+		assert_eq!(
+			handle.join().await.unwrap(),
+			Err("Crawl stopped".to_string())
+		);
+		assert!(!shutdown.is_shutdown_triggered());
+		drop(message_tx);
+
+		// Crawl loop is stopped by host application shutdown
+		let (_message_tx, message_rx) = broadcast::channel::<rpc::OutputEvent>(10);
+		let handle = spawn_crawl(message_rx, shutdown.clone());
+		shutdown.trigger_shutdown("Shutdown".to_string()).unwrap();
+		assert_eq!(handle.join().await.unwrap(), Err("Shutdown".to_string()));
+
+		// Crawl loop finishes when the messages sender is dropped
+		let (message_tx, message_rx) = broadcast::channel::<rpc::OutputEvent>(10);
+		let handle = spawn_crawl(message_rx, Controller::new());
+		drop(message_tx);
+		assert_eq!(handle.join().await.unwrap(), Ok(()));
+	}
+
+	#[test_case(None, 0 => None; "rows not crawled")]
+	#[test_case(Some((4, 0)), 0 => None; "no available rows")]
+	#[test_case(Some((4, 4)), 0 => Some(0.0); "all rows fetched")]
+	#[test_case(Some((4, 3)), 1 => Some(0.25); "some rows derived")]
+	#[test_case(Some((4, 0)), 4 => Some(1.0); "all rows derived")]
+	fn test_rows_derived_ratio(rows: Option<(usize, usize)>, derived_rows: usize) -> Option<f64> {
+		let result = CrawlResult {
+			derived_rows,
+			..result(None, rows)
+		};
+		result.rows_derived_ratio()
+	}
+
+	#[test_case(false => FinalBlock { block_number: 1, completed: false }; "fetch aborted")]
+	#[test_case(true => FinalBlock { block_number: 1, completed: true }; "fetch completed")]
+	#[tokio::test]
+	async fn test_shutdown_drain(fetch_finishes: bool) -> FinalBlock {
+		let config = CrawlConfig {
+			crawl_shutdown_drain_ms: if fetch_finishes { 10_000 } else { 50 },
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		let (fetch_tx, mut fetch_rx) = mpsc::unbounded_channel();
+		let release = Arc::new(tokio::sync::Notify::new());
+		let fetch_release = release.clone();
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, positions| {
+				let unfetched = positions.to_vec();
+				let fetch_tx = fetch_tx.clone();
+				let release = fetch_release.clone();
+				Box::pin(async move {
+					fetch_tx.send(()).unwrap();
+					// Fetch never resolves unless it is released
+					release.notified().await;
+					(vec![], unfetched, vec![])
+				})
+			});
+
+		let handle = spawn(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			client,
+			status.clone(),
+			CrawlExtensions::default(),
+			TokioClock,
+			Controller::new(),
+		);
+
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		fetch_rx.recv().await.unwrap();
+		handle.shutdown("Crawl stopped".to_string());
+		if fetch_finishes {
+			release.notify_one();
+		}
+		assert_eq!(
+			handle.join().await.unwrap(),
+			Err("Crawl stopped".to_string())
+		);
+
+		let status = status.read().unwrap();
+		assert_eq!(status.crawling.is_none(), fetch_finishes);
+		status.final_block.unwrap()
+	}
+
+	/// Host application code, which stops the crawler once the given block is crawled
+	async fn crawl_until(crawler: impl CrawlClient, block_number: u32) -> Result<(), String> {
+		let mut results = crawler.subscribe_results();
+		while let Ok(result) = results.recv().await {
+			if result.block_number >= block_number {
+				break;
+			}
+		}
+		crawler.shutdown(format!("Block {block_number} is crawled"));
+		crawler.wait().await
+	}
+
+	#[tokio::test]
+	async fn test_mock_crawl_client() {
+		let (result_tx, _) = broadcast::channel(10);
+		let mut crawler = MockCrawlClient::new();
+		let results = result_tx.clone();
+		crawler
+			.expect_subscribe_results()
+			.times(1)
+			.returning(move || results.subscribe());
+		crawler
+			.expect_shutdown()
+			.withf(|reason| reason == "Block 2 is crawled")
+			.times(1)
+			.return_const(());
+		crawler
+			.expect_wait()
+			.times(1)
+			.returning(|| Box::pin(async { Err("Block 2 is crawled".to_string()) }));
+
+		let host = tokio::spawn(crawl_until(crawler, 2));
+		while result_tx.receiver_count() == 0 {
+			tokio::task::yield_now().await;
+		}
+		for block_number in [1, 2] {
+			let result = CrawlResult {
+				block_number,
+				..result(None, None)
+			};
+			result_tx.send(result).unwrap();
+		}
+		assert_eq!(host.await.unwrap(), Err("Block 2 is crawled".to_string()));
+	}
+
+	#[tokio::test]
+	async fn test_recent_results() {
+		let config = CrawlConfig {
+			crawl_summary_history: 2,
+			..test_config()
+		};
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		let handle = spawn(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+			Controller::new(),
+		);
+		assert!(CrawlClient::recent_results(&handle).is_empty());
+		for block_number in 1..=3 {
+			message_tx.send(header_update(block_number, 1, 4)).unwrap();
+		}
+		drop(message_tx);
+		while !CrawlClient::is_finished(&handle) {
+			tokio::task::yield_now().await;
+		}
+
+		// Only the most recent results are retained
+		let blocks = CrawlClient::recent_results(&handle)
+			.iter()
+			.map(|result| result.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(blocks, vec![2, 3]);
+	}
+
+	#[tokio::test]
+	async fn test_crawl_handle_client() {
+		let config = test_config();
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+
+		let handle = spawn(
+			MemoryDB::default(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			unavailable_client(),
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+			Controller::new(),
+		);
+		let mut results = CrawlClient::subscribe_results(&handle);
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		assert_eq!(results.recv().await.unwrap().block_number, 1);
+		// Fetch failure is recorded before the result is sent
+		let status = CrawlClient::status(&handle);
+		assert!(status.last_errors.contains_key(&FailureCategory::Fetch));
+		assert!(!CrawlClient::is_finished(&handle));
+
+		CrawlClient::shutdown(&handle, "Crawl stopped".to_string());
+		assert_eq!(handle.wait().await, Err("Crawl stopped".to_string()));
+	}
+
+	#[tokio::test]
+	async fn test_resume_interrupted_block() {
+		let config = CrawlConfig {
+			crawl_checkpoint_batch_size: Some(4),
+			..test_config()
+		};
+		let db = MemoryDB::default();
+		let status = Arc::new(RwLock::new(CrawlStatus::new(&config)));
+		let (message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, _result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+
+		// First batch is fetched, and the crawler is stopped while the second batch is being fetched
+		let (fetch_tx, mut fetch_rx) = mpsc::unbounded_channel();
+		let mut client = MockClient::new();
+		let mut sequence = mockall::Sequence::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.times(1)
+			.in_sequence(&mut sequence)
+			.returning(|_, positions| {
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+		client
+			.expect_fetch_cells_from_dht()
+			.times(1)
+			.in_sequence(&mut sequence)
+			.returning(move |_, _| {
+				let fetch_tx = fetch_tx.clone();
+				Box::pin(async move {
+					fetch_tx.send(()).unwrap();
+					std::future::pending().await
+				})
+			});
+
+		let handle = spawn(
+			db.clone(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			client,
+			status.clone(),
+			CrawlExtensions::default(),
+			TokioClock,
+			Controller::new(),
+		);
+		message_tx.send(header_update(1, 1, 4)).unwrap();
+		fetch_rx.recv().await.unwrap();
+		handle.shutdown("Crawler restarted".to_string());
+		handle.join().await.unwrap().unwrap_err();
+
+		let checkpoint = db.get(CrawlCheckpointKey).unwrap();
+		assert_eq!(checkpoint.header.number, 1);
+		assert_eq!(checkpoint.batches, 1);
+		assert_eq!(db.get(CrawlCheckpointBatchKey(0)).unwrap().len(), 4);
+
+		// After restart, only the cells which are not checkpointed are fetched
+		let (_message_tx, message_rx) = broadcast::channel(10);
+		let (_config_tx, config_rx) = watch::channel(config.clone());
+		let (block_tx, _block_rx) = broadcast::channel(10);
+		let (result_tx, mut result_rx) = broadcast::channel(10);
+		let (event_tx, _event_rx) = mpsc::unbounded_channel();
+		let expected = (0..4)
+			.map(|col| Position { row: 1, col })
+			.collect::<Vec<_>>();
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_cells_from_dht()
+			.withf(move |_, positions| positions == expected)
+			.times(1)
+			.returning(|_, positions| {
+				let fetched = positions.iter().copied().map(cell).collect();
+				Box::pin(async move { (fetched, vec![], vec![]) })
+			});
+
+		tokio::spawn(run(
+			db.clone(),
+			channels(message_rx, config_rx, block_tx, result_tx, event_tx),
+			client,
+			status,
+			CrawlExtensions::default(),
+			TokioClock,
+		));
+
+		let result = result_rx.recv().await.unwrap();
+		assert_eq!(result.block_number, 1);
+		assert_eq!(
+			result.cells,
+			Some(CrawlStats {
+				total: 8,
+				fetched: 8
+			})
+		);
+		assert!(db.get(CrawlCheckpointKey).is_none());
+		assert!(db.get(CrawlCheckpointBatchKey(0)).is_none());
+	}
+
+	#[test_case(2, 2, 8 => 78.5714; "all sampled fetched")]
+	#[test_case(4, 3, 8 => 75.7143; "one sampled failed")]
+	#[test_case(5, 5, 8 => 100.0; "more than half fetched")]
+	#[test_case(8, 4, 8 => 0.0; "exactly half fetched")]
+	#[test_case(4, 0, 8 => 0.0; "none fetched")]
+	#[test_case(0, 0, 8 => 0.0; "nothing sampled")]
+	#[test_case(1, 1, 1 => 100.0; "single cell")]
+	#[test_case(10, 10, 1_000_000 => 99.9023; "large matrix")]
+	fn test_availability_confidence(sampled: usize, successful: usize, total: usize) -> f64 {
+		(availability_confidence(sampled, successful, total) * 10_000.0).round() / 10_000.0
+	}
+
+	#[test]
+	fn test_count_fetched_rows() {
+		let row = || Some(vec![0u8; 4]);
+		// Rows 0, 2 and 6 are fetched, row 4 is missing, row 3 is fetched but not requested
+		let fetched_rows = vec![row(), None, row(), row(), None, None, row(), None];
+
+		assert_eq!(
+			count_fetched_rows(&[0, 2, 4, 6], &fetched_rows, |_| true),
+			3
+		);
+		assert_eq!(
+			count_fetched_rows(&[6, 4, 2, 0], &fetched_rows, |_| true),
+			3
+		);
+		assert_eq!(count_fetched_rows(&[4, 5, 7], &fetched_rows, |_| true), 0);
+		// Requested rows out of the fetched range are not counted
+		assert_eq!(count_fetched_rows(&[0, 8, 10], &fetched_rows, |_| true), 1);
+		assert_eq!(count_fetched_rows(&[], &fetched_rows, |_| true), 0);
+		assert_eq!(count_fetched_rows(&[0, 2], &[], |_| true), 0);
+	}
+
+	#[test_case(8, 8 => Some(1.0); "every query fetched")]
+	#[test_case(12, 8 => Some(1.5); "refetched queries")]
+	#[test_case(8, 2 => Some(4.0); "few fetched")]
+	#[test_case(8, 0 => None; "none fetched")]
+	#[test_case(0, 0 => None; "nothing queried")]
+	fn test_queries_per_cell(queries: usize, fetched: usize) -> Option<f64> {
+		queries_per_cell(queries, fetched)
+	}
+
+	#[test]
+	fn test_ring_partition() {
+		let mut config = CrawlConfig {
+			ring: RingOpts {
+				crawl_ring_node: Some("node-1".to_string()),
+				crawl_ring_nodes: vec!["node-2".to_string(), "node-3".to_string()],
+				..Default::default()
+			},
+			..Default::default()
+		};
+		default_partition(&mut config);
+		let ring = Ring::new(["node-1", "node-2", "node-3"]);
+		let expected = ring.partitions("node-1", 20);
+		assert!(!expected.is_empty());
+		assert_eq!(
+			format_partitions(&config.crawl_block_matrix_partition),
+			format_partitions(&expected)
+		);
+
+		// Listing this node among the active nodes doesn't change the assignment
+		config.ring.crawl_ring_nodes.push("node-1".to_string());
+		default_partition(&mut config);
+		assert_eq!(
+			format_partitions(&config.crawl_block_matrix_partition),
+			format_partitions(&expected)
+		);
+	}
+
+	#[test]
+	fn test_count_valid_rows() {
+		let dimensions = Dimensions::new(2, 4).unwrap();
+		let valid = || Some(vec![0u8; 4 * CHUNK_SIZE]);
+		// Row 2 is truncated, and row 4 is empty, so only rows 0 and 6 are valid
+		let fetched_rows = vec![
+			valid(),
+			None,
+			Some(vec![0u8; 3 * CHUNK_SIZE]),
+			None,
+			Some(vec![]),
+			None,
+			valid(),
+			None,
+		];
+
+		let row_indexes = [0, 2, 4, 6];
+		assert_eq!(count_fetched_rows(&row_indexes, &fetched_rows, |_| true), 4);
+		let is_valid = is_valid_row(dimensions);
+		assert_eq!(count_fetched_rows(&row_indexes, &fetched_rows, is_valid), 2);
+	}
+
+	/// Rows result of the 4x4 matrix (8 extended rows, of which rows 0, 2, 4 and 6 are requested),
+	/// with the given rows fetched, and of the given length.
+	///
+	/// Cases encode the assumptions the rows counting relies on:
+	/// - result is indexed by the extended row index, so a row is fetched only if it is at its index,
+	///   and the rows at the mirrored indexes of the reversed result are not counted,
+	/// - rows which are not fetched (`None`) are holes, and they are not counted,
+	/// - rows which are not requested (odd rows, or rows beyond the extended matrix) are ignored,
+	/// - rows beyond the length of a truncated result are not fetched.
+	fn rows_result(
+		fetched: impl IntoIterator<Item = usize>,
+		length: usize,
+	) -> Vec<Option<Vec<u8>>> {
+		let mut result = vec![None; length];
+		for index in fetched {
+			result[index] = Some(vec![0u8; 4 * CHUNK_SIZE]);
+		}
+		result
+	}
+
+	#[test_case(rows_result([0, 2, 4, 6], 8) => 4; "requested rows in order")]
+	#[test_case(rows_result([7, 5, 3, 1], 8) => 0; "reversed rows")]
+	#[test_case(rows_result([0, 6], 8) => 2; "rows with holes")]
+	#[test_case(rows_result(0..8, 8) => 4; "extra odd rows")]
+	#[test_case(rows_result([0, 2, 4, 6, 8, 10], 12) => 4; "extra rows beyond matrix")]
+	#[test_case(rows_result([0, 2], 3) => 2; "truncated result")]
+	#[test_case(vec![] => 0; "empty result")]
+	#[tokio::test]
+	async fn test_rows_result_harness(rows: Vec<Option<Vec<u8>>>) -> usize {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Rows,
+			crawl_validate_rows: true,
+			..test_config()
+		};
+
+		let mut client = MockClient::new();
+		client
+			.expect_fetch_rows_from_dht()
+			.withf(|_, _, row_indexes| row_indexes == [0, 2, 4, 6])
+			.returning(move |_, _, _| {
+				let rows = rows.clone();
+				Box::pin(async move { rows })
+			});
+
+		let crawled = crawl_blocks(config, [header_update(1, 4, 4)], client).await;
+		let rows = crawled.results[0].rows.unwrap();
+		assert_eq!(rows.total, 4);
+		rows.fetched
+	}
+
+	#[test_case(0.123456, None => 0.123456; "full precision")]
+	#[test_case(0.123456, Some(2) => 0.12; "rounded down")]
+	#[test_case(0.125, Some(2) => 0.13; "rounded half up")]
+	#[test_case(2.0 / 3.0, Some(3) => 0.667; "rounded up")]
+	#[test_case(0.6, Some(0) => 1.0; "no decimal places")]
+	#[test_case(1.0, Some(4) => 1.0; "exact value")]
+	fn test_round(value: f64, precision: Option<u8>) -> f64 {
+		round(value, precision)
+	}
+
+	#[test]
+	fn test_confidence_does_not_overflow() {
+		let stats = CrawlStats {
+			total: 10_000,
+			fetched: 10_000,
+		};
+		assert_eq!(stats.confidence(1 << 20), 100.0);
 	}
 }
@@ -0,0 +1,141 @@
+//! Loading of the crawled partition assigned by the coordinator of a centrally managed fleet,
+//! so the nodes don't need the individually configured partitions.
+//!
+//! # Response
+//!
+//! Assignment is fetched with a `GET` request to the configured URL, which identifies the node,
+//! e.g. `http://coordinator:8080/assignments/crawler-1`. Coordinator responds with the `200` status and
+//! the JSON body with the partition in the same format as the configured `crawl_block_matrix_partition`:
+//!
+//! ```json
+//! { "partition": "3-5/20" }
+//! ```
+//!
+//! Unknown fields are ignored, so the coordinator can extend the response.
+//!
+//! # Fallback
+//!
+//! Assignment is loaded once at startup. If the coordinator is unreachable, doesn't respond within the timeout,
+//! responds with a non-success status or with an invalid partition, the configured partition is crawled instead.
+//! Loaded assignment is kept on the configuration reload, while the configured partition is reloaded
+//! if the assignment failed.
+
+use super::format_partitions;
+use crate::types::block_matrix_partition_format;
+use avail_rust::kate_recovery::matrix::Partition;
+use color_eyre::{eyre::eyre, Result};
+use hyper::{body, Client, Uri};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+#[derive(Deserialize)]
+struct Assignment {
+	#[serde(with = "block_matrix_partition_format::range")]
+	partition: Vec<Partition>,
+}
+
+/// Parses the assignment URL, only `http` URLs are supported
+pub fn parse_url(url: &str) -> Result<Uri> {
+	let uri = url.parse::<Uri>()?;
+	if uri.scheme_str() != Some("http") || uri.host().is_none() {
+		return Err(eyre!("Partition assignment URL {url} is not an http URL"));
+	}
+	Ok(uri)
+}
+
+/// Fetches the partition assigned to the node at `url`
+pub async fn fetch_partition(url: &Uri, timeout: Duration) -> Result<Vec<Partition>> {
+	let response = tokio::time::timeout(timeout, Client::new().get(url.clone()))
+		.await
+		.map_err(|_| eyre!("No response in {timeout:?}"))??;
+	let status = response.status();
+	if !status.is_success() {
+		return Err(eyre!("Coordinator responded with {status}"));
+	}
+	let body = tokio::time::timeout(timeout, body::to_bytes(response.into_body()))
+		.await
+		.map_err(|_| eyre!("No response body in {timeout:?}"))??;
+	let assignment = serde_json::from_slice::<Assignment>(&body)?;
+	Ok(assignment.partition)
+}
+
+/// Loads the partition assigned to the node at `url`.
+/// Returns `None` if the assignment fails, so the configured partition is crawled instead.
+pub async fn load_partition(url: &Uri, timeout: Duration) -> Option<Vec<Partition>> {
+	match fetch_partition(url, timeout).await {
+		Ok(partition) => {
+			let assigned = format_partitions(&partition);
+			info!(
+				partition = assigned,
+				"Loaded partition assignment from {url}"
+			);
+			Some(partition)
+		},
+		Err(error) => {
+			warn!("Cannot load partition assignment from {url}, crawling the configured partition: {error}");
+			None
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::net::SocketAddr;
+	use warp::{http::StatusCode, Filter};
+
+	fn serve(status: StatusCode, body: &'static str) -> SocketAddr {
+		let route = warp::get()
+			.and(warp::path!("assignments" / String))
+			.map(move |_node: String| warp::reply::with_status(body, status));
+		let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+		tokio::spawn(server);
+		address
+	}
+
+	fn url(address: SocketAddr) -> Uri {
+		parse_url(&format!("http://{address}/assignments/crawler-1")).unwrap()
+	}
+
+	#[test]
+	fn test_parse_url() {
+		assert!(parse_url("http://coordinator:8080/assignments/crawler-1").is_ok());
+		assert!(parse_url("https://coordinator/assignments/crawler-1").is_err());
+		assert!(parse_url("/assignments/crawler-1").is_err());
+	}
+
+	#[tokio::test]
+	async fn test_load_partition() {
+		let timeout = Duration::from_secs(1);
+
+		let address = serve(
+			StatusCode::OK,
+			r#"{"partition": "3-4/20", "node": "crawler-1"}"#,
+		);
+		let partition = load_partition(&url(address), timeout).await;
+		let expected = block_matrix_partition_format::range::parse("3-4/20").unwrap();
+		assert_eq!(partition, Some(expected));
+
+		// Configured partition is crawled if the assignment fails
+		let address = serve(StatusCode::SERVICE_UNAVAILABLE, "");
+		assert!(load_partition(&url(address), timeout).await.is_none());
+		let address = serve(StatusCode::OK, r#"{"partition": "21/20"}"#);
+		assert!(load_partition(&url(address), timeout).await.is_none());
+		let address = serve(StatusCode::OK, "crawler-1");
+		assert!(load_partition(&url(address), timeout).await.is_none());
+	}
+
+	#[tokio::test]
+	async fn test_unresponsive_coordinator() {
+		let route = warp::get().and_then(|| async {
+			tokio::time::sleep(Duration::from_secs(10)).await;
+			Ok::<_, std::convert::Infallible>(StatusCode::OK)
+		});
+		let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+		tokio::spawn(server);
+
+		let fetched = fetch_partition(&url(address), Duration::from_millis(50)).await;
+		assert!(fetched.is_err());
+	}
+}
@@ -0,0 +1,183 @@
+use avail_rust::{kate_recovery::data::Cell, H256};
+use std::{
+	collections::VecDeque,
+	time::{Duration, Instant},
+};
+
+#[derive(Clone, Debug)]
+struct CachedBlock {
+	block_hash: H256,
+	cached_at: Instant,
+	cells: Vec<Cell>,
+}
+
+/// Cells recently fetched for the blocks, reused when the block is crawled again within the TTL.
+/// At most `max_cells` cells are kept, and the oldest blocks are evicted first.
+#[derive(Clone, Debug)]
+pub struct FetchCache {
+	max_cells: usize,
+	ttl: Duration,
+	cells: usize,
+	blocks: VecDeque<CachedBlock>,
+}
+
+impl FetchCache {
+	pub fn new(max_cells: usize, ttl: Duration) -> Self {
+		Self {
+			max_cells,
+			ttl,
+			cells: 0,
+			blocks: VecDeque::new(),
+		}
+	}
+
+	/// Changes the limits, evicting the blocks which are over the new limits
+	pub fn set_limits(&mut self, max_cells: usize, ttl: Duration) {
+		self.max_cells = max_cells;
+		self.ttl = ttl;
+		self.evict(0);
+	}
+
+	fn evict(&mut self, needed: usize) {
+		while self.cells + needed > self.max_cells {
+			let Some(block) = self.blocks.pop_front() else {
+				break;
+			};
+			self.cells -= block.cells.len();
+		}
+	}
+
+	fn expire(&mut self, now: Instant) {
+		while let Some(block) = self.blocks.front() {
+			if now.saturating_duration_since(block.cached_at) < self.ttl {
+				break;
+			}
+			self.cells -= block.cells.len();
+			self.blocks.pop_front();
+		}
+	}
+
+	/// Returns cells of the block cached within the TTL, if any
+	pub fn get(&mut self, block_hash: H256, now: Instant) -> Option<Vec<Cell>> {
+		self.expire(now);
+		let block = self
+			.blocks
+			.iter()
+			.find(|block| block.block_hash == block_hash)?;
+		Some(block.cells.clone())
+	}
+
+	/// Caches the fetched cells of the block, replacing the previously cached cells of the block.
+	/// Replaced cells keep the time they are cached at, so re-crawls don't extend the TTL.
+	/// Cells are not cached if there are more of them than the cache can keep.
+	pub fn insert(&mut self, block_hash: H256, cells: Vec<Cell>, now: Instant) {
+		let mut cached_at = now;
+		if let Some(index) = self
+			.blocks
+			.iter()
+			.position(|block| block.block_hash == block_hash)
+		{
+			let block = self.blocks.remove(index).expect("Index is valid");
+			self.cells -= block.cells.len();
+			if now.saturating_duration_since(block.cached_at) < self.ttl {
+				cached_at = block.cached_at;
+			}
+		}
+		if cells.is_empty() || cells.len() > self.max_cells {
+			return;
+		}
+		self.expire(now);
+		self.evict(cells.len());
+		self.cells += cells.len();
+		// Blocks are kept in the order they are cached at, for the expiry
+		let index = self
+			.blocks
+			.partition_point(|block| block.cached_at <= cached_at);
+		let block = CachedBlock {
+			block_hash,
+			cached_at,
+			cells,
+		};
+		self.blocks.insert(index, block);
+	}
+
+	/// Returns the number of the cached cells
+	pub fn len(&self) -> usize {
+		self.cells
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.cells == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use avail_rust::kate_recovery::matrix::Position;
+
+	fn cells(count: u16) -> Vec<Cell> {
+		(0..count)
+			.map(|col| Cell {
+				position: Position { row: 0, col },
+				content: [0; 80],
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_fetch_cache() {
+		let now = Instant::now();
+		let (first, second, third) = (
+			H256::repeat_byte(1),
+			H256::repeat_byte(2),
+			H256::repeat_byte(3),
+		);
+		let mut cache = FetchCache::new(4, Duration::from_secs(10));
+		cache.insert(first, cells(2), now);
+		cache.insert(second, cells(2), now + Duration::from_secs(5));
+		assert_eq!(cache.get(first, now).map(|cells| cells.len()), Some(2));
+		assert_eq!(cache.len(), 4);
+
+		// Oldest block is evicted to keep the cells limit
+		cache.insert(third, cells(1), now + Duration::from_secs(5));
+		assert!(cache.get(first, now + Duration::from_secs(5)).is_none());
+		assert_eq!(cache.len(), 3);
+
+		// Blocks over the limit are not cached, and the previously cached cells are replaced
+		cache.insert(second, cells(5), now + Duration::from_secs(5));
+		assert!(cache.get(second, now + Duration::from_secs(5)).is_none());
+		assert_eq!(cache.len(), 1);
+
+		// Blocks expire after the TTL
+		assert!(cache.get(third, now + Duration::from_secs(9)).is_some());
+		assert!(cache.get(third, now + Duration::from_secs(15)).is_none());
+		assert!(cache.is_empty());
+
+		cache.insert(first, cells(3), now);
+		cache.set_limits(2, Duration::from_secs(10));
+		assert!(cache.is_empty());
+	}
+
+	#[test]
+	fn test_fetch_cache_reinsert() {
+		let now = Instant::now();
+		let (first, second) = (H256::repeat_byte(1), H256::repeat_byte(2));
+		let mut cache = FetchCache::new(4, Duration::from_secs(10));
+		cache.insert(first, cells(1), now);
+		cache.insert(second, cells(1), now + Duration::from_secs(5));
+
+		// Re-inserted cells keep the time they are cached at first
+		cache.insert(first, cells(2), now + Duration::from_secs(8));
+		assert_eq!(cache.len(), 3);
+		let cached = cache.get(first, now + Duration::from_secs(9));
+		assert_eq!(cached.map(|cells| cells.len()), Some(2));
+		assert!(cache.get(first, now + Duration::from_secs(10)).is_none());
+		assert!(cache.get(second, now + Duration::from_secs(10)).is_some());
+		assert_eq!(cache.len(), 1);
+
+		// Cells re-inserted after the TTL are cached anew
+		cache.insert(second, cells(1), now + Duration::from_secs(16));
+		assert!(cache.get(second, now + Duration::from_secs(20)).is_some());
+	}
+}
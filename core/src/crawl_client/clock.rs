@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use mockall::automock;
+use std::{
+	sync::Mutex,
+	time::{Duration, Instant, SystemTime},
+};
+
+/// Source of time for the crawl loop, which can be replaced in tests
+/// to observe and skip the sleeps.
+#[async_trait]
+#[automock]
+pub trait Clock: Send + Sync {
+	fn now(&self) -> Instant;
+	/// Wall-clock time, used to measure the age of the block timestamps
+	fn system_now(&self) -> SystemTime;
+	async fn sleep(&self, duration: Duration);
+}
+
+/// Clock backed by the system time and the tokio timer
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioClock;
+
+#[async_trait]
+impl Clock for TokioClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+
+	fn system_now(&self) -> SystemTime {
+		SystemTime::now()
+	}
+
+	async fn sleep(&self, duration: Duration) {
+		tokio::time::sleep(duration).await;
+	}
+}
+
+/// Clock which accumulates the time slept with the wrapped clock
+pub(crate) struct SleepTracker<'a, C> {
+	clock: &'a C,
+	slept: Mutex<Duration>,
+}
+
+impl<'a, C: Clock> SleepTracker<'a, C> {
+	pub(crate) fn new(clock: &'a C) -> Self {
+		Self {
+			clock,
+			slept: Mutex::new(Duration::ZERO),
+		}
+	}
+
+	/// Returns the time slept since the previous call, and resets it
+	pub(crate) fn take_slept(&self) -> Duration {
+		std::mem::take(&mut *self.slept.lock().expect("Lock acquired"))
+	}
+}
+
+#[async_trait]
+impl<C: Clock> Clock for SleepTracker<'_, C> {
+	fn now(&self) -> Instant {
+		self.clock.now()
+	}
+
+	fn system_now(&self) -> SystemTime {
+		self.clock.system_now()
+	}
+
+	async fn sleep(&self, duration: Duration) {
+		let start = self.clock.now();
+		self.clock.sleep(duration).await;
+		let slept = self.clock.now().saturating_duration_since(start);
+		*self.slept.lock().expect("Lock acquired") += slept;
+	}
+}
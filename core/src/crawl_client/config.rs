@@ -0,0 +1,934 @@
+use super::{
+	format_partitions, BlockNumbers, ClockSkew, Coverage, CrawlMode, CrawlResult, FetchOrder,
+	FetchPriority, FirstBlock, ForwardOrder, Sampling, ZeroRateAction, ENTIRE_BLOCK, ROWS_STEP,
+};
+use crate::types::{block_matrix_partition_format, option_duration_seconds_format};
+use avail_rust::kate_recovery::matrix::Partition;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::time::Duration;
+use tracing::info;
+
+/// Overrides of the crawl options for the cells, unset options fall back to the top-level options
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(default)]
+pub struct CellCrawlOpts {
+	/// Crawl delay of the cells in seconds (default: `crawl_block_delay`)
+	pub delay: Option<u64>,
+	/// Number of attempts to re-fetch the cells which failed to be fetched (default: `crawl_refetch_attempts`)
+	pub refetch_attempts: Option<u32>,
+}
+
+/// Overrides of the crawl options for the rows, unset options fall back to the top-level options
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RowCrawlOpts {
+	/// Crawl delay of the rows in seconds (default: `crawl_block_delay`)
+	pub delay: Option<u64>,
+	/// Number of attempts to re-fetch the rows which failed to be fetched or validated (default: `crawl_refetch_attempts`)
+	pub refetch_attempts: Option<u32>,
+}
+
+/// Crawl client configuration
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrawlConfig {
+	/// Crawl block delay. Increment to ensure large block crawling (default: 20)
+	pub crawl_block_delay: u64,
+	/// Crawl block delay in blocks, block is crawled once the header of the block K blocks later is received (default: None)
+	/// Delay in blocks takes precedence, and the crawl block delay in seconds is not applied if it is set.
+	pub crawl_block_delay_blocks: Option<u32>,
+	/// Crawl block mode. Available modes are "cells", "rows" and "both" (default: "cells")
+	pub crawl_block_mode: CrawlMode,
+	/// Fraction and number of the block matrix part to crawl (e.g. 2/20 means second 1/20 part of a matrix) (default: 1/1)
+	/// Range of the parts can be crawled as well (e.g. 3-5/20 means third, fourth and fifth 1/20 part of a matrix),
+	/// and it can be specified as a percentage range (e.g. 10%-15% is the same as 3/20)
+	/// Entire block is crawled if no partition is configured.
+	#[serde(with = "block_matrix_partition_format::range")]
+	pub crawl_block_matrix_partition: Vec<Partition>,
+	#[serde(flatten)]
+	pub availability: AvailabilityCriteria,
+	/// Maximum number of peers to keep fetch statistics for (default: 1000)
+	pub crawl_peer_stats_limit: usize,
+	/// Crawl cells of the extension (parity) rows, in addition to the original data rows (default: true)
+	/// Only the cells are affected. Rows crawling fetches the original data rows regardless of this option,
+	/// since only the data rows are stored in the DHT.
+	pub crawl_include_extension_rows: bool,
+	/// Pause after each crawled block in milliseconds, used to smooth resource usage on small nodes (default: 0)
+	pub crawl_post_block_cooldown_ms: u64,
+	/// Number of attempts to re-fetch the cells and rows which failed to be fetched, before moving to the next block (default: 0)
+	pub crawl_refetch_attempts: u32,
+	/// Overrides of the crawl delay and re-fetch attempts for the cells (default: no overrides)
+	pub crawl_cells: CellCrawlOpts,
+	/// Overrides of the crawl delay and re-fetch attempts for the rows (default: no overrides)
+	/// In `both` mode, the block is crawled after the shorter delay, and the other part waits for its own delay.
+	pub crawl_rows: RowCrawlOpts,
+	/// Sampling strategy of the crawled cells and rows. Available strategies are "partition" and "random" (default: "partition")
+	/// Custom strategy passed with the [`CrawlExtensions`](super::CrawlExtensions) takes precedence over the configured one.
+	pub crawl_sampling: Sampling,
+	/// Maximum number of cells and rows crawled per block with the random sampling (default: 100)
+	pub crawl_random_sample_size: usize,
+	/// Order of fetching the sampled cells and rows. Available orders are "sampled", "original-first" and "center-out" (default: "sampled")
+	/// Cells and rows fetched first are confirmed even if the fetch is cut short. See [`FetchPriority`] for the orders.
+	pub crawl_fetch_priority: FetchPriority,
+	/// Attempt to reconstruct the block from the fetched cells, which is CPU intensive (default: false)
+	/// Only the entire block crawling can result with a reconstructable block.
+	pub crawl_reconstruct: bool,
+	/// Number of decimal places of the recorded success rates, used to reduce metrics payload (default: full precision)
+	pub crawl_success_rate_precision: Option<u8>,
+	/// Minimum number of requested cells to record the cells success rate of the block (default: 0)
+	/// Success rate of the smaller blocks is not recorded, so a few missing cells don't trigger success rate alerts.
+	/// Availability of the smaller blocks is still evaluated, so available blocks count is not affected.
+	pub crawl_min_cells_for_rate: usize,
+	/// Columns crawled in addition to the partition cells with the partition sampling (default: empty)
+	/// Cells of both partition and columns are crawled once. Columns are not included in the reported coverage.
+	pub crawl_columns: Vec<u16>,
+	/// Time in milliseconds given to the block being crawled to finish on shutdown, before it is aborted (default: 0)
+	/// Applies only to the crawl loop started with [`spawn`](super::spawn). Blocks waiting for the crawl delay are aborted immediately.
+	pub crawl_shutdown_drain_ms: u64,
+	#[serde(flatten)]
+	pub escalation: EscalationOpts,
+	/// Fetch the cells missing for the reconstruction of the block, if it cannot be reconstructed from the crawled cells (default: false)
+	/// Cells are fetched only with the reconstruction enabled, and only if the crawl is escalated when the escalation sample is set,
+	/// so the block is sampled, escalated if the sample is below the trigger, and reconstructed if needed.
+	/// Cells fetched for the reconstruction are not included in the cells success rate.
+	pub crawl_reconstruct_fetch: bool,
+	#[serde(flatten)]
+	pub chaos: ChaosOpts,
+	/// Number of cells fetched at once, with the fetched cells checkpointed after each batch, so the interrupted crawl
+	/// of a large block is resumed on restart (default: None, cells are fetched at once without checkpoints)
+	/// Checkpoints are used only with the partition sampling. Cells fetched before the restart are not reconstructed.
+	pub crawl_checkpoint_batch_size: Option<usize>,
+	/// Maximum age of the block to crawl in seconds, older blocks are skipped (default: None, all blocks are crawled)
+	/// Age is measured from the block timestamp, derived from the BABE slot of the header,
+	/// or from the time the header is received if the header doesn't carry the slot.
+	/// Headers received while the previous blocks are crawled are skipped without the delay once they are too old,
+	/// so the crawler which falls behind catches up to the recent blocks instead of crawling the whole backlog.
+	#[serde(with = "option_duration_seconds_format")]
+	pub crawl_max_block_age: Option<Duration>,
+	/// Number of the queued headers over which the backlog is skipped, and the crawl continues from the latest header
+	/// (default: None, the whole backlog is crawled)
+	/// Skipped headers are not crawled later, so they are reported by the gap scan if they are within the scan window.
+	pub crawl_fast_forward_backlog: Option<usize>,
+	/// Numbers and ranges of the only blocks to crawl, e.g. `["100", "200-300"]` (default: None, all blocks are crawled)
+	pub crawl_allow_blocks: Option<BlockNumbers>,
+	/// Numbers and ranges of the blocks which are not crawled, e.g. `["150-160"]` (default: empty)
+	/// Denylist takes precedence over the allowlist, so the block in both lists is not crawled.
+	pub crawl_deny_blocks: BlockNumbers,
+	#[serde(flatten)]
+	pub zero_rate: ZeroRateOpts,
+	/// Handling of the first block received after the startup. Available handlings are "initialize", "record" and "skip" (default: "initialize")
+	/// Idle time of the crawl loop is measured since the startup for the first block, so it is recorded only with "record".
+	pub crawl_first_block: FirstBlock,
+	/// Fail on the options which are ignored in the configured mode and sampling, instead of warning (default: false)
+	/// See [`CrawlConfig::ignored_options`] for the validated combinations.
+	pub crawl_strict_validation: bool,
+	#[serde(flatten)]
+	pub offenders: OffendersOpts,
+	/// Count only the fetched rows of the expected length as fetched (default: false, every fetched row is counted)
+	/// Expected length of the row is the matrix width times the chunk size.
+	pub crawl_validate_rows: bool,
+	/// Number of the recently crawled blocks kept in the status for the summary and [`CrawlClient::recent_results`](super::CrawlClient::recent_results) (default: 20)
+	pub crawl_summary_history: usize,
+	/// Order of fetching cells and rows in the "both" mode. Available orders are "cells-first" and "rows-first" (default: "cells-first")
+	pub crawl_fetch_order: FetchOrder,
+	/// Number of the recently received header hashes kept to skip the duplicated headers, 0 disables (default: 16)
+	/// The same header can be delivered more than once in quick succession, and it is crawled once.
+	pub crawl_duplicate_headers_cache: usize,
+	#[serde(flatten)]
+	pub forward: ForwardOpts,
+	#[serde(flatten)]
+	pub ring: RingOpts,
+	/// Handling of the headers received in the future. Available strategies are "clamp" and "immediate" (default: "clamp")
+	/// Receive time of the header is clamped to the current time with "clamp", and the crawl delay is not awaited with "immediate".
+	pub crawl_clock_skew: ClockSkew,
+	#[serde(flatten)]
+	pub gap_scan: GapScanOpts,
+	#[serde(flatten)]
+	pub fetch_cache: FetchCacheOpts,
+	#[serde(flatten)]
+	pub heatmap: HeatmapOpts,
+	/// Factor of the matrix size change between the consecutive blocks which resets the accumulated
+	/// failing rows and heatmap, since they are invalid for the new dimensions, 0 disables (default: 16)
+	/// Change is detected if the number of the matrix cells grows or shrinks at least by the factor.
+	pub crawl_dimension_change_factor: f64,
+}
+
+impl CrawlConfig {
+	/// Returns descriptions of the configured options which are ignored in the configured mode and sampling:
+	/// - partition other than the entire block in `rows` mode, since rows are selected regardless of the partition,
+	/// - partition other than the entire block with the random sampling, which samples the whole matrix,
+	/// - columns in `rows` mode, or with the random sampling, since columns are crawled with the partition sampling only,
+	/// - reconstruction in `rows` mode, since the block is reconstructed from the fetched cells,
+	/// - reconstruction fetch without the reconstruction,
+	/// - cells overrides in `rows` mode, and rows overrides in `cells` mode,
+	/// - checkpoints with the random sampling, since random sample is different after the restart,
+	/// - fetch order other than the default outside of `both` mode, since only cells or only rows are fetched.
+	pub fn ignored_options(&self) -> Vec<String> {
+		let rows_only = self.crawl_block_mode == CrawlMode::Rows;
+		let random = self.crawl_sampling == Sampling::Random;
+		let partition = format_partitions(&self.crawl_block_matrix_partition);
+		let entire_block = partition == format_partitions(&[ENTIRE_BLOCK]);
+
+		let mut ignored = vec![];
+		if !entire_block && rows_only {
+			ignored.push(format!("Partition {partition} is ignored in rows mode"));
+		}
+		if !entire_block && random {
+			ignored.push(format!(
+				"Partition {partition} is ignored with random sampling"
+			));
+		}
+		if !self.crawl_columns.is_empty() && (rows_only || random) {
+			ignored.push("Columns are ignored in rows mode and with random sampling".to_string());
+		}
+		if self.crawl_reconstruct && rows_only {
+			ignored.push("Reconstruction is ignored in rows mode".to_string());
+		}
+		if self.crawl_reconstruct_fetch && !self.crawl_reconstruct {
+			ignored.push("Reconstruction fetch is ignored without reconstruction".to_string());
+		}
+		if self.crawl_cells != CellCrawlOpts::default() && rows_only {
+			ignored.push("Cells crawl options are ignored in rows mode".to_string());
+		}
+		if self.crawl_rows != RowCrawlOpts::default() && self.crawl_block_mode == CrawlMode::Cells {
+			ignored.push("Rows crawl options are ignored in cells mode".to_string());
+		}
+		if self.crawl_checkpoint_batch_size.is_some() && random {
+			ignored.push("Checkpoints are ignored with random sampling".to_string());
+		}
+		if self.crawl_fetch_order != FetchOrder::default()
+			&& self.crawl_block_mode != CrawlMode::Both
+		{
+			ignored.push("Fetch order is ignored outside of both mode".to_string());
+		}
+		ignored
+	}
+
+	/// Returns descriptions of the configured values which are out of the valid range:
+	/// - partition number outside of `1..=fraction`,
+	/// - success rates, fractions and decay outside of `[0, 1]`,
+	/// - minimum confidence outside of `[0, 100]` percents,
+	/// - dimension change factor between 0 and 1, since every block would be detected as changed,
+	/// - ring fraction of zero, if the ring node is configured,
+	/// - zero rate blocks of zero, since the action would be taken without the zero rate blocks,
+	/// - random sample size of zero with the random sampling, since no cells or rows are sampled.
+	pub fn invalid_options(&self) -> Vec<String> {
+		let mut invalid = vec![];
+		for &Partition { number, fraction } in &self.crawl_block_matrix_partition {
+			if number == 0 || number > fraction {
+				invalid.push(format!("Partition {number}/{fraction} is out of bounds"));
+			}
+		}
+
+		let AvailabilityCriteria {
+			crawl_min_cells_success_rate,
+			crawl_min_rows_success_rate,
+			crawl_min_confidence,
+		} = self.availability;
+		let fractions = [
+			(
+				"Minimum cells success rate",
+				Some(crawl_min_cells_success_rate),
+			),
+			(
+				"Minimum rows success rate",
+				Some(crawl_min_rows_success_rate),
+			),
+			(
+				"Escalation trigger",
+				Some(self.escalation.crawl_escalation_trigger),
+			),
+			("Chaos drop fraction", self.chaos.crawl_chaos_drop_fraction),
+			(
+				"Offenders decay",
+				Some(self.offenders.crawl_offenders_decay),
+			),
+		];
+		// Empty sample would be escalated for every block, so the block is sampled with at least one cell
+		if let Some(sample) = (self.escalation.crawl_escalation_sample)
+			.filter(|sample| !(*sample > 0.0 && *sample <= 1.0))
+		{
+			invalid.push(format!("Escalation sample {sample} is not in (0, 1]"));
+		}
+		for (name, value) in fractions {
+			if let Some(value) = value.filter(|value| !(0.0..=1.0).contains(value)) {
+				invalid.push(format!("{name} {value} is not in [0, 1]"));
+			}
+		}
+		if let Some(confidence) =
+			crawl_min_confidence.filter(|confidence| !(0.0..=100.0).contains(confidence))
+		{
+			invalid.push(format!(
+				"Minimum confidence {confidence} is not in [0, 100]"
+			));
+		}
+
+		let factor = self.crawl_dimension_change_factor;
+		if factor != 0.0 && factor < 1.0 {
+			invalid.push(format!(
+				"Dimension change factor {factor} is neither 0 nor at least 1"
+			));
+		}
+
+		if self.ring.crawl_ring_node.is_some() && self.ring.crawl_ring_fraction == 0 {
+			invalid.push("Ring fraction is zero".to_string());
+		}
+
+		if self.zero_rate.crawl_zero_rate_blocks == Some(0) {
+			invalid.push("Zero rate blocks is zero".to_string());
+		}
+
+		if self.crawl_sampling == Sampling::Random && self.crawl_random_sample_size == 0 {
+			invalid.push("Random sample size is zero".to_string());
+		}
+		invalid
+	}
+
+	/// Returns all invalid options (see [`CrawlConfig::invalid_options`]) at once.
+	/// Ignored options (see [`CrawlConfig::ignored_options`]) are returned as invalid only if the strict
+	/// validation is enabled, otherwise they are left to the caller to log as warnings.
+	pub fn validate(&self) -> std::result::Result<(), Vec<String>> {
+		let mut problems = self.invalid_options();
+		if self.crawl_strict_validation {
+			problems.extend(self.ignored_options());
+		}
+		if problems.is_empty() {
+			Ok(())
+		} else {
+			Err(problems)
+		}
+	}
+
+	/// Returns the crawl delay of the cells in seconds, with the override applied
+	pub fn cells_delay(&self) -> u64 {
+		self.crawl_cells.delay.unwrap_or(self.crawl_block_delay)
+	}
+
+	/// Returns the crawl delay of the rows in seconds, with the override applied
+	pub fn rows_delay(&self) -> u64 {
+		self.crawl_rows.delay.unwrap_or(self.crawl_block_delay)
+	}
+
+	/// Returns the number of the cells re-fetch attempts, with the override applied
+	pub fn cells_refetch_attempts(&self) -> u32 {
+		self.crawl_cells
+			.refetch_attempts
+			.unwrap_or(self.crawl_refetch_attempts)
+	}
+
+	/// Returns the number of the rows re-fetch attempts, with the override applied
+	pub fn rows_refetch_attempts(&self) -> u32 {
+		self.crawl_rows
+			.refetch_attempts
+			.unwrap_or(self.crawl_refetch_attempts)
+	}
+
+	/// Returns the crawl delay of the block in seconds, which is the shortest delay of the crawled parts
+	pub(crate) fn block_delay(&self) -> u64 {
+		match self.crawl_block_mode {
+			CrawlMode::Cells => self.cells_delay(),
+			CrawlMode::Rows => self.rows_delay(),
+			CrawlMode::Both => self.cells_delay().min(self.rows_delay()),
+		}
+	}
+
+	/// Returns the fraction of the extended matrix crawled with this configuration.
+	/// Partitions are expected to be of equal size, so the number of the partition is not taken into account.
+	/// Coverage of the random sampling depends on the block dimensions, so it is not reported.
+	pub fn coverage(&self) -> Coverage {
+		let mode = self.crawl_block_mode;
+		if self.crawl_sampling != Sampling::Partition {
+			return Coverage {
+				cells: None,
+				rows: None,
+			};
+		}
+
+		let cells = matches!(mode, CrawlMode::Cells | CrawlMode::Both).then(|| {
+			let partitions = &self.crawl_block_matrix_partition;
+			let fraction = partitions.first().map_or(1, |partition| partition.fraction);
+			let partition = partitions.len() as f64 / fraction as f64;
+			// Extension rows are half of the extended matrix rows
+			let rows = if self.crawl_include_extension_rows {
+				1.0
+			} else {
+				0.5
+			};
+			partition * rows
+		});
+
+		// Only the original data rows are fetched, which are every other row of the extended matrix
+		let rows =
+			matches!(mode, CrawlMode::Rows | CrawlMode::Both).then(|| 1.0 / ROWS_STEP as f64);
+
+		Coverage { cells, rows }
+	}
+}
+
+impl Default for CrawlConfig {
+	fn default() -> Self {
+		Self {
+			crawl_block_delay: 20,
+			crawl_block_delay_blocks: None,
+			crawl_block_mode: CrawlMode::Cells,
+			crawl_block_matrix_partition: vec![ENTIRE_BLOCK],
+			availability: Default::default(),
+			crawl_peer_stats_limit: 1000,
+			crawl_include_extension_rows: true,
+			crawl_post_block_cooldown_ms: 0,
+			crawl_refetch_attempts: 0,
+			crawl_cells: CellCrawlOpts::default(),
+			crawl_rows: RowCrawlOpts::default(),
+			crawl_sampling: Sampling::Partition,
+			crawl_random_sample_size: 100,
+			crawl_fetch_priority: FetchPriority::Sampled,
+			crawl_reconstruct: false,
+			crawl_success_rate_precision: None,
+			crawl_min_cells_for_rate: 0,
+			crawl_columns: vec![],
+			crawl_shutdown_drain_ms: 0,
+			escalation: Default::default(),
+			crawl_reconstruct_fetch: false,
+			chaos: Default::default(),
+			crawl_checkpoint_batch_size: None,
+			crawl_max_block_age: None,
+			crawl_fast_forward_backlog: None,
+			crawl_allow_blocks: None,
+			crawl_deny_blocks: BlockNumbers::default(),
+			zero_rate: Default::default(),
+			crawl_first_block: FirstBlock::Initialize,
+			crawl_strict_validation: false,
+			offenders: Default::default(),
+			crawl_validate_rows: false,
+			crawl_summary_history: 20,
+			crawl_fetch_order: FetchOrder::CellsFirst,
+			crawl_duplicate_headers_cache: 16,
+			forward: Default::default(),
+			ring: Default::default(),
+			crawl_clock_skew: ClockSkew::Clamp,
+			gap_scan: Default::default(),
+			fetch_cache: Default::default(),
+			heatmap: Default::default(),
+			crawl_dimension_change_factor: 16.0,
+		}
+	}
+}
+
+/// Escalation of the sampled block crawl to the remaining cells
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EscalationOpts {
+	/// Fraction of the block cells fetched first, with the remaining cells fetched only if the sample
+	/// success rate is below the escalation trigger (default: None, all cells are fetched at once)
+	pub crawl_escalation_sample: Option<f64>,
+	/// Sample success rate below which the remaining cells are fetched (default: 0.9)
+	pub crawl_escalation_trigger: f64,
+}
+
+impl Default for EscalationOpts {
+	fn default() -> Self {
+		Self {
+			crawl_escalation_sample: None,
+			crawl_escalation_trigger: 0.9,
+		}
+	}
+}
+
+/// Chaos testing of the availability alerting, disabled by default
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChaosOpts {
+	/// CHAOS TESTING ONLY: fraction of the fetched cells and rows dropped before computing the success rates,
+	/// to simulate unavailability and verify alerting against a live node (default: None, disabled)
+	pub crawl_chaos_drop_fraction: Option<f64>,
+}
+
+/// Handling of the consecutive blocks with none of the requested cells and rows fetched
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZeroRateOpts {
+	/// Number of the consecutive crawled blocks with none of the requested cells and rows fetched,
+	/// after which the zero rate action is taken (default: None, disabled)
+	/// Action is repeated after each further such number of blocks, until a block is fetched at least partially.
+	pub crawl_zero_rate_blocks: Option<u32>,
+	/// Action on the consecutive zero rate blocks. Available actions are "log" and "exit" (default: "log")
+	pub crawl_zero_rate_action: ZeroRateAction,
+}
+
+/// Tracking of the matrix rows with the most failed cells
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OffendersOpts {
+	/// Maximum number of the matrix rows with the most failed cells kept in the status (default: 100)
+	pub crawl_offenders_limit: usize,
+	/// Factor applied to the failed cells counts of the rows on each compaction (default: 0.5)
+	pub crawl_offenders_decay: f64,
+	/// Number of blocks between the compactions of the failed cells counts, 0 disables decay (default: 100)
+	/// Compaction is done after crawling the block which number is a multiple of the interval.
+	pub crawl_offenders_compaction_interval: u32,
+}
+
+impl Default for OffendersOpts {
+	fn default() -> Self {
+		Self {
+			crawl_offenders_limit: 100,
+			crawl_offenders_decay: 0.5,
+			crawl_offenders_compaction_interval: 100,
+		}
+	}
+}
+
+/// Forwarding of the crawled blocks to the block verified subscribers
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForwardOpts {
+	/// Forward the crawled blocks to the block verified subscribers (default: true)
+	/// Disabled forwarding is intended for the metrics-only nodes without the block consumers.
+	pub crawl_forward_blocks: bool,
+	/// Maximum number of the crawled blocks buffered to forward the blocks in the block number order, 0 disables (default: 0)
+	/// Block is held until the previous blocks are forwarded, or until the buffer is full, at the cost of the forwarding latency.
+	/// Skipped blocks, e.g. denied, too old or fast-forwarded, are not waited for.
+	pub crawl_ordered_forward_buffer: usize,
+	/// Order of crawling and forwarding the block. Available orders are "crawl-then-send" and "send-then-crawl" (default: "crawl-then-send")
+	/// With "send-then-crawl", the block is forwarded even if its crawling is cancelled, so the crawl result may never follow.
+	pub crawl_forward_order: ForwardOrder,
+}
+
+impl Default for ForwardOpts {
+	fn default() -> Self {
+		Self {
+			crawl_forward_blocks: true,
+			crawl_ordered_forward_buffer: 0,
+			crawl_forward_order: ForwardOrder::CrawlThenSend,
+		}
+	}
+}
+
+/// Assignment of the crawled partitions by the consistent hashing ring of the crawler fleet
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RingOpts {
+	/// Id of this node in the consistent hashing ring of the crawler fleet (default: None)
+	/// If set, crawled partitions are assigned by the ring instead of the configured block matrix partition,
+	/// and the ring is rebalanced when the active nodes change on configuration reload.
+	pub crawl_ring_node: Option<String>,
+	/// Ids of the active nodes of the ring, this node is always included (default: empty)
+	pub crawl_ring_nodes: Vec<String>,
+	/// Number of the block matrix partitions distributed over the ring nodes (default: 20)
+	pub crawl_ring_fraction: u8,
+}
+
+impl Default for RingOpts {
+	fn default() -> Self {
+		Self {
+			crawl_ring_node: None,
+			crawl_ring_nodes: vec![],
+			crawl_ring_fraction: 20,
+		}
+	}
+}
+
+/// Scans for the recent blocks which are not crawled
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GapScanOpts {
+	/// Number of blocks between the scans for the recent blocks which are not crawled, 0 disables (default: 100)
+	/// Scan is done after the block which number is a multiple of the interval is received.
+	pub crawl_gap_scan_interval: u32,
+	/// Number of the recent blocks scanned for the blocks which are not crawled (default: 100)
+	/// Blocks are not crawled if they are skipped (e.g. too old or without the header extension), or never received.
+	pub crawl_gap_scan_window: u32,
+}
+
+impl Default for GapScanOpts {
+	fn default() -> Self {
+		Self {
+			crawl_gap_scan_interval: 100,
+			crawl_gap_scan_window: 100,
+		}
+	}
+}
+
+/// Cache of the fetched cells reused when the block is crawled again
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FetchCacheOpts {
+	/// Maximum number of the fetched cells cached for the blocks crawled again, 0 disables (default: 0)
+	/// Each cached cell takes about 80 bytes, and the oldest blocks are evicted first.
+	pub crawl_fetch_cache_cells: usize,
+	/// Time in seconds for which the fetched cells are reused when the block is crawled again (default: 600)
+	pub crawl_fetch_cache_ttl: u64,
+}
+
+impl Default for FetchCacheOpts {
+	fn default() -> Self {
+		Self {
+			crawl_fetch_cache_cells: 0,
+			crawl_fetch_cache_ttl: 600,
+		}
+	}
+}
+
+/// Availability statistics of the extended matrix regions
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeatmapOpts {
+	/// Size of the square buckets of the extended matrix cells counted in the availability heatmap, 0 disables (default: 0)
+	/// Bucket counts cells of `size` rows and `size` columns, see [`heatmap`](super::heatmap) for the exported heatmap.
+	pub crawl_heatmap_bucket_size: u32,
+	/// Size of the square matrix regions of `size` rows and `size` columns, over which the variance of the cells success rate
+	/// within the block is computed, 0 disables (default: 16)
+	/// High variance signals the localized unavailability, which is hidden by the average success rate of the block.
+	pub crawl_region_size: u32,
+}
+
+impl Default for HeatmapOpts {
+	fn default() -> Self {
+		Self {
+			crawl_heatmap_bucket_size: 0,
+			crawl_region_size: 16,
+		}
+	}
+}
+
+/// Criteria used to decide whether a crawled block is available.
+///
+/// Only the criteria of the crawled aspects are applied, e.g. in `cells` mode
+/// the rows success rate is not taken into account.
+/// Default criteria require at least half of the requested cells and rows to be fetched,
+/// which is the minimum needed to reconstruct the 2x extended matrix, and don't check confidence.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AvailabilityCriteria {
+	/// Minimum cells success rate for block to be considered available (default: 0.5)
+	pub crawl_min_cells_success_rate: f64,
+	/// Minimum rows success rate for block to be considered available (default: 0.5)
+	pub crawl_min_rows_success_rate: f64,
+	/// Minimum confidence calculated from the requested and fetched cells, in percents (default: None)
+	/// Confidence is the same as the recorded availability confidence, see [`availability_confidence`](super::availability_confidence).
+	pub crawl_min_confidence: Option<f64>,
+}
+
+impl Default for AvailabilityCriteria {
+	fn default() -> Self {
+		Self {
+			crawl_min_cells_success_rate: 0.5,
+			crawl_min_rows_success_rate: 0.5,
+			crawl_min_confidence: None,
+		}
+	}
+}
+
+impl AvailabilityCriteria {
+	/// Returns `true` if crawl result of the block with `matrix_cells` cells of the extended matrix
+	/// satisfies all applicable criteria.
+	pub fn is_available(&self, result: &CrawlResult, matrix_cells: usize) -> bool {
+		let cells_available = result.cells.is_none_or(|cells| {
+			let confidence_reached = self
+				.crawl_min_confidence
+				.is_none_or(|min_confidence| cells.confidence(matrix_cells) >= min_confidence);
+			cells.success_rate() >= self.crawl_min_cells_success_rate && confidence_reached
+		});
+
+		let rows_available = result
+			.rows
+			.is_none_or(|rows| rows.success_rate() >= self.crawl_min_rows_success_rate);
+
+		cells_available && rows_available
+	}
+}
+
+/// Returns the options which differ between the configurations, as they are configured
+pub fn config_changes(current: &CrawlConfig, new: &CrawlConfig) -> Vec<String> {
+	let options = |config| match serde_json::to_value(config) {
+		Ok(Value::Object(options)) => options,
+		_ => Map::new(),
+	};
+	let (current, new) = (options(current), options(new));
+	new.iter()
+		.filter(|(name, value)| current.get(*name) != Some(value))
+		.map(|(name, value)| {
+			let previous = current.get(name).unwrap_or(&Value::Null);
+			format!("{name} changed from {previous} to {value}")
+		})
+		.collect()
+}
+
+/// Logs the differences between configurations, and returns `true` if crawled partition has changed.
+pub fn log_config_changes(current: &CrawlConfig, new: &CrawlConfig) -> bool {
+	for change in config_changes(current, new) {
+		info!("Crawl configuration {change}");
+	}
+	format_partitions(&current.crawl_block_matrix_partition)
+		!= format_partitions(&new.crawl_block_matrix_partition)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	#[test]
+	fn test_part_overrides() {
+		let config = CrawlConfig {
+			crawl_block_delay: 20,
+			crawl_refetch_attempts: 1,
+			..Default::default()
+		};
+		assert_eq!((config.cells_delay(), config.rows_delay()), (20, 20));
+		assert_eq!(config.cells_refetch_attempts(), 1);
+		assert_eq!(config.rows_refetch_attempts(), 1);
+
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Both,
+			crawl_cells: CellCrawlOpts {
+				delay: Some(30),
+				refetch_attempts: None,
+			},
+			crawl_rows: RowCrawlOpts {
+				delay: None,
+				refetch_attempts: Some(3),
+			},
+			..config
+		};
+		assert_eq!((config.cells_delay(), config.rows_delay()), (30, 20));
+		assert_eq!(config.block_delay(), 20);
+		assert_eq!(config.cells_refetch_attempts(), 1);
+		assert_eq!(config.rows_refetch_attempts(), 3);
+	}
+
+	#[test_case(CrawlMode::Cells, "1/1", true => (Some(1.0), None); "entire block cells")]
+	#[test_case(CrawlMode::Cells, "1/4", true => (Some(0.25), None); "quarter block cells")]
+	#[test_case(CrawlMode::Cells, "1/4", false => (Some(0.125), None); "quarter block original cells")]
+	#[test_case(CrawlMode::Rows, "1/4", true => (None, Some(0.5)); "rows ignore partition")]
+	#[test_case(CrawlMode::Both, "1/20", true => (Some(0.05), Some(0.5)); "both modes")]
+	#[test_case(CrawlMode::Both, "1/2", false => (Some(0.25), Some(0.5)); "both modes original cells")]
+	#[test_case(CrawlMode::Rows, "1/1", false => (None, Some(0.5)); "rows without extension rows")]
+	#[test_case(CrawlMode::Cells, "3-5/20", true => (Some(0.15), None); "partition range")]
+	fn test_coverage(
+		mode: CrawlMode,
+		partition: &str,
+		include_extension_rows: bool,
+	) -> (Option<f64>, Option<f64>) {
+		let config = CrawlConfig {
+			crawl_block_mode: mode,
+			crawl_block_matrix_partition: block_matrix_partition_format::range::parse(partition)
+				.unwrap(),
+			crawl_include_extension_rows: include_extension_rows,
+			..Default::default()
+		};
+		let Coverage { cells, rows } = config.coverage();
+		(cells, rows)
+	}
+
+	#[test_case(CrawlConfig::default() => 0; "default")]
+	#[test_case(CrawlConfig {
+		crawl_block_mode: CrawlMode::Rows,
+		crawl_block_matrix_partition: vec![Partition { number: 1, fraction: 2 }],
+		..Default::default()
+	} => 1; "partition in rows mode")]
+	#[test_case(CrawlConfig {
+		crawl_block_mode: CrawlMode::Rows,
+		..Default::default()
+	} => 0; "entire block in rows mode")]
+	#[test_case(CrawlConfig {
+		crawl_sampling: Sampling::Random,
+		crawl_block_matrix_partition: vec![Partition { number: 1, fraction: 2 }],
+		..Default::default()
+	} => 1; "partition with random sampling")]
+	#[test_case(CrawlConfig {
+		crawl_block_mode: CrawlMode::Rows,
+		crawl_columns: vec![1],
+		..Default::default()
+	} => 1; "columns in rows mode")]
+	#[test_case(CrawlConfig {
+		crawl_sampling: Sampling::Random,
+		crawl_columns: vec![1],
+		..Default::default()
+	} => 1; "columns with random sampling")]
+	#[test_case(CrawlConfig {
+		crawl_block_mode: CrawlMode::Both,
+		crawl_columns: vec![1],
+		crawl_reconstruct: true,
+		..Default::default()
+	} => 0; "columns and reconstruction in both mode")]
+	#[test_case(CrawlConfig {
+		crawl_block_mode: CrawlMode::Rows,
+		crawl_reconstruct: true,
+		..Default::default()
+	} => 1; "reconstruction in rows mode")]
+	#[test_case(CrawlConfig {
+		crawl_sampling: Sampling::Random,
+		crawl_checkpoint_batch_size: Some(100),
+		..Default::default()
+	} => 1; "checkpoints with random sampling")]
+	#[test_case(CrawlConfig {
+		crawl_fetch_order: FetchOrder::RowsFirst,
+		..Default::default()
+	} => 1; "fetch order in cells mode")]
+	#[test_case(CrawlConfig {
+		crawl_rows: RowCrawlOpts { refetch_attempts: Some(1), ..Default::default() },
+		..Default::default()
+	} => 1; "rows options in cells mode")]
+	#[test_case(CrawlConfig {
+		crawl_block_mode: CrawlMode::Rows,
+		crawl_cells: CellCrawlOpts { delay: Some(1), ..Default::default() },
+		..Default::default()
+	} => 1; "cells options in rows mode")]
+	fn test_ignored_options(config: CrawlConfig) -> usize {
+		config.ignored_options().len()
+	}
+
+	#[test_case(false, false => Ok(()); "valid lenient")]
+	#[test_case(false, true => Ok(()); "valid strict")]
+	#[test_case(true, false => Ok(()); "invalid lenient")]
+	#[test_case(true, true => Err(vec!["Reconstruction is ignored in rows mode".to_string()]); "invalid strict")]
+	fn test_validate_strict(invalid: bool, strict: bool) -> Result<(), Vec<String>> {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Rows,
+			crawl_reconstruct: invalid,
+			crawl_strict_validation: strict,
+			..Default::default()
+		};
+		config.validate()
+	}
+
+	#[test_case(CrawlConfig::default() => Vec::<String>::new(); "default")]
+	#[test_case(CrawlConfig {
+		crawl_block_matrix_partition: vec![
+			Partition { number: 0, fraction: 20 },
+			Partition { number: 21, fraction: 20 },
+		],
+		..Default::default()
+	} => vec![
+		"Partition 0/20 is out of bounds",
+		"Partition 21/20 is out of bounds",
+	]; "partition bounds")]
+	#[test_case(CrawlConfig {
+		availability: AvailabilityCriteria {
+			crawl_min_cells_success_rate: 1.5,
+			crawl_min_rows_success_rate: -0.5,
+			crawl_min_confidence: Some(101.0),
+		},
+		..Default::default()
+	} => vec![
+		"Minimum cells success rate 1.5 is not in [0, 1]",
+		"Minimum rows success rate -0.5 is not in [0, 1]",
+		"Minimum confidence 101 is not in [0, 100]",
+	]; "availability criteria")]
+	#[test_case(CrawlConfig {
+		escalation: EscalationOpts {
+			crawl_escalation_sample: Some(2.0),
+			crawl_escalation_trigger: 1.1,
+		},
+		..Default::default()
+	} => vec![
+		"Escalation sample 2 is not in (0, 1]",
+		"Escalation trigger 1.1 is not in [0, 1]",
+	]; "escalation")]
+	#[test_case(CrawlConfig {
+		escalation: EscalationOpts {
+			crawl_escalation_sample: Some(0.0),
+			..Default::default()
+		},
+		..Default::default()
+	} => vec!["Escalation sample 0 is not in (0, 1]"]; "empty escalation sample")]
+	#[test_case(CrawlConfig {
+		chaos: ChaosOpts {
+			crawl_chaos_drop_fraction: Some(-1.0),
+		},
+		offenders: OffendersOpts {
+			crawl_offenders_decay: 2.0,
+			..Default::default()
+		},
+		..Default::default()
+	} => vec![
+		"Chaos drop fraction -1 is not in [0, 1]",
+		"Offenders decay 2 is not in [0, 1]",
+	]; "chaos and decay")]
+	#[test_case(CrawlConfig {
+		crawl_sampling: Sampling::Random,
+		crawl_random_sample_size: 0,
+		..Default::default()
+	} => vec!["Random sample size is zero"]; "random sample size")]
+	#[test_case(CrawlConfig {
+		ring: RingOpts {
+			crawl_ring_node: Some("node-1".to_string()),
+			crawl_ring_fraction: 0,
+			..Default::default()
+		},
+		..Default::default()
+	} => vec!["Ring fraction is zero"]; "ring fraction")]
+	#[test_case(CrawlConfig {
+		zero_rate: ZeroRateOpts {
+			crawl_zero_rate_blocks: Some(0),
+			..Default::default()
+		},
+		..Default::default()
+	} => vec!["Zero rate blocks is zero"]; "zero rate blocks")]
+	#[test_case(CrawlConfig {
+		crawl_dimension_change_factor: 0.5,
+		..Default::default()
+	} => vec!["Dimension change factor 0.5 is neither 0 nor at least 1"]; "dimension change factor")]
+	#[test_case(CrawlConfig {
+		crawl_block_mode: CrawlMode::Rows,
+		crawl_reconstruct: true,
+		offenders: OffendersOpts {
+			crawl_offenders_decay: 2.0,
+			..Default::default()
+		},
+		crawl_strict_validation: true,
+		..Default::default()
+	} => vec![
+		"Offenders decay 2 is not in [0, 1]",
+		"Reconstruction is ignored in rows mode",
+	]; "all problems at once")]
+	fn test_validate(config: CrawlConfig) -> Vec<String> {
+		config.validate().err().unwrap_or_default()
+	}
+
+	#[test]
+	fn test_config_changes() {
+		let current = CrawlConfig::default();
+		assert!(config_changes(&current, &current).is_empty());
+
+		let new = CrawlConfig {
+			crawl_block_delay: 30,
+			crawl_block_matrix_partition: vec![Partition {
+				number: 2,
+				fraction: 20,
+			}],
+			crawl_max_block_age: Some(Duration::from_secs(60)),
+			..Default::default()
+		};
+		let mut changes = config_changes(&current, &new);
+		changes.sort();
+		assert_eq!(
+			changes,
+			vec![
+				"crawl_block_delay changed from 20 to 30",
+				"crawl_block_matrix_partition changed from \"1/1\" to \"2/20\"",
+				"crawl_max_block_age changed from null to 60",
+			]
+		);
+		assert!(log_config_changes(&current, &new));
+		assert!(!log_config_changes(&current, &current));
+	}
+
+	#[test]
+	fn test_flattened_options() {
+		let json = r#"{"crawl_fetch_cache_cells": 10, "crawl_ring_node": "node-1", "crawl_block_delay": 5}"#;
+		let config: CrawlConfig = serde_json::from_str(json).unwrap();
+		assert_eq!(config.crawl_block_delay, 5);
+		assert_eq!(config.fetch_cache.crawl_fetch_cache_cells, 10);
+		assert_eq!(config.fetch_cache.crawl_fetch_cache_ttl, 600);
+		assert_eq!(config.ring.crawl_ring_node.as_deref(), Some("node-1"));
+		assert_eq!(config.ring.crawl_ring_fraction, 20);
+		assert_eq!(config.offenders, OffendersOpts::default());
+
+		let options = serde_json::to_value(&config).unwrap();
+		assert_eq!(options["crawl_fetch_cache_cells"], 10);
+		assert_eq!(options["crawl_gap_scan_window"], 100);
+	}
+}
@@ -0,0 +1,267 @@
+//! Writing of the crawl results as CSV rows, for the analysis in a spreadsheet.
+//!
+//! # Columns
+//!
+//! ```csv
+//! block_number,block_hash,mode,partition,cells_total,cells_fetched,cells_rate,rows_total,rows_fetched,rows_rate,elapsed_ms,timestamp
+//! 10,0x…,cells,1/20,16,4,0.25,,,,1500,1700000021.500
+//! ```
+//!
+//! - `mode` and `partition` are configured when the block is crawled, in the configuration format
+//! - `cells_*` and `rows_*` are empty if cells or rows are not crawled in the configured mode
+//! - `elapsed_ms` is the crawling time in milliseconds
+//! - `timestamp` is the wall-clock time the crawling is completed at, in seconds since the Unix epoch
+//!
+//! Header row is written once, so the rows appended to the existing file after the restart follow the same header.
+//! Column order is stable, and new columns are only appended at the end.
+//! Fields are quoted if they contain a comma, a quote or a line break, and quotes are doubled.
+
+use super::{format_partitions, CrawlConfig, CrawlMode, CrawlResult, CrawlStats};
+use std::{
+	borrow::Cow,
+	fs::OpenOptions,
+	io::{self, Write},
+	time::UNIX_EPOCH,
+};
+use tokio::sync::{
+	broadcast::{self, error::RecvError},
+	watch,
+};
+use tracing::{info, warn};
+
+/// Header row of the crawl results CSV
+pub const HEADER: &str = "block_number,block_hash,mode,partition,cells_total,cells_fetched,cells_rate,rows_total,rows_fetched,rows_rate,elapsed_ms,timestamp";
+
+/// Path which writes the CSV to the standard output instead of a file
+pub const STDOUT: &str = "-";
+
+fn escape(field: &str) -> Cow<'_, str> {
+	if !field.contains([',', '"', '\n', '\r']) {
+		return Cow::Borrowed(field);
+	}
+	Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+}
+
+fn mode_name(mode: CrawlMode) -> &'static str {
+	match mode {
+		CrawlMode::Rows => "rows",
+		CrawlMode::Cells => "cells",
+		CrawlMode::Both => "both",
+	}
+}
+
+fn stats_fields(stats: Option<CrawlStats>) -> [String; 3] {
+	let Some(stats) = stats else {
+		return Default::default();
+	};
+	let rate = if stats.total == 0 {
+		String::new()
+	} else {
+		stats.success_rate().to_string()
+	};
+	[stats.total.to_string(), stats.fetched.to_string(), rate]
+}
+
+/// Encodes the crawl result as a CSV row, without the line break
+pub fn encode_row(result: &CrawlResult, config: &CrawlConfig) -> String {
+	let timestamp = result
+		.crawled_at
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs_f64();
+	let [cells_total, cells_fetched, cells_rate] = stats_fields(result.cells);
+	let [rows_total, rows_fetched, rows_rate] = stats_fields(result.rows);
+	let fields = [
+		result.block_number.to_string(),
+		format!("{:?}", result.block_hash),
+		mode_name(config.crawl_block_mode).to_string(),
+		format_partitions(&config.crawl_block_matrix_partition),
+		cells_total,
+		cells_fetched,
+		cells_rate,
+		rows_total,
+		rows_fetched,
+		rows_rate,
+		result.elapsed.as_millis().to_string(),
+		format!("{timestamp:.3}"),
+	];
+	fields
+		.iter()
+		.map(|field| escape(field))
+		.collect::<Vec<_>>()
+		.join(",")
+}
+
+/// Writer of the crawl results CSV rows, flushed after each row
+pub struct CsvWriter<W> {
+	writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+	/// Creates the writer, writing the header row if `write_header` is set
+	pub fn new(mut writer: W, write_header: bool) -> io::Result<Self> {
+		if write_header {
+			writeln!(writer, "{HEADER}")?;
+			writer.flush()?;
+		}
+		Ok(Self { writer })
+	}
+
+	/// Writes the crawl result row
+	pub fn write(&mut self, result: &CrawlResult, config: &CrawlConfig) -> io::Result<()> {
+		writeln!(self.writer, "{}", encode_row(result, config))?;
+		self.writer.flush()
+	}
+}
+
+/// Opens the CSV writer to the standard output for the [`STDOUT`] path, or to the file at `path`.
+/// Rows are appended to the existing file, and the header row is written only if the file is empty.
+pub fn open(path: &str) -> io::Result<CsvWriter<Box<dyn Write + Send>>> {
+	if path == STDOUT {
+		return CsvWriter::new(Box::new(io::stdout()), true);
+	}
+	let file = OpenOptions::new().create(true).append(true).open(path)?;
+	let is_empty = file.metadata()?.len() == 0;
+	CsvWriter::new(Box::new(file), is_empty)
+}
+
+/// Writes crawl results received on `result_receiver` as CSV rows, until the result sender is dropped
+/// or the writing fails. Mode and partition columns are taken from the current configuration.
+pub async fn write_results<W: Write>(
+	mut writer: CsvWriter<W>,
+	mut result_receiver: broadcast::Receiver<CrawlResult>,
+	config_receiver: watch::Receiver<CrawlConfig>,
+) {
+	info!("Writing crawl results as CSV");
+	loop {
+		let result = match result_receiver.recv().await {
+			Ok(result) => result,
+			Err(RecvError::Lagged(skipped)) => {
+				warn!(skipped, "Crawl results CSV is lagging behind");
+				continue;
+			},
+			Err(RecvError::Closed) => return,
+		};
+		let config = config_receiver.borrow().clone();
+		if let Err(error) = writer.write(&result, &config) {
+			warn!(
+				block_number = result.block_number,
+				"Cannot write crawl results CSV row, stopping: {error}"
+			);
+			return;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::block_matrix_partition_format;
+	use avail_rust::H256;
+	use std::{fs, time::Duration};
+
+	fn result(
+		block_number: u32,
+		cells: Option<CrawlStats>,
+		rows: Option<CrawlStats>,
+	) -> CrawlResult {
+		CrawlResult {
+			block_number,
+			block_hash: H256::repeat_byte(1),
+			cells,
+			rows,
+			refetched_cells: 0,
+			reconstructable: None,
+			derived_rows: 0,
+			elapsed: Duration::from_millis(1500),
+			received_at: UNIX_EPOCH,
+			crawled_at: UNIX_EPOCH + Duration::from_millis(1_700_000_021_500),
+			available: true,
+		}
+	}
+
+	#[test]
+	fn test_escape() {
+		assert_eq!(escape("1/20"), "1/20");
+		assert_eq!(escape("1/20,3/20"), "\"1/20,3/20\"");
+		assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+		assert_eq!(escape("a\nb"), "\"a\nb\"");
+	}
+
+	#[tokio::test]
+	async fn test_write_results() {
+		let config = CrawlConfig {
+			crawl_block_mode: CrawlMode::Both,
+			crawl_block_matrix_partition: block_matrix_partition_format::range::parse("1-2/20")
+				.unwrap(),
+			..Default::default()
+		};
+		let (_config_tx, config_rx) = watch::channel(config);
+		let (result_tx, result_rx) = broadcast::channel(10);
+
+		let cells = CrawlStats {
+			total: 16,
+			fetched: 4,
+		};
+		let rows = CrawlStats {
+			total: 2,
+			fetched: 2,
+		};
+		let empty = CrawlStats {
+			total: 0,
+			fetched: 0,
+		};
+		result_tx.send(result(10, Some(cells), Some(rows))).unwrap();
+		result_tx.send(result(11, Some(cells), None)).unwrap();
+		result_tx.send(result(12, Some(empty), None)).unwrap();
+		drop(result_tx);
+
+		let mut output = vec![];
+		let writer = CsvWriter::new(&mut output, true).unwrap();
+		write_results(writer, result_rx, config_rx).await;
+
+		let text = String::from_utf8(output).unwrap();
+		let hash = format!("{:?}", H256::repeat_byte(1));
+		let expected = [
+			HEADER.to_string(),
+			format!("10,{hash},both,1-2/20,16,4,0.25,2,2,1,1500,1700000021.500"),
+			format!("11,{hash},both,1-2/20,16,4,0.25,,,,1500,1700000021.500"),
+			// Rate of the block without the requested cells is empty instead of NaN
+			format!("12,{hash},both,1-2/20,0,0,,,,,1500,1700000021.500"),
+		];
+		assert_eq!(text.lines().collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn test_open() {
+		let path = std::env::temp_dir().join(format!("crawl-{}.csv", std::process::id()));
+		let path_str = path.to_str().unwrap();
+		let config = CrawlConfig::default();
+		let cells = CrawlStats {
+			total: 4,
+			fetched: 4,
+		};
+
+		let mut writer = open(path_str).unwrap();
+		writer
+			.write(&result(1, Some(cells), None), &config)
+			.unwrap();
+		drop(writer);
+
+		// Header is not repeated when the rows are appended after the restart
+		let mut writer = open(path_str).unwrap();
+		writer
+			.write(&result(2, Some(cells), None), &config)
+			.unwrap();
+		drop(writer);
+
+		let text = fs::read_to_string(&path).unwrap();
+		let lines = text.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 3);
+		assert_eq!(lines[0], HEADER);
+		assert!(lines[1].starts_with("1,") && lines[2].starts_with("2,"));
+		assert_eq!(lines[1].split(',').count(), HEADER.split(',').count());
+
+		fs::remove_file(path).unwrap();
+	}
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Block numbers given as the individual numbers and the inclusive ranges, e.g. `["100", "200-300"]`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct BlockNumbers(Vec<RangeInclusive<u32>>);
+
+impl BlockNumbers {
+	pub fn contains(&self, block_number: u32) -> bool {
+		self.0.iter().any(|range| range.contains(&block_number))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+fn parse(value: &str) -> Result<RangeInclusive<u32>, String> {
+	let number = |value: &str| {
+		value
+			.trim()
+			.parse::<u32>()
+			.map_err(|_| format!("Invalid block number: {value:?}"))
+	};
+	let Some((start, end)) = value.split_once('-') else {
+		let block_number = number(value)?;
+		return Ok(block_number..=block_number);
+	};
+	let (start, end) = (number(start)?, number(end)?);
+	if start > end {
+		return Err(format!("Invalid block range: {value}"));
+	}
+	Ok(start..=end)
+}
+
+impl TryFrom<Vec<String>> for BlockNumbers {
+	type Error = String;
+
+	fn try_from(values: Vec<String>) -> Result<Self, Self::Error> {
+		values
+			.iter()
+			.map(|value| parse(value))
+			.collect::<Result<_, _>>()
+			.map(BlockNumbers)
+	}
+}
+
+impl From<BlockNumbers> for Vec<String> {
+	fn from(BlockNumbers(ranges): BlockNumbers) -> Self {
+		ranges
+			.into_iter()
+			.map(|range| match (range.start(), range.end()) {
+				(start, end) if start == end => start.to_string(),
+				(start, end) => format!("{start}-{end}"),
+			})
+			.collect()
+	}
+}
+
+/// Returns `true` if the block is denied, or if the allowlist is set and the block is not allowed.
+/// Denylist takes precedence, so the block which is both allowed and denied is not crawled.
+pub fn is_denied(allow: Option<&BlockNumbers>, deny: &BlockNumbers, block_number: u32) -> bool {
+	deny.contains(block_number) || allow.is_some_and(|allow| !allow.contains(block_number))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn numbers(values: &[&str]) -> BlockNumbers {
+		let values = values
+			.iter()
+			.map(|value| value.to_string())
+			.collect::<Vec<_>>();
+		BlockNumbers::try_from(values).unwrap()
+	}
+
+	#[test]
+	fn test_block_numbers() {
+		let blocks = numbers(&["5", "10-20", " 30 - 30 "]);
+		assert!(blocks.contains(5));
+		assert!(blocks.contains(10) && blocks.contains(20));
+		assert!(blocks.contains(30));
+		assert!(!blocks.contains(6) && !blocks.contains(21));
+
+		let formatted: Vec<String> = blocks.into();
+		assert_eq!(formatted, vec!["5", "10-20", "30"]);
+
+		for invalid in ["", "x", "20-10", "1-", "-1"] {
+			assert!(BlockNumbers::try_from(vec![invalid.to_string()]).is_err());
+		}
+
+		let deserialized = serde_json::from_str::<BlockNumbers>(r#"["1", "2-3"]"#).unwrap();
+		assert_eq!(deserialized, numbers(&["1", "2-3"]));
+		assert!(serde_json::from_str::<BlockNumbers>(r#"["3-2"]"#).is_err());
+	}
+}
@@ -0,0 +1,114 @@
+use super::partition_positions;
+use avail_rust::kate_recovery::matrix::{Dimensions, Partition};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Coverage of the extended matrix by the partitions assigned to the fleet nodes, in percents of the matrix cells
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FleetCoverage {
+	/// Cells crawled by at least one node
+	pub coverage: f64,
+	/// Cells crawled by more than one node
+	pub overlap: f64,
+	/// Cells crawled by none of the nodes
+	pub gaps: f64,
+}
+
+impl FleetCoverage {
+	/// Returns `true` if the fleet crawls every cell, and at most `overlap_tolerance` percents of the cells are crawled more than once
+	pub fn is_valid(&self, overlap_tolerance: f64) -> bool {
+		self.gaps == 0.0 && self.overlap <= overlap_tolerance
+	}
+}
+
+/// Calculates coverage of the matrix of the given dimensions, with the partitions assigned to each node of the fleet.
+/// Partitions of the same node which overlap are counted once, as the node crawls the cell once.
+pub fn fleet_coverage(dimensions: &Dimensions, assignments: &[Vec<Partition>]) -> FleetCoverage {
+	let mut nodes_per_cell = HashMap::new();
+	for partitions in assignments {
+		let mut positions = partitions
+			.iter()
+			.flat_map(|partition| partition_positions(dimensions, partition))
+			.collect::<Vec<_>>();
+		positions.sort_by_key(|position| (position.row, position.col));
+		positions.dedup();
+		for position in positions {
+			*nodes_per_cell.entry(position).or_insert(0u32) += 1;
+		}
+	}
+
+	let total = f64::from(dimensions.extended_size());
+	let covered = nodes_per_cell.len() as f64;
+	let overlapping = nodes_per_cell.values().filter(|&&nodes| nodes > 1).count() as f64;
+	FleetCoverage {
+		coverage: 100.0 * covered / total,
+		overlap: 100.0 * overlapping / total,
+		gaps: 100.0 * (total - covered) / total,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::block_matrix_partition_format::range::parse;
+
+	fn coverage(assignments: &[&str]) -> FleetCoverage {
+		let dimensions = Dimensions::new(4, 5).unwrap();
+		let assignments = assignments
+			.iter()
+			.map(|partition| parse(partition).unwrap())
+			.collect::<Vec<_>>();
+		fleet_coverage(&dimensions, &assignments)
+	}
+
+	#[test]
+	fn test_perfect_fleet() {
+		let perfect = coverage(&["1-2/4", "3/4", "4/4"]);
+		assert_eq!(
+			perfect,
+			FleetCoverage {
+				coverage: 100.0,
+				overlap: 0.0,
+				gaps: 0.0
+			}
+		);
+		assert!(perfect.is_valid(0.0));
+	}
+
+	#[test]
+	fn test_overlapping_fleet() {
+		let overlapping = coverage(&["1-3/4", "3-4/4"]);
+		assert_eq!(overlapping.coverage, 100.0);
+		assert_eq!(overlapping.overlap, 25.0);
+		assert_eq!(overlapping.gaps, 0.0);
+		assert!(!overlapping.is_valid(10.0));
+		assert!(overlapping.is_valid(25.0));
+
+		// Overlapping partitions of the same node are not counted as the fleet overlap
+		let dimensions = Dimensions::new(4, 5).unwrap();
+		let node = vec![
+			Partition {
+				number: 1,
+				fraction: 2,
+			},
+			Partition {
+				number: 1,
+				fraction: 4,
+			},
+		];
+		let other = parse("2/2").unwrap();
+		let fleet = fleet_coverage(&dimensions, &[node, other]);
+		assert_eq!(fleet.overlap, 0.0);
+		assert_eq!(fleet.coverage, 100.0);
+	}
+
+	#[test]
+	fn test_gapped_fleet() {
+		let gapped = coverage(&["1/4", "3/4"]);
+		assert_eq!(gapped.coverage, 50.0);
+		assert_eq!(gapped.overlap, 0.0);
+		assert_eq!(gapped.gaps, 50.0);
+		assert!(!gapped.is_valid(100.0));
+		assert_eq!(coverage(&[]).gaps, 100.0);
+	}
+}
@@ -0,0 +1,93 @@
+use std::collections::BTreeSet;
+
+/// Numbers of the blocks crawled within the window of the recent blocks,
+/// used to find the blocks which are skipped or never received.
+#[derive(Clone, Debug)]
+pub struct CrawledBlocks {
+	window: u32,
+	/// First crawled block, so the blocks before the crawler is started are not reported
+	first: Option<u32>,
+	blocks: BTreeSet<u32>,
+}
+
+impl CrawledBlocks {
+	pub fn new(window: u32) -> Self {
+		Self {
+			window,
+			first: None,
+			blocks: BTreeSet::new(),
+		}
+	}
+
+	/// Changes the window size, forgetting the blocks which are out of the window
+	pub fn set_window(&mut self, window: u32) {
+		self.window = window;
+		self.prune();
+	}
+
+	pub fn record(&mut self, block_number: u32) {
+		self.first = Some(
+			self.first
+				.map_or(block_number, |first| first.min(block_number)),
+		);
+		self.blocks.insert(block_number);
+		self.prune();
+	}
+
+	/// Returns the first block of the window which ends with the latest crawled block
+	fn start(&self) -> Option<u32> {
+		let latest = *self.blocks.last()?;
+		let start = latest.saturating_sub(self.window.saturating_sub(1));
+		Some(start.max(self.first?))
+	}
+
+	fn prune(&mut self) {
+		if let Some(start) = self.start() {
+			self.blocks = self.blocks.split_off(&start);
+		}
+	}
+
+	/// Returns numbers of the blocks of the window which are not crawled.
+	/// Window ends with the latest crawled block, and it doesn't start before the first crawled block.
+	pub fn gaps(&self) -> Vec<u32> {
+		let (Some(start), Some(&latest)) = (self.start(), self.blocks.last()) else {
+			return vec![];
+		};
+		(start..=latest)
+			.filter(|block_number| !self.blocks.contains(block_number))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_gaps() {
+		let mut crawled = CrawledBlocks::new(5);
+		assert!(crawled.gaps().is_empty());
+
+		for block_number in [1, 2, 4, 7] {
+			crawled.record(block_number);
+		}
+		// Window covers blocks 3 to 7, and the older blocks are forgotten
+		assert_eq!(crawled.gaps(), vec![3, 5, 6]);
+
+		for block_number in [5, 6, 8, 9] {
+			crawled.record(block_number);
+		}
+		assert!(crawled.gaps().is_empty());
+
+		crawled.set_window(2);
+		assert!(crawled.gaps().is_empty());
+		crawled.record(11);
+		assert_eq!(crawled.gaps(), vec![10]);
+
+		// Blocks before the first crawled block are not reported
+		let mut crawled = CrawledBlocks::new(10);
+		crawled.record(20);
+		crawled.record(22);
+		assert_eq!(crawled.gaps(), vec![21]);
+	}
+}
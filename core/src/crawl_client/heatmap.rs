@@ -0,0 +1,213 @@
+//! Availability heatmap of the extended matrix regions, accumulated over the crawled blocks,
+//! which shows the regions which are chronically under-available.
+//!
+//! Cells are counted in the square buckets of `bucket_size` rows and columns, so the memory is bounded
+//! by the number of buckets instead of the number of cells of the largest matrix.
+//!
+//! # Schema
+//!
+//! ```json
+//! {
+//!   "bucket_size": 16,
+//!   "buckets": [
+//!     { "row": 0, "col": 1, "fetched": 120, "unfetched": 8, "success_rate": 0.9375 }
+//!   ]
+//! }
+//! ```
+//!
+//! - `row` and `col` are the indexes of the bucket, which covers the cells from the row `row * bucket_size`
+//!   and the column `col * bucket_size`
+//! - `fetched` and `unfetched` are the numbers of the cells of the bucket fetched and failed to fetch since the start,
+//!   and `success_rate` is the fraction of the fetched cells
+//! - `buckets` are ordered by row and column, and buckets without the crawled cells are omitted
+//!
+//! The same buckets are used within a single block by [`region_rate_variance`], as the matrix regions.
+
+use avail_rust::kate_recovery::matrix::Position;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Counts {
+	fetched: u64,
+	unfetched: u64,
+}
+
+impl Counts {
+	fn success_rate(&self) -> f64 {
+		self.fetched as f64 / (self.fetched + self.unfetched) as f64
+	}
+}
+
+/// Fetch counts of the extended matrix cells, accumulated in the buckets
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+	bucket_size: u32,
+	buckets: BTreeMap<(u32, u32), Counts>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HeatmapBucket {
+	pub row: u32,
+	pub col: u32,
+	pub fetched: u64,
+	pub unfetched: u64,
+	pub success_rate: f64,
+}
+
+/// Exported heatmap, see the [module documentation](self) for the schema
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HeatmapExport {
+	pub bucket_size: u32,
+	pub buckets: Vec<HeatmapBucket>,
+}
+
+impl Heatmap {
+	pub fn new(bucket_size: u32) -> Self {
+		Self {
+			bucket_size,
+			buckets: BTreeMap::new(),
+		}
+	}
+
+	/// Changes the bucket size, discarding the counts of the previous buckets
+	pub fn set_bucket_size(&mut self, bucket_size: u32) {
+		self.bucket_size = bucket_size;
+		self.clear();
+	}
+
+	/// Discards the counts of all buckets
+	pub fn clear(&mut self) {
+		self.buckets.clear();
+	}
+
+	fn bucket(&mut self, position: &Position) -> &mut Counts {
+		let size = self.bucket_size.max(1);
+		let key = (position.row / size, u32::from(position.col) / size);
+		self.buckets.entry(key).or_default()
+	}
+
+	/// Counts the fetched and unfetched cells of the block, nothing is counted if the bucket size is 0
+	pub fn record<'a>(
+		&mut self,
+		fetched: impl IntoIterator<Item = &'a Position>,
+		unfetched: impl IntoIterator<Item = &'a Position>,
+	) {
+		if self.bucket_size == 0 {
+			return;
+		}
+		for position in fetched {
+			self.bucket(position).fetched += 1;
+		}
+		for position in unfetched {
+			self.bucket(position).unfetched += 1;
+		}
+	}
+
+	pub fn export(&self) -> HeatmapExport {
+		let buckets = self
+			.buckets
+			.iter()
+			.map(|(&(row, col), counts)| HeatmapBucket {
+				row,
+				col,
+				fetched: counts.fetched,
+				unfetched: counts.unfetched,
+				success_rate: counts.success_rate(),
+			})
+			.collect();
+		HeatmapExport {
+			bucket_size: self.bucket_size,
+			buckets,
+		}
+	}
+}
+
+/// Returns the variance of the success rates of the block matrix regions of `region_size` rows and columns,
+/// over the regions with the crawled cells. High variance means that the unavailability is localized,
+/// even if the success rate of the block is high. Returns `None` if the region size is 0,
+/// or if the cells of fewer than 2 regions are crawled.
+pub fn region_rate_variance<'a>(
+	region_size: u32,
+	fetched: impl IntoIterator<Item = &'a Position>,
+	unfetched: impl IntoIterator<Item = &'a Position>,
+) -> Option<f64> {
+	let mut regions = Heatmap::new(region_size);
+	regions.record(fetched, unfetched);
+	let rates = regions
+		.buckets
+		.values()
+		.map(Counts::success_rate)
+		.collect::<Vec<_>>();
+	if rates.len() < 2 {
+		return None;
+	}
+	let count = rates.len() as f64;
+	let mean = rates.iter().sum::<f64>() / count;
+	let variance = rates.iter().map(|rate| (rate - mean).powi(2)).sum::<f64>() / count;
+	Some(variance)
+}
+
+impl HeatmapExport {
+	/// Serializes the heatmap to the JSON document
+	pub fn to_json(&self) -> serde_json::Result<String> {
+		serde_json::to_string(self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn position(row: u32, col: u16) -> Position {
+		Position { row, col }
+	}
+
+	#[test]
+	fn test_heatmap() {
+		let mut heatmap = Heatmap::new(2);
+		heatmap.record(&[position(0, 0), position(1, 1)], &[position(0, 2)]);
+		heatmap.record(
+			&[position(0, 1), position(3, 3)],
+			&[position(1, 3), position(2, 2)],
+		);
+
+		let bucket = |row, col, fetched, unfetched, success_rate| HeatmapBucket {
+			row,
+			col,
+			fetched,
+			unfetched,
+			success_rate,
+		};
+		let expected = HeatmapExport {
+			bucket_size: 2,
+			buckets: vec![
+				bucket(0, 0, 3, 0, 1.0),
+				bucket(0, 1, 0, 2, 0.0),
+				bucket(1, 1, 1, 1, 0.5),
+			],
+		};
+		assert_eq!(heatmap.export(), expected);
+
+		let json = serde_json::from_str::<serde_json::Value>(&heatmap.export().to_json().unwrap());
+		assert_eq!(json.unwrap()["buckets"][2]["success_rate"], 0.5);
+
+		heatmap.set_bucket_size(0);
+		heatmap.record(&[position(0, 0)], &[]);
+		assert!(heatmap.export().buckets.is_empty());
+	}
+
+	#[test]
+	fn test_region_rate_variance() {
+		let fetched = [position(0, 0), position(0, 1), position(1, 0)];
+		let unfetched = [position(0, 2), position(1, 2), position(1, 3)];
+		// Regions are fetched entirely and not at all, with the average success rate of 0.5
+		assert_eq!(region_rate_variance(2, &fetched, &unfetched), Some(0.25));
+		assert_eq!(region_rate_variance(2, &fetched[..2], &[]), None);
+		assert_eq!(region_rate_variance(4, &fetched, &unfetched), None);
+		assert_eq!(region_rate_variance(0, &fetched, &unfetched), None);
+
+		let uniform = region_rate_variance(1, &[position(0, 0), position(0, 1)], &[]);
+		assert_eq!(uniform, Some(0.0));
+	}
+}
@@ -0,0 +1,316 @@
+//! Manifest of the crawl metrics the crawler can emit, for generating the dashboards.
+//!
+//! # Format
+//!
+//! ```json
+//! {
+//!   "metrics": [
+//!     {
+//!       "name": "avail.light.crawl.cells_success_rate",
+//!       "openmetrics_name": "avail_light_crawl_cells_success_rate",
+//!       "type": "gauge",
+//!       "aggregation": "avg",
+//!       "description": "Fraction of the requested cells which are fetched, per crawled block"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Name, type and aggregation are derived from the [`CrawlMetricValue`] names and their conversion to the
+//! recorded [`Record`], so they match the emitted metrics. Averaged and maximum metrics are gauges,
+//! and summed metrics are counters, whose OpenMetrics samples have the `_total` suffix.
+
+use super::{openmetrics, CrawlMetricValue};
+use crate::telemetry::{otlp::Record, MetricName};
+use serde::Serialize;
+
+/// Type of the metric, as exposed in the OpenMetrics format
+#[derive(Serialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetricType {
+	Gauge,
+	Counter,
+}
+
+/// Aggregation of the values recorded within the metrics interval
+#[derive(Serialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Aggregation {
+	Avg,
+	Max,
+	Sum,
+}
+
+/// Description of the crawl metric
+#[derive(Serialize, PartialEq, Clone, Debug)]
+pub struct MetricDescriptor {
+	pub name: &'static str,
+	/// Metric family name in the OpenMetrics file
+	pub openmetrics_name: String,
+	#[serde(rename = "type")]
+	pub metric_type: MetricType,
+	pub aggregation: Aggregation,
+	pub description: &'static str,
+}
+
+#[derive(Serialize)]
+struct Manifest {
+	metrics: Vec<MetricDescriptor>,
+}
+
+/// Crawl metrics, one value of each [`CrawlMetricValue`] variant, in the manifest order.
+/// New variants are added here as well, at their position in `_assert_listed`,
+/// and described in [`CrawlMetricValue::description`].
+pub const METRICS: [CrawlMetricValue; 34] = {
+	use CrawlMetricValue::*;
+	[
+		CellsSuccessRate(0.0),
+		RowsSuccessRate(0.0),
+		BlockDelay(0.0),
+		AvailableBlocks,
+		CellsCoverage(0.0),
+		RowsCoverage(0.0),
+		RefetchedCells(0),
+		Reconstructable(false),
+		RowsDerivedRatio(0.0),
+		DimensionAnomalies,
+		AvailabilityConfidence(0.0),
+		CellsServedLocally(0),
+		CellsServedDht(0),
+		TooOldBlocks,
+		QueriesPerCell(0.0),
+		DuplicateHeaders,
+		ClockSkew,
+		RecentGapBlocks(0),
+		FetchCacheHits,
+		FetchCacheMisses,
+		EmptyPartitions,
+		DimensionChanges,
+		SampleRate(0.0),
+		Escalations,
+		ReconstructionCells(0),
+		BytesFetched(0),
+		FastForwardedBlocks(0),
+		IdleTime(0),
+		SleepingTime(0),
+		FetchingTime(0),
+		DeniedBlocks,
+		ZeroRateEscalations,
+		IntraBlockRateVariance(0.0),
+		ReconciliationDelta(0.0),
+	]
+};
+
+/// Returns the position of the variant in [`METRICS`].
+/// Match has no wildcard, so a new variant fails to compile until it is given a position,
+/// and the assertion below fails to compile unless the metrics are listed at their positions.
+const fn _assert_listed(value: &CrawlMetricValue) -> usize {
+	use CrawlMetricValue::*;
+	match value {
+		CellsSuccessRate(_) => 0,
+		RowsSuccessRate(_) => 1,
+		BlockDelay(_) => 2,
+		AvailableBlocks => 3,
+		CellsCoverage(_) => 4,
+		RowsCoverage(_) => 5,
+		RefetchedCells(_) => 6,
+		Reconstructable(_) => 7,
+		RowsDerivedRatio(_) => 8,
+		DimensionAnomalies => 9,
+		AvailabilityConfidence(_) => 10,
+		CellsServedLocally(_) => 11,
+		CellsServedDht(_) => 12,
+		TooOldBlocks => 13,
+		QueriesPerCell(_) => 14,
+		DuplicateHeaders => 15,
+		ClockSkew => 16,
+		RecentGapBlocks(_) => 17,
+		FetchCacheHits => 18,
+		FetchCacheMisses => 19,
+		EmptyPartitions => 20,
+		DimensionChanges => 21,
+		SampleRate(_) => 22,
+		Escalations => 23,
+		ReconstructionCells(_) => 24,
+		BytesFetched(_) => 25,
+		FastForwardedBlocks(_) => 26,
+		IdleTime(_) => 27,
+		SleepingTime(_) => 28,
+		FetchingTime(_) => 29,
+		DeniedBlocks => 30,
+		ZeroRateEscalations => 31,
+		IntraBlockRateVariance(_) => 32,
+		ReconciliationDelta(_) => 33,
+	}
+}
+
+const _: () = {
+	let mut index = 0;
+	while index < METRICS.len() {
+		assert!(_assert_listed(&METRICS[index]) == index);
+		index += 1;
+	}
+};
+
+impl CrawlMetricValue {
+	/// Returns the description of the metric, with the unit of the recorded values
+	pub fn description(&self) -> &'static str {
+		use CrawlMetricValue::*;
+		match self {
+			CellsSuccessRate(_) => {
+				"Fraction of the requested cells which are fetched, per crawled block"
+			},
+			RowsSuccessRate(_) => {
+				"Fraction of the requested rows which are fetched, per crawled block"
+			},
+			BlockDelay(_) => {
+				"Time since the header is received until the block is crawled, in seconds"
+			},
+			AvailableBlocks => "Number of the crawled blocks which meet the availability criteria",
+			CellsCoverage(_) => {
+				"Fraction of the matrix cells crawled with the configured partition"
+			},
+			RowsCoverage(_) => "Fraction of the matrix rows crawled with the configured partition",
+			RefetchedCells(_) => "Number of the cells fetched again after the failed fetch",
+			Reconstructable(_) => {
+				"Fraction of the crawled blocks which can be reconstructed from the crawled cells"
+			},
+			RowsDerivedRatio(_) => {
+				"Fraction of the crawled rows derived from the cells instead of fetched"
+			},
+			DimensionAnomalies => {
+				"Number of the blocks with the dimensions inconsistent with the extension"
+			},
+			AvailabilityConfidence(_) => {
+				"Confidence in the block availability given the randomly sampled cells, in percent"
+			},
+			CellsServedLocally(_) => "Number of the cells served from the local store",
+			CellsServedDht(_) => "Number of the cells served from the DHT",
+			TooOldBlocks => {
+				"Number of the blocks skipped because they exceed the maximum block age"
+			},
+			QueriesPerCell(_) => "Number of the DHT queries per fetched cell",
+			DuplicateHeaders => "Number of the received headers of the already crawled blocks",
+			ClockSkew => "Number of the headers which appear to be received in the future",
+			RecentGapBlocks(_) => "Number of the blocks missing within the gap scan window",
+			FetchCacheHits => "Number of the blocks whose cells are reused from the fetch cache",
+			FetchCacheMisses => "Number of the blocks whose cells are not found in the fetch cache",
+			EmptyPartitions => "Number of the blocks without any cells in the configured partition",
+			DimensionChanges => {
+				"Number of the block dimension changes which reset the per-row accumulators"
+			},
+			SampleRate(_) => "Success rate of the cells sampled before the escalation",
+			Escalations => "Number of the blocks escalated to the remaining partition cells",
+			ReconstructionCells(_) => "Number of the cells fetched for the block reconstruction",
+			BytesFetched(_) => "Payload of the fetched cells and rows, in bytes",
+			FastForwardedBlocks(_) => "Number of the queued headers skipped by the fast-forward",
+			IdleTime(_) => "Time of the crawl loop waiting for the next header, in milliseconds",
+			SleepingTime(_) => {
+				"Time of the crawl loop sleeping for the crawl delays, in milliseconds"
+			},
+			FetchingTime(_) => "Time of the crawl loop crawling the blocks, in milliseconds",
+			DeniedBlocks => "Number of the blocks skipped by the allowed and denied blocks lists",
+			ZeroRateEscalations => {
+				"Number of the escalations of the consecutive blocks with the zero success rate"
+			},
+			IntraBlockRateVariance(_) => {
+				"Variance of the cells success rates across the matrix regions"
+			},
+			ReconciliationDelta(_) => {
+				"Difference of the crawled success rate from the availability reported by the node"
+			},
+		}
+	}
+
+	/// Returns the descriptor of the metric
+	pub fn descriptor(&self) -> MetricDescriptor {
+		let (metric_type, aggregation) = match Record::from(self.clone()) {
+			Record::AvgF64(..) => (MetricType::Gauge, Aggregation::Avg),
+			Record::MaxU64(..) => (MetricType::Gauge, Aggregation::Max),
+			Record::SumU64(..) => (MetricType::Counter, Aggregation::Sum),
+		};
+		MetricDescriptor {
+			name: self.name(),
+			openmetrics_name: openmetrics::metric_name(self.name()),
+			metric_type,
+			aggregation,
+			description: self.description(),
+		}
+	}
+}
+
+/// Returns the descriptors of the crawl metrics
+pub fn manifest() -> Vec<MetricDescriptor> {
+	METRICS.iter().map(CrawlMetricValue::descriptor).collect()
+}
+
+/// Encodes the manifest as JSON
+pub fn encode() -> serde_json::Result<String> {
+	let metrics = manifest();
+	serde_json::to_string_pretty(&Manifest { metrics })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use openmetrics::Snapshot;
+	use std::{collections::HashSet, mem};
+
+	#[test]
+	fn test_manifest() {
+		let manifest = manifest();
+		let names = manifest.iter().map(|metric| metric.name);
+		assert_eq!(names.collect::<HashSet<_>>().len(), METRICS.len());
+		let variants = METRICS.iter().map(mem::discriminant);
+		assert_eq!(variants.collect::<HashSet<_>>().len(), METRICS.len());
+
+		let delay = CrawlMetricValue::BlockDelay(20.0).descriptor();
+		assert_eq!(delay.name, "avail.light.crawl.block_delay");
+		assert_eq!(delay.openmetrics_name, "avail_light_crawl_block_delay");
+		assert_eq!(delay.metric_type, MetricType::Gauge);
+		assert_eq!(delay.aggregation, Aggregation::Avg);
+		let gaps = CrawlMetricValue::RecentGapBlocks(3).descriptor();
+		assert_eq!(gaps.aggregation, Aggregation::Max);
+		let available = CrawlMetricValue::AvailableBlocks.descriptor();
+		assert_eq!(available.metric_type, MetricType::Counter);
+	}
+
+	#[test]
+	fn test_emitted_metrics() {
+		// Every metric emitted to the OpenMetrics file is in the manifest, with the same type
+		let mut snapshot = Snapshot::default();
+		for value in METRICS {
+			snapshot.record(value);
+		}
+		let manifest = manifest();
+		let emitted = snapshot.encode();
+		let families = emitted
+			.lines()
+			.filter_map(|line| line.strip_prefix("# TYPE "))
+			.map(|family| family.split_once(' ').unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(families.len(), manifest.len());
+		for (name, metric_type) in families {
+			let metric = manifest
+				.iter()
+				.find(|metric| metric.openmetrics_name == name);
+			let metric = metric.unwrap_or_else(|| panic!("{name} is not in the manifest"));
+			let expected = match metric.metric_type {
+				MetricType::Gauge => "gauge",
+				MetricType::Counter => "counter",
+			};
+			assert_eq!(metric_type, expected);
+		}
+	}
+
+	#[test]
+	fn test_encode() {
+		let json: serde_json::Value = serde_json::from_str(&encode().unwrap()).unwrap();
+		let metrics = json["metrics"].as_array().unwrap();
+		assert_eq!(metrics.len(), METRICS.len());
+		assert_eq!(metrics[0]["name"], "avail.light.crawl.cells_success_rate");
+		assert_eq!(metrics[0]["type"], "gauge");
+		assert_eq!(metrics[0]["aggregation"], "avg");
+		assert!(metrics.iter().all(|metric| metric["description"] != ""));
+	}
+}
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Bounded accounting of failures per key (e.g. per matrix row), which keeps the top offenders only.
+///
+/// Counts are decayed and the keys with the fewest failures are dropped on [`Offenders::compact`],
+/// so the old failures lose weight and at most `capacity` keys are kept after the compaction.
+/// Between the compactions, map is compacted as soon as it reaches twice the `capacity`,
+/// so memory usage is bounded regardless of the number of distinct keys.
+#[derive(Clone, Debug)]
+pub struct Offenders<K> {
+	capacity: usize,
+	decay: f64,
+	counts: HashMap<K, f64>,
+}
+
+/// Counts which decayed below this value are dropped on compaction
+const MIN_COUNT: f64 = 0.5;
+
+impl<K: Hash + Eq + Copy> Offenders<K> {
+	/// Creates accumulator which keeps up to `capacity` keys, and multiplies counts by `decay` on compaction.
+	pub fn new(capacity: usize, decay: f64) -> Self {
+		Self {
+			capacity,
+			decay: decay.clamp(0.0, 1.0),
+			counts: HashMap::new(),
+		}
+	}
+
+	/// Changes the limits, compacting the accumulated counts if there are more than `capacity` keys.
+	pub fn set_limits(&mut self, capacity: usize, decay: f64) {
+		self.capacity = capacity;
+		self.decay = decay.clamp(0.0, 1.0);
+		if self.counts.len() > capacity {
+			self.truncate();
+		}
+	}
+
+	pub fn record(&mut self, key: K, count: u64) {
+		if self.capacity == 0 || count == 0 {
+			return;
+		}
+
+		if !self.counts.contains_key(&key) && self.counts.len() >= 2 * self.capacity {
+			self.truncate();
+		}
+		*self.counts.entry(key).or_default() += count as f64;
+	}
+
+	/// Decays the counts, and keeps only `capacity` keys with the most failures.
+	pub fn compact(&mut self) {
+		for count in self.counts.values_mut() {
+			*count *= self.decay;
+		}
+		self.counts.retain(|_, count| *count >= MIN_COUNT);
+		self.truncate();
+	}
+
+	fn truncate(&mut self) {
+		if self.counts.len() <= self.capacity {
+			return;
+		}
+		let top = self.top();
+		self.counts = top.into_iter().take(self.capacity).collect();
+	}
+
+	/// Returns the keys with their decayed failure counts, the most failing first.
+	pub fn top(&self) -> Vec<(K, f64)> {
+		let mut top = self
+			.counts
+			.iter()
+			.map(|(key, count)| (*key, *count))
+			.collect::<Vec<_>>();
+		top.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+		top
+	}
+
+	/// Forgets all accumulated counts
+	pub fn clear(&mut self) {
+		self.counts.clear();
+	}
+
+	pub fn get(&self, key: &K) -> Option<f64> {
+		self.counts.get(key).copied()
+	}
+
+	pub fn len(&self) -> usize {
+		self.counts.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.counts.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_offenders_bounded() {
+		let mut offenders = Offenders::new(10, 0.5);
+		// Repeated offender is kept among many distinct keys
+		for key in 0..10_000u32 {
+			offenders.record(key, 1);
+			offenders.record(u32::MAX, 1);
+			assert!(offenders.len() <= 20);
+		}
+		assert!(offenders.get(&u32::MAX).unwrap() > 1_000.0);
+
+		offenders.compact();
+		assert!(offenders.len() <= 10);
+		assert_eq!(offenders.top()[0], (u32::MAX, 5_000.0));
+
+		offenders.set_limits(1, 0.5);
+		assert_eq!(offenders.len(), 1);
+
+		let mut disabled = Offenders::new(0, 0.5);
+		disabled.record(1, 1);
+		assert!(disabled.is_empty());
+	}
+
+	#[test]
+	fn test_offenders_decay() {
+		let mut offenders = Offenders::new(10, 0.5);
+		offenders.record(1, 4);
+		offenders.record(2, 1);
+
+		offenders.compact();
+		assert_eq!(offenders.top(), vec![(1, 2.0), (2, 0.5)]);
+
+		// Old failures are dropped once decayed below the minimum
+		offenders.compact();
+		assert_eq!(offenders.top(), vec![(1, 1.0)]);
+	}
+}
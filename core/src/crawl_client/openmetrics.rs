@@ -0,0 +1,113 @@
+use super::CrawlMetricValue;
+use crate::telemetry::otlp::Record;
+use std::{
+	collections::BTreeMap,
+	fmt::Write as _,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+/// Current values of the crawl metrics, which can be written in the OpenMetrics text format
+/// and consumed by the tools which don't integrate with the OpenTelemetry collector.
+/// Averaged metrics are exposed as gauges with the last recorded value,
+/// and summed metrics are exposed as counters with the total since the start.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+	gauges: BTreeMap<&'static str, f64>,
+	counters: BTreeMap<&'static str, u64>,
+}
+
+/// Converts metric name to the OpenMetrics name (e.g. `avail.light.crawl.block_delay` to `avail_light_crawl_block_delay`)
+pub(crate) fn metric_name(name: &str) -> String {
+	name.replace(['.', '-'], "_")
+}
+
+impl Snapshot {
+	pub fn record(&mut self, value: CrawlMetricValue) {
+		match value.into() {
+			Record::AvgF64(name, number) => {
+				self.gauges.insert(name, number);
+			},
+			Record::MaxU64(name, number) => {
+				self.gauges.insert(name, number as f64);
+			},
+			Record::SumU64(name, number) => {
+				*self.counters.entry(name).or_default() += number;
+			},
+		}
+	}
+
+	/// Encodes metrics in the OpenMetrics text exposition format
+	pub fn encode(&self) -> String {
+		let mut text = String::new();
+		for (name, value) in &self.gauges {
+			let name = metric_name(name);
+			let _ = writeln!(text, "# TYPE {name} gauge");
+			let _ = writeln!(text, "{name} {value}");
+		}
+		for (name, value) in &self.counters {
+			let name = metric_name(name);
+			let _ = writeln!(text, "# TYPE {name} counter");
+			let _ = writeln!(text, "{name}_total {value}");
+		}
+		text.push_str("# EOF\n");
+		text
+	}
+
+	/// Writes encoded metrics to the temporary file, which is renamed to the given path,
+	/// so readers never observe partially written file.
+	pub fn write(&self, path: &Path) -> io::Result<()> {
+		let mut temporary = PathBuf::from(path).into_os_string();
+		temporary.push(".tmp");
+		fs::write(&temporary, self.encode())?;
+		fs::rename(&temporary, path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_encode() {
+		let mut snapshot = Snapshot::default();
+		assert_eq!(snapshot.encode(), "# EOF\n");
+
+		snapshot.record(CrawlMetricValue::CellsSuccessRate(0.25));
+		snapshot.record(CrawlMetricValue::CellsSuccessRate(0.5));
+		snapshot.record(CrawlMetricValue::AvailableBlocks);
+		snapshot.record(CrawlMetricValue::AvailableBlocks);
+		snapshot.record(CrawlMetricValue::RefetchedCells(3));
+
+		let expected = "\
+# TYPE avail_light_crawl_cells_success_rate gauge
+avail_light_crawl_cells_success_rate 0.5
+# TYPE avail_light_crawl_available_blocks counter
+avail_light_crawl_available_blocks_total 2
+# TYPE avail_light_crawl_refetched_cells counter
+avail_light_crawl_refetched_cells_total 3
+# EOF
+";
+		assert_eq!(snapshot.encode(), expected);
+	}
+
+	#[test]
+	fn test_write() {
+		let path = std::env::temp_dir().join(format!("crawl-{}.prom", std::process::id()));
+		let mut snapshot = Snapshot::default();
+		snapshot.record(CrawlMetricValue::BlockDelay(20.0));
+		snapshot.write(&path).unwrap();
+
+		let text = fs::read_to_string(&path).unwrap();
+		// Each metric family has a type, samples follow the family name, and exposition is terminated
+		let lines = text.lines().collect::<Vec<_>>();
+		assert_eq!(lines[0], "# TYPE avail_light_crawl_block_delay gauge");
+		assert_eq!(lines[1], "avail_light_crawl_block_delay 20");
+		assert_eq!(lines.last(), Some(&"# EOF"));
+		let mut temporary = path.clone().into_os_string();
+		temporary.push(".tmp");
+		assert!(!Path::new(&temporary).exists());
+
+		fs::remove_file(path).unwrap();
+	}
+}
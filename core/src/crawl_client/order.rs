@@ -0,0 +1,165 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Crawled blocks buffered to be forwarded in the block number order, instead of the crawl completion order.
+///
+/// Block is released once all previous blocks are released, so at most `max_buffered` blocks are held.
+/// If the buffer is full, the lowest block is released without waiting for the missing previous blocks,
+/// so a block which is never crawled delays the following blocks by at most `max_buffered` crawled blocks.
+/// Blocks older than the already released blocks are released immediately, since they cannot be ordered anymore.
+/// Blocks which are never crawled on purpose (e.g. denied or too old) are skipped, so they don't delay the following blocks.
+/// With `max_buffered` of 0, blocks are released as they are pushed.
+#[derive(Clone, Debug)]
+pub struct OrderedBlocks<T> {
+	max_buffered: usize,
+	/// Number of the next block to release, set by the first pushed block
+	next: Option<u32>,
+	blocks: BTreeMap<u32, T>,
+	/// Blocks after the next block which are never pushed
+	skipped: BTreeSet<u32>,
+}
+
+impl<T> OrderedBlocks<T> {
+	pub fn new(max_buffered: usize) -> Self {
+		Self {
+			max_buffered,
+			next: None,
+			blocks: BTreeMap::new(),
+			skipped: BTreeSet::new(),
+		}
+	}
+
+	/// Changes the buffer bound, and returns the blocks released to keep the new bound
+	pub fn set_max_buffered(&mut self, max_buffered: usize) -> Vec<T> {
+		self.max_buffered = max_buffered;
+		let mut released = vec![];
+		self.release(&mut released);
+		released
+	}
+
+	/// Buffers the crawled block, and returns the blocks which are released in the block number order
+	pub fn push(&mut self, block_number: u32, block: T) -> Vec<T> {
+		if self.next.is_some_and(|next| block_number < next) {
+			return vec![block];
+		}
+		self.next.get_or_insert(block_number);
+		self.skipped.remove(&block_number);
+		self.blocks.insert(block_number, block);
+		let mut released = vec![];
+		self.release(&mut released);
+		released
+	}
+
+	/// Marks the block which is never pushed, so the following blocks don't wait for it,
+	/// and returns the blocks which are released in the block number order
+	pub fn skip(&mut self, block_number: u32) -> Vec<T> {
+		let is_released = self.next.is_some_and(|next| block_number < next);
+		if self.max_buffered == 0 || is_released || self.blocks.contains_key(&block_number) {
+			return vec![];
+		}
+		self.next.get_or_insert(block_number);
+		self.skipped.insert(block_number);
+		let mut released = vec![];
+		self.release(&mut released);
+		released
+	}
+
+	fn release(&mut self, released: &mut Vec<T>) {
+		loop {
+			while let Some(next) = self.next.filter(|next| self.skipped.remove(next)) {
+				self.next = next.checked_add(1);
+			}
+			let Some(&first) = self.blocks.keys().next() else {
+				return;
+			};
+			let is_next = self.next == Some(first);
+			if !is_next && self.blocks.len() <= self.max_buffered {
+				return;
+			}
+			let block = self.blocks.remove(&first).expect("Block is buffered");
+			self.next = first.checked_add(1);
+			self.forget_skipped();
+			released.push(block);
+		}
+	}
+
+	/// Forgets the skipped blocks before the next block, which are released already
+	fn forget_skipped(&mut self) {
+		match self.next {
+			Some(next) => self.skipped = self.skipped.split_off(&next),
+			None => self.skipped.clear(),
+		}
+	}
+
+	/// Releases all buffered blocks in the block number order
+	pub fn drain(&mut self) -> Vec<T> {
+		let blocks = std::mem::take(&mut self.blocks);
+		if let Some(&last) = blocks.keys().last() {
+			self.next = last.checked_add(1);
+			self.forget_skipped();
+		}
+		blocks.into_values().collect()
+	}
+
+	/// Returns the number of the buffered blocks
+	pub fn len(&self) -> usize {
+		self.blocks.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.blocks.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_ordered_blocks() {
+		let mut ordered = OrderedBlocks::new(3);
+		assert_eq!(ordered.push(10, 10), vec![10]);
+
+		// Blocks completed out of order are held until the previous blocks are released
+		assert!(ordered.push(12, 12).is_empty());
+		assert!(ordered.push(13, 13).is_empty());
+		assert_eq!(ordered.push(11, 11), vec![11, 12, 13]);
+
+		// Lowest block is released without the missing block once the buffer is full
+		assert!(ordered.push(15, 15).is_empty());
+		assert!(ordered.push(17, 17).is_empty());
+		assert!(ordered.push(16, 16).is_empty());
+		assert_eq!(ordered.push(19, 19), vec![15, 16, 17]);
+		assert_eq!(ordered.len(), 1);
+
+		// Block older than the released blocks is released immediately
+		assert_eq!(ordered.push(14, 14), vec![14]);
+
+		assert!(ordered.push(21, 21).is_empty());
+		assert_eq!(ordered.set_max_buffered(1), vec![19]);
+		assert_eq!(ordered.drain(), vec![21]);
+		assert!(ordered.is_empty());
+
+		// Skipped blocks don't hold the following blocks
+		let mut ordered = OrderedBlocks::new(3);
+		assert_eq!(ordered.push(30, 30), vec![30]);
+		assert!(ordered.push(32, 32).is_empty());
+		assert!(ordered.push(34, 34).is_empty());
+		assert_eq!(ordered.skip(31), vec![32]);
+		assert!(ordered.skip(31).is_empty());
+		assert!(ordered.skip(34).is_empty());
+		assert_eq!(ordered.len(), 1);
+		assert!(ordered.skip(35).is_empty());
+		assert_eq!(ordered.skip(33), vec![34]);
+		assert_eq!(ordered.push(36, 36), vec![36]);
+		// Block skipped before any block is pushed starts the order
+		let mut ordered = OrderedBlocks::new(3);
+		assert!(ordered.skip(40).is_empty());
+		assert_eq!(ordered.push(41, 41), vec![41]);
+
+		// Blocks are not buffered without the bound
+		let mut unordered = OrderedBlocks::new(0);
+		assert_eq!(unordered.push(2, 2), vec![2]);
+		assert_eq!(unordered.push(4, 4), vec![4]);
+		assert_eq!(unordered.push(3, 3), vec![3]);
+	}
+}
@@ -0,0 +1,138 @@
+use libp2p::PeerId;
+use std::collections::HashMap;
+
+/// Number of valid and invalid records served by a peer
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PeerFetchCounts {
+	pub successes: u64,
+	pub failures: u64,
+}
+
+impl PeerFetchCounts {
+	pub fn total(&self) -> u64 {
+		self.successes + self.failures
+	}
+
+	pub fn success_rate(&self) -> f64 {
+		self.successes as f64 / self.total() as f64
+	}
+}
+
+/// Per-peer accounting of records served during crawling, used as a reputation signal.
+///
+/// Counts are kept in a map keyed by peer ID, with at most `capacity` entries.
+/// When a previously unseen peer is recorded while the map is full,
+/// the peer with the fewest recorded fetches is evicted first,
+/// so memory usage is bounded to `capacity` entries (around 100 bytes per entry).
+#[derive(Clone, Debug)]
+pub struct PeerStats {
+	capacity: usize,
+	peers: HashMap<PeerId, PeerFetchCounts>,
+}
+
+impl PeerStats {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			peers: HashMap::new(),
+		}
+	}
+
+	/// Changes the maximum number of peers, evicting the peers with the fewest recorded fetches if needed.
+	pub fn set_capacity(&mut self, capacity: usize) {
+		self.capacity = capacity;
+		while self.peers.len() > capacity {
+			self.evict_least_used();
+		}
+	}
+
+	fn evict_least_used(&mut self) {
+		let least_used = self
+			.peers
+			.iter()
+			.min_by_key(|(_, counts)| counts.total())
+			.map(|(peer, _)| *peer);
+
+		if let Some(least_used) = least_used {
+			self.peers.remove(&least_used);
+		}
+	}
+
+	/// Records a record served by the peer, with `success` set to `false` if the record was invalid.
+	pub fn record(&mut self, peer: PeerId, success: bool) {
+		if self.capacity == 0 {
+			return;
+		}
+
+		if !self.peers.contains_key(&peer) && self.peers.len() >= self.capacity {
+			self.evict_least_used();
+		}
+
+		let counts = self.peers.entry(peer).or_default();
+		if success {
+			counts.successes += 1;
+		} else {
+			counts.failures += 1;
+		}
+	}
+
+	pub fn get(&self, peer: &PeerId) -> Option<&PeerFetchCounts> {
+		self.peers.get(peer)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&PeerId, &PeerFetchCounts)> {
+		self.peers.iter()
+	}
+
+	pub fn len(&self) -> usize {
+		self.peers.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.peers.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_peer_stats_record() {
+		let mut stats = PeerStats::new(10);
+		let peer = PeerId::random();
+
+		stats.record(peer, true);
+		stats.record(peer, true);
+		stats.record(peer, false);
+
+		let counts = stats.get(&peer).unwrap();
+		assert_eq!(counts.successes, 2);
+		assert_eq!(counts.failures, 1);
+		assert_eq!(counts.total(), 3);
+	}
+
+	#[test]
+	fn test_peer_stats_capacity() {
+		let mut stats = PeerStats::new(2);
+		let (reliable, other, new) = (PeerId::random(), PeerId::random(), PeerId::random());
+
+		stats.record(reliable, true);
+		stats.record(reliable, true);
+		stats.record(other, true);
+		stats.record(new, false);
+
+		assert_eq!(stats.len(), 2);
+		assert!(stats.get(&reliable).is_some());
+		assert!(stats.get(&other).is_none());
+		assert!(stats.get(&new).is_some());
+
+		stats.set_capacity(1);
+		assert_eq!(stats.len(), 1);
+		assert!(stats.get(&reliable).is_some());
+
+		let mut disabled = PeerStats::new(0);
+		disabled.record(reliable, true);
+		assert!(disabled.is_empty());
+	}
+}
@@ -0,0 +1,168 @@
+//! Reconciliation of the crawl results with the availability of the blocks reported by the full node,
+//! which catches the crawler view of the network diverging from the view of the node.
+//!
+//! Success rate of the crawled cells, or of the crawled rows in the rows mode, is compared with the fraction
+//! of the block cells the node reports as available, and their absolute difference is recorded as the
+//! [`OutputEvent::RecordReconciliationDelta`]. Reconciliation runs on a separate task which receives
+//! the crawl results, so the requests to the node don't delay the crawl loop.
+
+use super::{CrawlResult, OutputEvent};
+use crate::{data::Database, network::rpc};
+use async_trait::async_trait;
+use avail_rust::H256;
+use color_eyre::Result;
+use mockall::automock;
+use tokio::sync::{
+	broadcast::{self, error::RecvError},
+	mpsc::UnboundedSender,
+};
+use tracing::{info, warn};
+
+/// Source of the block availability reported by the full node
+#[async_trait]
+#[automock]
+pub trait AvailabilitySource {
+	/// Returns the fraction of the block cells the node reports as available, requested with the RPC `method`
+	async fn block_availability(&self, method: &str, block_hash: H256) -> Result<f64>;
+}
+
+#[async_trait]
+impl<T: Database + Sync> AvailabilitySource for rpc::Client<T> {
+	async fn block_availability(&self, method: &str, block_hash: H256) -> Result<f64> {
+		rpc::Client::request_block_availability(self, method, block_hash).await
+	}
+}
+
+/// Returns the success rate of the crawled cells, or of the crawled rows if the cells are not crawled
+fn crawled_rate(result: &CrawlResult) -> Option<f64> {
+	result
+		.cells
+		.or(result.rows)
+		.filter(|stats| stats.total > 0)
+		.map(|stats| stats.success_rate())
+}
+
+/// Reconciles crawl results received on `result_receiver` with the availability reported by the `source`,
+/// until the result sender is dropped. Difference of at least `threshold` is logged as a discrepancy.
+pub async fn reconcile_results(
+	source: impl AvailabilitySource,
+	method: String,
+	mut result_receiver: broadcast::Receiver<CrawlResult>,
+	event_sender: UnboundedSender<OutputEvent>,
+	threshold: f64,
+) {
+	info!("Reconciling crawl results with the {method} availability");
+	loop {
+		let result = match result_receiver.recv().await {
+			Ok(result) => result,
+			Err(RecvError::Lagged(skipped)) => {
+				warn!(skipped, "Crawl results reconciliation is lagging behind");
+				continue;
+			},
+			Err(RecvError::Closed) => return,
+		};
+		let block_number = result.block_number;
+		let Some(crawled) = crawled_rate(&result) else {
+			continue;
+		};
+		let reported = match source.block_availability(&method, result.block_hash).await {
+			Ok(reported) => reported,
+			Err(error) => {
+				warn!(
+					block_number,
+					"Cannot get reported block availability: {error}"
+				);
+				continue;
+			},
+		};
+
+		let delta = (crawled - reported).abs();
+		if delta >= threshold {
+			warn!(
+				block_number,
+				crawled, reported, "Crawled success rate diverges from the reported availability"
+			);
+		}
+		if event_sender
+			.send(OutputEvent::RecordReconciliationDelta(delta))
+			.is_err()
+		{
+			return;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crawl_client::CrawlStats;
+	use color_eyre::eyre::eyre;
+	use std::time::{Duration, UNIX_EPOCH};
+	use tokio::sync::mpsc;
+
+	fn result(
+		block_number: u32,
+		cells: Option<CrawlStats>,
+		rows: Option<CrawlStats>,
+	) -> CrawlResult {
+		CrawlResult {
+			block_number,
+			block_hash: H256::repeat_byte(block_number as u8),
+			cells,
+			rows,
+			refetched_cells: 0,
+			reconstructable: None,
+			derived_rows: 0,
+			elapsed: Duration::from_secs(1),
+			received_at: UNIX_EPOCH,
+			crawled_at: UNIX_EPOCH,
+			available: true,
+		}
+	}
+
+	fn stats(total: usize, fetched: usize) -> CrawlStats {
+		CrawlStats { total, fetched }
+	}
+
+	#[tokio::test]
+	async fn test_reconcile_results() {
+		let mut source = MockAvailabilitySource::new();
+		source
+			.expect_block_availability()
+			.withf(|method, _| method == "kate_blockAvailability")
+			.times(3)
+			.returning(|_, block_hash| {
+				let reported = match block_hash {
+					hash if hash == H256::repeat_byte(1) => Ok(1.0),
+					hash if hash == H256::repeat_byte(2) => Ok(0.9),
+					_ => Err(eyre!("Block is not found")),
+				};
+				Box::pin(async move { reported })
+			});
+
+		let (result_tx, result_rx) = broadcast::channel(10);
+		let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+		// Node reports the full availability, while the crawler fetched a quarter of the cells
+		result_tx.send(result(1, Some(stats(4, 1)), None)).unwrap();
+		// Rows are reconciled in the rows mode
+		result_tx.send(result(2, None, Some(stats(2, 2)))).unwrap();
+		// Result without the crawled cells or rows is not reconciled
+		result_tx.send(result(3, Some(stats(0, 0)), None)).unwrap();
+		// Availability which cannot be requested is not recorded
+		result_tx.send(result(4, Some(stats(4, 4)), None)).unwrap();
+		drop(result_tx);
+
+		let source_method = "kate_blockAvailability".to_string();
+		reconcile_results(source, source_method, result_rx, event_tx, 0.5).await;
+
+		let deltas = std::iter::from_fn(|| event_rx.try_recv().ok())
+			.map(|event| match event {
+				OutputEvent::RecordReconciliationDelta(delta) => delta,
+				_ => panic!("Unexpected event"),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(deltas.len(), 2);
+		assert_eq!(deltas[0], 0.75);
+		assert!((deltas[1] - 0.1).abs() < 1e-9);
+	}
+}
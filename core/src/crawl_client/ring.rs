@@ -0,0 +1,118 @@
+use crate::utils::blake2_256;
+use avail_rust::kate_recovery::matrix::Partition;
+use std::collections::BTreeMap;
+
+/// Number of the points of each node on the ring, which evens out the number of partitions assigned to the nodes
+const VIRTUAL_NODES: u32 = 100;
+
+fn point(key: &str) -> u64 {
+	let hash = blake2_256(key.as_bytes());
+	u64::from_be_bytes(hash[..8].try_into().expect("Hash is 32 bytes"))
+}
+
+/// Consistent hashing ring of the crawler nodes, which assigns the block matrix partitions to the nodes.
+///
+/// Each node is placed on the ring at multiple points, and each partition is assigned to the node
+/// with the first point following the partition point. When a node joins, it takes over only the partitions
+/// in front of its points, and when a node leaves, only its partitions are reassigned,
+/// so rebalancing of the fleet moves the minimal coverage.
+#[derive(Clone, Debug, Default)]
+pub struct Ring {
+	points: BTreeMap<u64, String>,
+}
+
+impl Ring {
+	pub fn new<'a>(nodes: impl IntoIterator<Item = &'a str>) -> Self {
+		let mut ring = Ring::default();
+		for node in nodes {
+			ring.insert(node);
+		}
+		ring
+	}
+
+	pub fn insert(&mut self, node: &str) {
+		for replica in 0..VIRTUAL_NODES {
+			self.points
+				.insert(point(&format!("{node}-{replica}")), node.to_string());
+		}
+	}
+
+	pub fn remove(&mut self, node: &str) {
+		self.points.retain(|_, owner| owner != node);
+	}
+
+	/// Returns the node which owns the partition, or `None` if the ring is empty
+	fn owner(&self, partition: u8) -> Option<&str> {
+		let point = point(&format!("partition-{partition}"));
+		self.points
+			.range(point..)
+			.chain(&self.points)
+			.next()
+			.map(|(_, node)| node.as_str())
+	}
+
+	/// Returns partitions of the matrix divided into `fraction` parts, which are assigned to the node
+	pub fn partitions(&self, node: &str, fraction: u8) -> Vec<Partition> {
+		(1..=fraction)
+			.filter(|&number| self.owner(number) == Some(node))
+			.map(|number| Partition { number, fraction })
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+
+	const FRACTION: u8 = 100;
+
+	fn assignments(ring: &Ring, nodes: &[&str]) -> HashMap<u8, String> {
+		nodes
+			.iter()
+			.flat_map(|node| {
+				let partitions = ring.partitions(node, FRACTION);
+				partitions
+					.into_iter()
+					.map(move |partition| (partition.number, node.to_string()))
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_partitions_assigned_once() {
+		let nodes = ["node-1", "node-2", "node-3"];
+		let ring = Ring::new(nodes);
+		let assigned = nodes
+			.iter()
+			.map(|node| ring.partitions(node, FRACTION).len())
+			.collect::<Vec<_>>();
+		assert_eq!(assigned.iter().sum::<usize>(), FRACTION as usize);
+		assert!(assigned.iter().all(|&count| count > 0));
+		assert!(ring.partitions("node-4", FRACTION).is_empty());
+		assert!(Ring::default().partitions("node-1", FRACTION).is_empty());
+	}
+
+	#[test]
+	fn test_minimal_reassignment() {
+		let nodes = ["node-1", "node-2", "node-3", "node-4"];
+		let mut ring = Ring::new(nodes[..3].iter().copied());
+		let before = assignments(&ring, &nodes[..3]);
+
+		// Joined node takes over partitions from the other nodes, which keep the rest
+		ring.insert(nodes[3]);
+		let after = assignments(&ring, &nodes);
+		for (number, node) in &after {
+			assert!(node == nodes[3] || before[number] == *node);
+		}
+		assert!(after.values().any(|node| node == nodes[3]));
+
+		// Partitions of the left node are reassigned, and the other partitions stay
+		ring.remove(nodes[0]);
+		let remaining = assignments(&ring, &nodes[1..]);
+		for (number, node) in &remaining {
+			assert!(after[number] == nodes[0] || after[number] == *node);
+		}
+		assert_eq!(remaining.len(), FRACTION as usize);
+	}
+}
@@ -0,0 +1,442 @@
+use super::{format_partitions, CrawlConfig, CrawlMode, ROWS_STEP};
+use crate::utils;
+use avail_rust::kate_recovery::matrix::{Dimensions, Partition, Position};
+use rand::seq::index;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Cell positions and row indexes to fetch for a block
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sample {
+	pub positions: Vec<Position>,
+	pub row_indexes: Vec<u32>,
+}
+
+/// Selects cells and rows of the block matrix to crawl.
+///
+/// Positions are expected to be empty if cells are not crawled in the configured mode,
+/// and row indexes are expected to be empty if rows are not crawled.
+pub trait SamplingStrategy {
+	fn sample(&self, dimensions: &Dimensions, config: &CrawlConfig) -> Sample;
+}
+
+/// Returns positions of the extended matrix cells in the partition, in the order they are crawled.
+/// Extension rows are included, regardless of the configuration.
+pub fn partition_positions(dimensions: &Dimensions, partition: &Partition) -> Vec<Position> {
+	dimensions
+		.iter_extended_partition_positions(partition)
+		.collect::<Vec<_>>()
+}
+
+/// Crawls cells of the configured partitions and columns, and every original data row
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PartitionSampling;
+
+impl SamplingStrategy for PartitionSampling {
+	fn sample(&self, dimensions: &Dimensions, config: &CrawlConfig) -> Sample {
+		let mode = config.crawl_block_mode;
+		let include_extension_rows = config.crawl_include_extension_rows;
+
+		let mut positions = vec![];
+		if matches!(mode, CrawlMode::Cells | CrawlMode::Both) {
+			let columns = config
+				.crawl_columns
+				.iter()
+				.filter(|&&col| col < dimensions.width::<u16>())
+				.flat_map(|&col| {
+					(0..dimensions.extended_rows()).map(move |row| Position { row, col })
+				});
+
+			// Cells which are both in the partition and in the columns are crawled once
+			let mut unique = HashSet::new();
+			positions = config
+				.crawl_block_matrix_partition
+				.iter()
+				.flat_map(|partition| partition_positions(dimensions, partition))
+				.chain(columns)
+				.filter(|position| include_extension_rows || !position.is_extended())
+				.filter(|position| unique.insert(*position))
+				.collect::<Vec<_>>();
+		}
+
+		let mut row_indexes = vec![];
+		if matches!(mode, CrawlMode::Rows | CrawlMode::Both) {
+			row_indexes = (0..dimensions.extended_rows())
+				.step_by(ROWS_STEP)
+				.collect::<Vec<_>>();
+		}
+
+		Sample {
+			positions,
+			row_indexes,
+		}
+	}
+}
+
+/// Crawls up to `sample_size` random distinct cells and original data rows,
+/// so the nodes of the fleet don't need to coordinate partitions.
+#[derive(Clone, Copy, Debug)]
+pub struct RandomSampling {
+	pub sample_size: usize,
+}
+
+impl SamplingStrategy for RandomSampling {
+	fn sample(&self, dimensions: &Dimensions, config: &CrawlConfig) -> Sample {
+		let mode = config.crawl_block_mode;
+		let cols: u32 = dimensions.width();
+		let mut rng = utils::rng();
+
+		let mut positions = vec![];
+		if matches!(mode, CrawlMode::Cells | CrawlMode::Both) {
+			// Without extension rows, cells are sampled from the original rows only,
+			// which are every other row of the extended matrix
+			let (rows_step, size) = if config.crawl_include_extension_rows {
+				(1, dimensions.extended_size())
+			} else {
+				(
+					ROWS_STEP as u32,
+					dimensions.extended_size() / ROWS_STEP as u32,
+				)
+			};
+			let amount = self.sample_size.min(size as usize);
+			positions = index::sample(&mut rng, size as usize, amount)
+				.into_iter()
+				.map(|index| index as u32)
+				.map(|index| Position {
+					row: index / cols * rows_step,
+					col: (index % cols) as u16,
+				})
+				.collect::<Vec<_>>();
+		}
+
+		let mut row_indexes = vec![];
+		if matches!(mode, CrawlMode::Rows | CrawlMode::Both) {
+			let rows = dimensions.extended_rows() as usize / ROWS_STEP;
+			let amount = self.sample_size.min(rows);
+			row_indexes = index::sample(&mut rng, rows, amount)
+				.into_iter()
+				.map(|index| (index * ROWS_STEP) as u32)
+				.collect::<Vec<_>>();
+		}
+
+		Sample {
+			positions,
+			row_indexes,
+		}
+	}
+}
+
+/// Built-in sampling strategies, selectable in the configuration
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sampling {
+	#[default]
+	Partition,
+	Random,
+}
+
+impl SamplingStrategy for Sampling {
+	fn sample(&self, dimensions: &Dimensions, config: &CrawlConfig) -> Sample {
+		match self {
+			Sampling::Partition => PartitionSampling.sample(dimensions, config),
+			Sampling::Random => RandomSampling {
+				sample_size: config.crawl_random_sample_size,
+			}
+			.sample(dimensions, config),
+		}
+	}
+}
+
+/// Order of fetching the sampled cells and rows, so the most important are fetched first
+/// if the fetch is cut short, e.g. by a checkpointed restart or by the shutdown
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum FetchPriority {
+	/// Cells and rows are fetched in the sampled order
+	#[default]
+	Sampled,
+	/// Cells of the original data rows are fetched before the cells of the extension rows
+	OriginalFirst,
+	/// Cells and rows closest to the center of the extended matrix are fetched first
+	CenterOut,
+}
+
+impl FetchPriority {
+	/// Orders the sample positions and row indexes by the priority, keeping the sampled order of the equal priorities
+	pub fn order(&self, dimensions: &Dimensions, sample: &mut Sample) {
+		// Distances are doubled to stay integer, since the center can be between the rows or columns
+		let rows = dimensions.extended_rows();
+		let cols: u32 = dimensions.width();
+		let row_distance = |row: u32| u64::from((2 * row).abs_diff(rows - 1));
+		let col_distance = |col: u16| u64::from((2 * u32::from(col)).abs_diff(cols - 1));
+		match self {
+			FetchPriority::Sampled => (),
+			FetchPriority::OriginalFirst => {
+				sample.positions.sort_by_key(Position::is_extended);
+			},
+			FetchPriority::CenterOut => {
+				sample.positions.sort_by_key(|position| {
+					row_distance(position.row).pow(2) + col_distance(position.col).pow(2)
+				});
+				sample.row_indexes.sort_by_key(|&row| row_distance(row));
+			},
+		}
+	}
+}
+
+/// Configuration which the partition sample depends on
+#[derive(Clone, Debug, PartialEq)]
+struct SampleKey {
+	dimensions: Dimensions,
+	mode: CrawlMode,
+	partition: String,
+	columns: Vec<u16>,
+	include_extension_rows: bool,
+	priority: FetchPriority,
+}
+
+impl SampleKey {
+	fn new(dimensions: &Dimensions, config: &CrawlConfig) -> Self {
+		Self {
+			dimensions: *dimensions,
+			mode: config.crawl_block_mode,
+			partition: format_partitions(&config.crawl_block_matrix_partition),
+			columns: config.crawl_columns.clone(),
+			include_extension_rows: config.crawl_include_extension_rows,
+			priority: config.crawl_fetch_priority,
+		}
+	}
+}
+
+/// Partition sample of the last sampled block, reused for the following blocks of the same dimensions,
+/// so the positions are not generated again for the blocks crawled repeatedly or with the unchanged dimensions.
+/// Sample is computed again if the dimensions or the sampling options change.
+/// Random samples are computed for each block, since the reused random sample would not be random.
+#[derive(Clone, Debug, Default)]
+pub struct SampleCache {
+	cached: Option<(SampleKey, Sample)>,
+}
+
+impl SampleCache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the sample of the block with the given dimensions, ordered by the [`FetchPriority`],
+	/// computed only if it is not cached
+	pub fn sample(&mut self, dimensions: &Dimensions, config: &CrawlConfig) -> Sample {
+		let priority = config.crawl_fetch_priority;
+		if config.crawl_sampling != Sampling::Partition {
+			let mut sample = config.crawl_sampling.sample(dimensions, config);
+			priority.order(dimensions, &mut sample);
+			return sample;
+		}
+		let key = SampleKey::new(dimensions, config);
+		match &self.cached {
+			Some((cached_key, sample)) if *cached_key == key => sample.clone(),
+			_ => {
+				let mut sample = PartitionSampling.sample(dimensions, config);
+				priority.order(dimensions, &mut sample);
+				self.cached = Some((key, sample.clone()));
+				sample
+			},
+		}
+	}
+
+	/// Returns `true` if the sample of the block with the given dimensions is cached
+	pub fn contains(&self, dimensions: &Dimensions, config: &CrawlConfig) -> bool {
+		config.crawl_sampling == Sampling::Partition
+			&& self
+				.cached
+				.as_ref()
+				.is_some_and(|(key, _)| *key == SampleKey::new(dimensions, config))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config(mode: CrawlMode, include_extension_rows: bool) -> CrawlConfig {
+		CrawlConfig {
+			crawl_block_mode: mode,
+			crawl_include_extension_rows: include_extension_rows,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_partition_sampling() {
+		let dimensions = Dimensions::new(2, 4).unwrap();
+
+		let sample = PartitionSampling.sample(&dimensions, &config(CrawlMode::Both, true));
+		assert_eq!(sample.positions.len(), 16);
+		assert_eq!(sample.row_indexes, vec![0, 2]);
+
+		let partition_config = CrawlConfig {
+			crawl_block_matrix_partition: vec![Partition {
+				number: 2,
+				fraction: 4,
+			}],
+			..config(CrawlMode::Cells, true)
+		};
+		let sample = PartitionSampling.sample(&dimensions, &partition_config);
+		let expected = (0..4)
+			.map(|col| Position { row: 1, col })
+			.collect::<Vec<_>>();
+		assert_eq!(sample.positions, expected);
+		assert!(sample.row_indexes.is_empty());
+
+		let range_config = CrawlConfig {
+			crawl_block_matrix_partition: (2..=3)
+				.map(|number| Partition {
+					number,
+					fraction: 4,
+				})
+				.collect(),
+			..config(CrawlMode::Cells, true)
+		};
+		let sample = PartitionSampling.sample(&dimensions, &range_config);
+		let expected = (1..=2)
+			.flat_map(|row| (0..4).map(move |col| Position { row, col }))
+			.collect::<Vec<_>>();
+		assert_eq!(sample.positions, expected);
+
+		let columns_config = CrawlConfig {
+			crawl_columns: vec![1, 1, 9],
+			..partition_config.clone()
+		};
+		let sample = PartitionSampling.sample(&dimensions, &columns_config);
+		// Cell 1:1 is both in the partition and in the column, and column 9 is out of the matrix
+		let expected = (0..4)
+			.map(|col| Position { row: 1, col })
+			.chain([0, 2, 3].map(|row| Position { row, col: 1 }))
+			.collect::<Vec<_>>();
+		assert_eq!(sample.positions, expected);
+
+		let sample = PartitionSampling.sample(&dimensions, &config(CrawlMode::Rows, true));
+		assert!(sample.positions.is_empty());
+		assert_eq!(sample.row_indexes, vec![0, 2]);
+
+		let sample = PartitionSampling.sample(&dimensions, &config(CrawlMode::Both, false));
+		assert!(sample
+			.positions
+			.iter()
+			.all(|position| !position.is_extended()));
+		assert_eq!(sample.row_indexes, vec![0, 2]);
+	}
+
+	#[test]
+	fn test_random_sampling() {
+		let dimensions = Dimensions::new(4, 8).unwrap();
+		let strategy = RandomSampling { sample_size: 10 };
+
+		let sample = strategy.sample(&dimensions, &config(CrawlMode::Both, true));
+		let positions = sample.positions.iter().collect::<HashSet<_>>();
+		assert_eq!(positions.len(), 10);
+		assert!(positions
+			.iter()
+			.all(|position| position.row < 8 && position.col < 8));
+		// Sample size is limited by the number of original rows
+		let mut row_indexes = sample.row_indexes.clone();
+		row_indexes.sort();
+		assert_eq!(row_indexes, vec![0, 2, 4, 6]);
+
+		let sample = strategy.sample(&dimensions, &config(CrawlMode::Cells, false));
+		assert_eq!(sample.positions.len(), 10);
+		assert!(sample
+			.positions
+			.iter()
+			.all(|position| !position.is_extended()));
+		assert!(sample.row_indexes.is_empty());
+
+		// Sample size is limited by the number of cells
+		let strategy = RandomSampling { sample_size: 100 };
+		let sample = strategy.sample(&dimensions, &config(CrawlMode::Cells, true));
+		let positions = sample.positions.iter().collect::<HashSet<_>>();
+		assert_eq!(positions.len(), 64);
+	}
+
+	#[test]
+	fn test_sample_cache() {
+		let dimensions = Dimensions::new(2, 4).unwrap();
+		let other_dimensions = Dimensions::new(4, 4).unwrap();
+		let config = config(CrawlMode::Both, true);
+		let mut cache = SampleCache::new();
+		assert!(!cache.contains(&dimensions, &config));
+
+		// Cached sample is the same as the computed one
+		let computed = PartitionSampling.sample(&dimensions, &config);
+		assert_eq!(cache.sample(&dimensions, &config), computed);
+		assert!(cache.contains(&dimensions, &config));
+		assert_eq!(cache.sample(&dimensions, &config), computed);
+
+		// Sample is computed again for the changed dimensions or options
+		let computed = PartitionSampling.sample(&other_dimensions, &config);
+		assert_eq!(cache.sample(&other_dimensions, &config), computed);
+		assert!(!cache.contains(&dimensions, &config));
+		let partition_config = CrawlConfig {
+			crawl_block_matrix_partition: vec![Partition {
+				number: 1,
+				fraction: 2,
+			}],
+			..config.clone()
+		};
+		assert!(!cache.contains(&other_dimensions, &partition_config));
+		let computed = PartitionSampling.sample(&other_dimensions, &partition_config);
+		assert_eq!(cache.sample(&other_dimensions, &partition_config), computed);
+
+		// Random samples are not cached
+		let random_config = CrawlConfig {
+			crawl_sampling: Sampling::Random,
+			..partition_config
+		};
+		assert!(!cache.contains(&other_dimensions, &random_config));
+	}
+
+	#[test]
+	fn test_fetch_priority() {
+		let dimensions = Dimensions::new(2, 2).unwrap();
+		let sample = || PartitionSampling.sample(&dimensions, &config(CrawlMode::Both, true));
+		let positions = |sample: Sample| {
+			(sample.positions.iter())
+				.map(|position| (position.row, position.col))
+				.collect::<Vec<_>>()
+		};
+
+		let mut sampled = sample();
+		FetchPriority::Sampled.order(&dimensions, &mut sampled);
+		assert_eq!(sampled, sample());
+
+		let mut original_first = sample();
+		FetchPriority::OriginalFirst.order(&dimensions, &mut original_first);
+		assert_eq!(original_first.row_indexes, vec![0, 2]);
+		let expected = vec![
+			(0, 0),
+			(0, 1),
+			(2, 0),
+			(2, 1),
+			(1, 0),
+			(1, 1),
+			(3, 0),
+			(3, 1),
+		];
+		assert_eq!(positions(original_first), expected);
+
+		// Center of the 4x2 extended matrix is between the rows 1 and 2
+		let mut center_out = sample();
+		FetchPriority::CenterOut.order(&dimensions, &mut center_out);
+		assert_eq!(center_out.row_indexes, vec![2, 0]);
+		let expected = vec![
+			(1, 0),
+			(1, 1),
+			(2, 0),
+			(2, 1),
+			(0, 0),
+			(0, 1),
+			(3, 0),
+			(3, 1),
+		];
+		assert_eq!(positions(center_out), expected);
+	}
+}
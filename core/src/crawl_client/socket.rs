@@ -0,0 +1,150 @@
+//! Streaming of the crawl results to a co-located collector over a Unix domain socket.
+//!
+//! # Framing
+//!
+//! Each crawl result is sent as a frame of the 4 bytes big-endian length, followed by
+//! the JSON serialized [`CrawlResult`] of that length. Frames are sent in the order the blocks are crawled.
+//!
+//! Collector listens on the socket, and the crawler connects to it. If the collector is not listening,
+//! or it disconnects, the crawler reconnects after the reconnect interval.
+//! Result which failed to be sent on disconnect is dropped, and results crawled while reconnecting
+//! are sent after reconnecting, unless the crawl results channel lags behind.
+
+use super::CrawlResult;
+use color_eyre::Result;
+use std::{path::Path, time::Duration};
+use tokio::{
+	io::AsyncWriteExt,
+	net::UnixStream,
+	sync::broadcast::{self, error::RecvError},
+};
+use tracing::{info, warn};
+
+/// Encodes the crawl result as a length-prefixed JSON frame
+pub fn encode_frame(result: &CrawlResult) -> Result<Vec<u8>> {
+	let json = serde_json::to_vec(result)?;
+	let length = u32::try_from(json.len())?;
+	let mut frame = Vec::with_capacity(4 + json.len());
+	frame.extend_from_slice(&length.to_be_bytes());
+	frame.extend_from_slice(&json);
+	Ok(frame)
+}
+
+/// Streams crawl results received on `result_receiver` to the socket at `path`,
+/// until the result sender is dropped.
+pub async fn stream_results(
+	path: impl AsRef<Path>,
+	mut result_receiver: broadcast::Receiver<CrawlResult>,
+	reconnect: Duration,
+) {
+	let path = path.as_ref();
+	loop {
+		let mut stream = match UnixStream::connect(path).await {
+			Ok(stream) => stream,
+			Err(error) => {
+				warn!(
+					"Cannot connect to crawl results socket {}: {error}",
+					path.display()
+				);
+				tokio::time::sleep(reconnect).await;
+				continue;
+			},
+		};
+		info!("Streaming crawl results to socket {}", path.display());
+
+		loop {
+			let result = match result_receiver.recv().await {
+				Ok(result) => result,
+				Err(RecvError::Lagged(skipped)) => {
+					warn!(skipped, "Crawl results socket is lagging behind");
+					continue;
+				},
+				Err(RecvError::Closed) => return,
+			};
+			let frame = match encode_frame(&result) {
+				Ok(frame) => frame,
+				Err(error) => {
+					warn!(
+						block_number = result.block_number,
+						"Cannot encode crawl result: {error}"
+					);
+					continue;
+				},
+			};
+			if let Err(error) = stream.write_all(&frame).await {
+				warn!(
+					"Crawl results socket {} is disconnected: {error}",
+					path.display()
+				);
+				break;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crawl_client::CrawlStats;
+	use avail_rust::H256;
+	use std::time::SystemTime;
+	use tokio::{io::AsyncReadExt, net::UnixListener};
+
+	fn result(block_number: u32) -> CrawlResult {
+		CrawlResult {
+			block_number,
+			block_hash: H256::repeat_byte(1),
+			cells: Some(CrawlStats {
+				total: 4,
+				fetched: 2,
+			}),
+			rows: None,
+			refetched_cells: 0,
+			reconstructable: None,
+			derived_rows: 0,
+			elapsed: Duration::from_secs(1),
+			received_at: SystemTime::UNIX_EPOCH,
+			crawled_at: SystemTime::UNIX_EPOCH + Duration::from_secs(1),
+			available: true,
+		}
+	}
+
+	async fn read_frame(stream: &mut UnixStream) -> serde_json::Value {
+		let length = stream.read_u32().await.unwrap();
+		let mut json = vec![0; length as usize];
+		stream.read_exact(&mut json).await.unwrap();
+		serde_json::from_slice(&json).unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_stream_results() {
+		let path = std::env::temp_dir().join(format!("crawl-{}.sock", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		let listener = UnixListener::bind(&path).unwrap();
+
+		let (result_tx, result_rx) = broadcast::channel(10);
+		let handle = tokio::spawn(stream_results(
+			path.clone(),
+			result_rx,
+			Duration::from_millis(10),
+		));
+
+		let (mut stream, _) = listener.accept().await.unwrap();
+		result_tx.send(result(1)).unwrap();
+		let frame = read_frame(&mut stream).await;
+		assert_eq!(frame["block_number"], 1);
+		assert_eq!(frame["cells"]["fetched"], 2);
+		assert_eq!(frame["available"], true);
+
+		// Result sent to the disconnected collector is dropped, and the next one is sent after reconnecting
+		drop(stream);
+		result_tx.send(result(2)).unwrap();
+		let (mut stream, _) = listener.accept().await.unwrap();
+		result_tx.send(result(3)).unwrap();
+		assert_eq!(read_frame(&mut stream).await["block_number"], 3);
+
+		drop(result_tx);
+		handle.await.unwrap();
+		std::fs::remove_file(path).unwrap();
+	}
+}
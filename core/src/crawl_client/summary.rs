@@ -0,0 +1,291 @@
+//! Summary of the recent crawl performance, intended for the small status views
+//! which need a single JSON document instead of the raw metrics.
+//!
+//! # Schema
+//!
+//! ```json
+//! {
+//!   "crawling": 12,
+//!   "delay": 20.0,
+//!   "coverage": { "cells": 0.05, "rows": null },
+//!   "blocks": 2,
+//!   "available_fraction": 0.5,
+//!   "cells_success_rate": 0.75,
+//!   "rows_success_rate": null,
+//!   "recent": [
+//!     {
+//!       "block_number": 10,
+//!       "block_hash": "0x…",
+//!       "cells_success_rate": 1.0,
+//!       "rows_success_rate": null,
+//!       "available": true,
+//!       "delay": 20.0,
+//!       "elapsed": 1.5,
+//!       "received_at": 1700000000.0,
+//!       "crawled_at": 1700000021.5
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! - `crawling` is the number of the block which is being crawled, or `null`
+//! - `delay` is the delay in seconds between receiving and crawling the last crawled block, or `null` if none is crawled
+//! - `coverage` contains fractions of the extended matrix cells and rows crawled, `null` if not crawled in the configured mode
+//! - `blocks` is the number of recent blocks summarized, up to the configured history size
+//! - `available_fraction` is the fraction of the recent blocks which are available, or `null` if there are none
+//! - `cells_success_rate` and `rows_success_rate` are averaged over the recent blocks which crawled cells or rows,
+//!   `null` if there are none
+//! - `recent` lists recent blocks from the oldest to the newest, with `delay` and `elapsed` crawling time in seconds,
+//!   and the wall-clock times the header is `received_at` and crawling is completed at (`crawled_at`), in seconds since the Unix epoch
+
+use super::{Coverage, CrawlResult, CrawlStats, CrawlStatus};
+use avail_rust::H256;
+use serde::Serialize;
+use std::{
+	collections::VecDeque,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Crawl result of the block, with the delay after which crawling started
+#[derive(Clone, Debug)]
+pub struct RecentBlock {
+	pub result: CrawlResult,
+	pub delay: Duration,
+}
+
+/// Recently crawled blocks, with at most `capacity` blocks kept
+#[derive(Clone, Debug)]
+pub struct History {
+	capacity: usize,
+	blocks: VecDeque<RecentBlock>,
+}
+
+impl History {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			blocks: VecDeque::with_capacity(capacity),
+		}
+	}
+
+	/// Changes the maximum number of blocks, dropping the oldest blocks if needed.
+	pub fn set_capacity(&mut self, capacity: usize) {
+		self.capacity = capacity;
+		while self.blocks.len() > capacity {
+			self.blocks.pop_front();
+		}
+	}
+
+	/// Records crawled block, dropping the oldest block if history is full.
+	pub fn record(&mut self, result: CrawlResult, delay: Duration) {
+		if self.capacity == 0 {
+			return;
+		}
+		if self.blocks.len() == self.capacity {
+			self.blocks.pop_front();
+		}
+		self.blocks.push_back(RecentBlock { result, delay });
+	}
+
+	/// Returns recent blocks from the oldest to the newest
+	pub fn iter(&self) -> impl Iterator<Item = &RecentBlock> {
+		self.blocks.iter()
+	}
+
+	pub fn len(&self) -> usize {
+		self.blocks.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.blocks.is_empty()
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CoverageSummary {
+	pub cells: Option<f64>,
+	pub rows: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BlockSummary {
+	pub block_number: u32,
+	pub block_hash: H256,
+	pub cells_success_rate: Option<f64>,
+	pub rows_success_rate: Option<f64>,
+	pub available: bool,
+	pub delay: f64,
+	pub elapsed: f64,
+	pub received_at: f64,
+	pub crawled_at: f64,
+}
+
+/// Summary of the recent crawl performance, see the [module documentation](self) for the schema
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Summary {
+	pub crawling: Option<u32>,
+	pub delay: Option<f64>,
+	pub coverage: CoverageSummary,
+	pub blocks: usize,
+	pub available_fraction: Option<f64>,
+	pub cells_success_rate: Option<f64>,
+	pub rows_success_rate: Option<f64>,
+	pub recent: Vec<BlockSummary>,
+}
+
+fn unix_seconds(time: SystemTime) -> f64 {
+	time.duration_since(UNIX_EPOCH)
+		.map_or(0.0, |duration| duration.as_secs_f64())
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+	let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+	(count > 0).then(|| sum / count as f64)
+}
+
+impl Summary {
+	/// Assembles the summary from the status and the coverage of the current configuration
+	pub fn new(status: &CrawlStatus, coverage: Coverage) -> Self {
+		let recent = status
+			.history
+			.iter()
+			.map(|RecentBlock { result, delay }| BlockSummary {
+				block_number: result.block_number,
+				block_hash: result.block_hash,
+				cells_success_rate: result.cells.as_ref().map(CrawlStats::success_rate),
+				rows_success_rate: result.rows.as_ref().map(CrawlStats::success_rate),
+				available: result.available,
+				delay: delay.as_secs_f64(),
+				elapsed: result.elapsed.as_secs_f64(),
+				received_at: unix_seconds(result.received_at),
+				crawled_at: unix_seconds(result.crawled_at),
+			})
+			.collect::<Vec<_>>();
+
+		let available = recent
+			.iter()
+			.map(|block| f64::from(u8::from(block.available)));
+		Summary {
+			crawling: status.crawling,
+			delay: recent.last().map(|block| block.delay),
+			coverage: CoverageSummary {
+				cells: coverage.cells,
+				rows: coverage.rows,
+			},
+			blocks: recent.len(),
+			available_fraction: average(available),
+			cells_success_rate: average(recent.iter().filter_map(|block| block.cells_success_rate)),
+			rows_success_rate: average(recent.iter().filter_map(|block| block.rows_success_rate)),
+			recent,
+		}
+	}
+
+	/// Serializes the summary to the JSON document
+	pub fn to_json(&self) -> serde_json::Result<String> {
+		serde_json::to_string(self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn result(block_number: u32, fetched: usize, available: bool) -> CrawlResult {
+		CrawlResult {
+			block_number,
+			block_hash: H256::repeat_byte(block_number as u8),
+			cells: Some(CrawlStats { total: 4, fetched }),
+			rows: None,
+			refetched_cells: 0,
+			reconstructable: None,
+			derived_rows: 0,
+			elapsed: Duration::from_millis(500),
+			received_at: UNIX_EPOCH + Duration::from_secs(u64::from(block_number) * 20),
+			crawled_at: UNIX_EPOCH + Duration::from_secs(u64::from(block_number) * 20 + 1),
+			available,
+		}
+	}
+
+	#[test]
+	fn test_history_bounded() {
+		let mut history = History::new(2);
+		for block_number in 1..=3 {
+			history.record(result(block_number, 4, true), Duration::ZERO);
+		}
+		let blocks = history
+			.iter()
+			.map(|block| block.result.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(blocks, vec![2, 3]);
+
+		history.set_capacity(1);
+		assert_eq!(history.len(), 1);
+		history.set_capacity(0);
+		history.record(result(4, 4, true), Duration::ZERO);
+		assert!(history.is_empty());
+	}
+
+	#[test]
+	fn test_summary_json() {
+		let empty = Summary::new(
+			&CrawlStatus::new(&Default::default()),
+			Coverage {
+				cells: None,
+				rows: None,
+			},
+		);
+		assert_eq!(empty.blocks, 0);
+		assert_eq!(empty.delay, None);
+		assert_eq!(empty.available_fraction, None);
+
+		let mut status = CrawlStatus::new(&Default::default());
+		status.crawling = Some(3);
+		status
+			.history
+			.record(result(1, 4, true), Duration::from_secs(20));
+		status
+			.history
+			.record(result(2, 1, false), Duration::from_secs(21));
+		let coverage = Coverage {
+			cells: Some(0.25),
+			rows: None,
+		};
+
+		let json = Summary::new(&status, coverage).to_json().unwrap();
+		let summary = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+		let expected = serde_json::json!({
+			"crawling": 3,
+			"delay": 21.0,
+			"coverage": { "cells": 0.25, "rows": null },
+			"blocks": 2,
+			"available_fraction": 0.5,
+			"cells_success_rate": 0.625,
+			"rows_success_rate": null,
+			"recent": [
+				{
+					"block_number": 1,
+					"block_hash": format!("{:?}", H256::repeat_byte(1)),
+					"cells_success_rate": 1.0,
+					"rows_success_rate": null,
+					"available": true,
+					"delay": 20.0,
+					"elapsed": 0.5,
+					"received_at": 20.0,
+					"crawled_at": 21.0,
+				},
+				{
+					"block_number": 2,
+					"block_hash": format!("{:?}", H256::repeat_byte(2)),
+					"cells_success_rate": 0.25,
+					"rows_success_rate": null,
+					"available": false,
+					"delay": 21.0,
+					"elapsed": 0.5,
+					"received_at": 40.0,
+					"crawled_at": 41.0,
+				},
+			],
+		});
+		assert_eq!(summary, expected);
+	}
+}
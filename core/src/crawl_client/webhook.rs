@@ -0,0 +1,240 @@
+//! Posting of the crawl results to an HTTP webhook, for the notifications without the metrics infrastructure.
+//!
+//! # Payload
+//!
+//! Each crawl result is sent as a `POST` request with the `application/json` body of the JSON serialized
+//! [`CrawlResult`], the same as the frames of the [results socket](super::socket), e.g.:
+//!
+//! ```json
+//! {"block_number":10,"block_hash":"0x…","cells":{"total":16,"fetched":4},"rows":null,"available":false,…}
+//! ```
+//!
+//! With [`WebhookOptions::unavailable_only`], only the results of the blocks which are not available
+//! by the configured availability criteria are sent.
+//!
+//! # Delivery
+//!
+//! Webhook receives the results from the crawl results channel, so the crawl loop doesn't wait for the webhook.
+//! Request which fails, or doesn't respond within the timeout, is retried with the doubling delay,
+//! and the result is dropped once the retries are exhausted. Results crawled while the webhook is retried
+//! are queued in the channel, and the oldest are dropped if the channel lags behind.
+
+use super::CrawlResult;
+use color_eyre::{eyre::eyre, Result};
+use hyper::{client::HttpConnector, header, Body, Client, Method, Request, Uri};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{info, warn};
+
+/// Delivery options of the webhook
+#[derive(Clone, Copy, Debug)]
+pub struct WebhookOptions {
+	/// Send only the results of the unavailable blocks
+	pub unavailable_only: bool,
+	/// Time to wait for the response of each request
+	pub timeout: Duration,
+	/// Number of retries of the failed request
+	pub retries: u32,
+	/// Delay before the first retry, doubled on each next retry
+	pub retry_delay: Duration,
+}
+
+/// Parses the webhook URL, only `http` URLs are supported
+pub fn parse_url(url: &str) -> Result<Uri> {
+	let uri = url.parse::<Uri>()?;
+	if uri.scheme_str() != Some("http") || uri.host().is_none() {
+		return Err(eyre!("Webhook URL {url} is not an http URL"));
+	}
+	Ok(uri)
+}
+
+async fn post(
+	client: &Client<HttpConnector>,
+	url: &Uri,
+	body: Vec<u8>,
+	timeout: Duration,
+) -> Result<()> {
+	let request = Request::builder()
+		.method(Method::POST)
+		.uri(url.clone())
+		.header(header::CONTENT_TYPE, "application/json")
+		.body(Body::from(body))?;
+	let response = tokio::time::timeout(timeout, client.request(request))
+		.await
+		.map_err(|_| eyre!("No response in {timeout:?}"))??;
+	let status = response.status();
+	if !status.is_success() {
+		return Err(eyre!("Webhook responded with {status}"));
+	}
+	Ok(())
+}
+
+/// Posts crawl results received on `result_receiver` to the webhook at `url`,
+/// until the result sender is dropped.
+pub async fn post_results(
+	url: Uri,
+	mut result_receiver: broadcast::Receiver<CrawlResult>,
+	options: WebhookOptions,
+) {
+	let client = Client::new();
+	info!("Posting crawl results to webhook {url}");
+	loop {
+		let result = match result_receiver.recv().await {
+			Ok(result) => result,
+			Err(RecvError::Lagged(skipped)) => {
+				warn!(skipped, "Crawl results webhook is lagging behind");
+				continue;
+			},
+			Err(RecvError::Closed) => return,
+		};
+		if options.unavailable_only && result.available {
+			continue;
+		}
+		let block_number = result.block_number;
+		let body = match serde_json::to_vec(&result) {
+			Ok(body) => body,
+			Err(error) => {
+				warn!(block_number, "Cannot encode crawl result: {error}");
+				continue;
+			},
+		};
+
+		let mut delay = options.retry_delay;
+		for attempt in 0..=options.retries {
+			match post(&client, &url, body.clone(), options.timeout).await {
+				Ok(()) => break,
+				Err(error) if attempt < options.retries => {
+					warn!(
+						block_number,
+						"Crawl results webhook failed, retrying in {delay:?}: {error}"
+					);
+					tokio::time::sleep(delay).await;
+					delay *= 2;
+				},
+				Err(error) => {
+					warn!(
+						block_number,
+						"Crawl results webhook failed, dropping the result: {error}"
+					);
+				},
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crawl_client::CrawlStats;
+	use avail_rust::H256;
+	use std::{
+		convert::Infallible,
+		sync::{
+			atomic::{AtomicUsize, Ordering},
+			Arc, Mutex,
+		},
+		time::SystemTime,
+	};
+	use warp::{http::StatusCode, Filter};
+
+	fn result(block_number: u32, available: bool) -> CrawlResult {
+		CrawlResult {
+			block_number,
+			block_hash: H256::repeat_byte(1),
+			cells: Some(CrawlStats {
+				total: 4,
+				fetched: 2,
+			}),
+			rows: None,
+			refetched_cells: 0,
+			reconstructable: None,
+			derived_rows: 0,
+			elapsed: Duration::from_secs(1),
+			received_at: SystemTime::UNIX_EPOCH,
+			crawled_at: SystemTime::UNIX_EPOCH + Duration::from_secs(1),
+			available,
+		}
+	}
+
+	fn options(timeout: Duration, retries: u32) -> WebhookOptions {
+		WebhookOptions {
+			unavailable_only: true,
+			timeout,
+			retries,
+			retry_delay: Duration::from_millis(10),
+		}
+	}
+
+	#[test]
+	fn test_parse_url() {
+		assert!(parse_url("http://localhost:8080/hook").is_ok());
+		assert!(parse_url("https://localhost/hook").is_err());
+		assert!(parse_url("/hook").is_err());
+	}
+
+	#[tokio::test]
+	async fn test_post_results() {
+		// First request fails, and it is retried
+		let failures = Arc::new(AtomicUsize::new(1));
+		let received = Arc::new(Mutex::new(vec![]));
+		let webhook_received = received.clone();
+		let route = warp::post()
+			.and(warp::path("hook"))
+			.and(warp::body::json())
+			.map(move |result: serde_json::Value| {
+				let failed = failures
+					.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+					.is_ok();
+				if failed {
+					return StatusCode::INTERNAL_SERVER_ERROR;
+				}
+				webhook_received.lock().unwrap().push(result);
+				StatusCode::OK
+			});
+		let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+		tokio::spawn(server);
+
+		let url = parse_url(&format!("http://{address}/hook")).unwrap();
+		let (result_tx, result_rx) = broadcast::channel(10);
+		let options = options(Duration::from_secs(1), 1);
+		let handle = tokio::spawn(post_results(url, result_rx, options));
+
+		// Result of the available block is not posted
+		result_tx.send(result(1, false)).unwrap();
+		result_tx.send(result(2, true)).unwrap();
+		result_tx.send(result(3, false)).unwrap();
+		drop(result_tx);
+		handle.await.unwrap();
+
+		let received = received.lock().unwrap();
+		let blocks = received
+			.iter()
+			.map(|result| result["block_number"].as_u64().unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(blocks, vec![1, 3]);
+		assert_eq!(received[0]["cells"]["fetched"], 2);
+		assert_eq!(received[0]["available"], false);
+	}
+
+	#[tokio::test]
+	async fn test_slow_webhook() {
+		let route = warp::post().and_then(|| async {
+			tokio::time::sleep(Duration::from_secs(10)).await;
+			Ok::<_, Infallible>(StatusCode::OK)
+		});
+		let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+		tokio::spawn(server);
+
+		let url = parse_url(&format!("http://{address}/hook")).unwrap();
+		let (result_tx, result_rx) = broadcast::channel(10);
+		let options = options(Duration::from_millis(50), 1);
+		let handle = tokio::spawn(post_results(url, result_rx, options));
+
+		// Results are dropped after the timed out retries, without waiting for the webhook
+		result_tx.send(result(1, false)).unwrap();
+		result_tx.send(result(2, false)).unwrap();
+		drop(result_tx);
+		let finished = tokio::time::timeout(Duration::from_secs(5), handle).await;
+		assert!(finished.is_ok());
+	}
+}
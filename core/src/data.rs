@@ -56,6 +56,14 @@ pub struct FinalitySyncCheckpoint {
 	pub validator_set: Vec<ed25519::Public>,
 }
 
+/// Progress of the block crawl, persisted so the crawl interrupted by restart can be resumed
+#[derive(Serialize, Deserialize, Debug, Clone, Decode, Encode)]
+pub struct CrawlCheckpoint {
+	pub header: AvailHeader,
+	/// Number of the batches of the block cells fetched so far, each stored with the [`CrawlCheckpointBatchKey`]
+	pub batches: u32,
+}
+
 pub struct AppDataKey(pub u32, pub u32);
 impl RecordKey for AppDataKey {
 	type Type = Vec<Vec<u8>>;
@@ -310,3 +318,33 @@ impl RecordKey for SignerNonceKey {
 		SIGNER_NONCE.into()
 	}
 }
+
+pub struct CrawlCheckpointKey;
+
+impl RecordKey for CrawlCheckpointKey {
+	type Type = CrawlCheckpoint;
+
+	fn space(&self) -> Option<&'static str> {
+		Some(APP_STATE_CF)
+	}
+
+	fn key(&self) -> String {
+		CRAWL_CHECKPOINT_KEY.into()
+	}
+}
+
+/// Rows and columns of the block cells fetched in the checkpointed batch, by the batch index
+pub struct CrawlCheckpointBatchKey(pub u32);
+
+impl RecordKey for CrawlCheckpointBatchKey {
+	type Type = Vec<(u32, u16)>;
+
+	fn space(&self) -> Option<&'static str> {
+		Some(APP_STATE_CF)
+	}
+
+	fn key(&self) -> String {
+		let CrawlCheckpointBatchKey(batch) = self;
+		format!("{CRAWL_CHECKPOINT_BATCH_PREFIX}:{batch}")
+	}
+}
@@ -35,3 +35,7 @@ pub const CLIENT_ID_KEY: &str = "client_id";
 pub const P2P_KEYPAIR_KEY: &str = "p2p_keypair";
 /// Key for storing signer nonce
 pub const SIGNER_NONCE: &str = "signer_nonce";
+/// Key for storing the crawl checkpoint of the interrupted block
+pub const CRAWL_CHECKPOINT_KEY: &str = "crawl_checkpoint";
+/// Prefix of the keys for storing the batches of the crawl checkpoint
+pub const CRAWL_CHECKPOINT_BATCH_PREFIX: &str = "crawl_checkpoint_batch";
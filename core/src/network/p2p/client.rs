@@ -18,6 +18,7 @@ use libp2p::{
 };
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
+use std::{collections::HashSet, sync::Arc};
 use sysinfo::System;
 use tokio::sync::{mpsc::UnboundedSender, oneshot};
 #[cfg(target_arch = "wasm32")]
@@ -39,6 +40,8 @@ pub struct Client {
 	dht_parallelization_limit: usize,
 	/// Cell time to live in DHT (in seconds)
 	ttl: Duration,
+	/// Peers whose records are accepted, all peers are accepted if not set
+	allowed_peers: Option<Arc<HashSet<PeerId>>>,
 }
 
 struct DHTCell(Cell);
@@ -84,6 +87,25 @@ impl Client {
 			command_sender: sender,
 			dht_parallelization_limit,
 			ttl,
+			allowed_peers: None,
+		}
+	}
+
+	/// Accepts only the cells and rows served by the given peers or from the local store.
+	/// Records served by the other peers are treated as not found.
+	///
+	/// DHT queries cannot be restricted to the given peers, and the first found record is used,
+	/// so results depend on which peer answers first: if another peer answers before the allowed one,
+	/// the record is not found even if the allowed peer holds it.
+	pub fn with_allowed_peers(mut self, peers: impl IntoIterator<Item = PeerId>) -> Self {
+		self.allowed_peers = Some(Arc::new(peers.into_iter().collect()));
+		self
+	}
+
+	fn is_allowed(&self, peer: Option<PeerId>) -> bool {
+		match (&self.allowed_peers, peer) {
+			(Some(allowed_peers), Some(peer)) => allowed_peers.contains(&peer),
+			_ => true,
 		}
 	}
 
@@ -461,7 +483,12 @@ impl Client {
 
 	// Since callers ignores DHT errors, debug logs are used to observe DHT behavior.
 	// Return type assumes that cell is not found in case when error is present.
-	async fn fetch_cell_from_dht(&self, block_number: u32, position: Position) -> Option<Cell> {
+	// Peer which served the record is returned along with the record validity, if known.
+	async fn fetch_cell_from_dht(
+		&self,
+		block_number: u32,
+		position: Position,
+	) -> (Option<Cell>, Option<(Option<PeerId>, bool)>) {
 		let reference = position.reference(block_number);
 		let record_key = RecordKey::from(reference.as_bytes().to_vec());
 
@@ -470,24 +497,38 @@ impl Client {
 		match self.get_kad_record(record_key).await {
 			Ok(peer_record) => {
 				trace!("Fetched cell {reference} from the DHT");
-
-				let try_content: Result<[u8; COMMITMENT_SIZE + CHUNK_SIZE], _> =
-					peer_record.record.value.try_into();
-
-				let Ok(content) = try_content else {
-					debug!("Cannot convert cell {reference} into 80 bytes");
-					return None;
-				};
-
-				Some(Cell { position, content })
+				self.cell_from_record(position, &reference, peer_record)
 			},
 			Err(error) => {
 				trace!("Cell {reference} not found in the DHT: {error}");
-				None
+				(None, None)
 			},
 		}
 	}
 
+	// Record served by the peer which is not allowed is treated as not found.
+	fn cell_from_record(
+		&self,
+		position: Position,
+		reference: &str,
+		peer_record: PeerRecord,
+	) -> (Option<Cell>, Option<(Option<PeerId>, bool)>) {
+		let peer = peer_record.peer;
+		if !self.is_allowed(peer) {
+			trace!("Cell {reference} is served by the peer which is not allowed");
+			return (None, None);
+		}
+		let try_content: Result<[u8; COMMITMENT_SIZE + CHUNK_SIZE], _> =
+			peer_record.record.value.try_into();
+
+		let Ok(content) = try_content else {
+			debug!("Cannot convert cell {reference} into 80 bytes");
+			return (None, Some((peer, false)));
+		};
+
+		(Some(Cell { position, content }), Some((peer, true)))
+	}
+
 	async fn fetch_row_from_dht(
 		&self,
 		block_number: u32,
@@ -500,7 +541,7 @@ impl Client {
 		trace!("Getting DHT record for reference {}", reference);
 
 		match self.get_kad_record(record_key).await {
-			Ok(peer_record) => Some((row_index.0, peer_record.record.value)),
+			Ok(peer_record) => self.row_from_record(row_index, &reference, peer_record),
 			Err(error) => {
 				debug!("Row {reference} not found in the DHT: {error}");
 				None
@@ -508,6 +549,20 @@ impl Client {
 		}
 	}
 
+	// Record served by the peer which is not allowed is treated as not found.
+	fn row_from_record(
+		&self,
+		row_index: RowIndex,
+		reference: &str,
+		peer_record: PeerRecord,
+	) -> Option<(u32, Vec<u8>)> {
+		if !self.is_allowed(peer_record.peer) {
+			trace!("Row {reference} is served by the peer which is not allowed");
+			return None;
+		}
+		Some((row_index.0, peer_record.record.value))
+	}
+
 	/// Fetches cells from DHT.
 	/// Returns fetched cells and unfetched positions (so we can try RPC fetch).
 	///
@@ -520,12 +575,36 @@ impl Client {
 		block_number: u32,
 		positions: &[Position],
 	) -> (Vec<Cell>, Vec<Position>) {
+		let (fetched, unfetched, _) = self
+			.fetch_cells_from_dht_with_peers(block_number, positions)
+			.await;
+		(fetched, unfetched)
+	}
+
+	/// Fetches cells from DHT, same as [`Client::fetch_cells_from_dht`].
+	/// Additionally returns peers which served the records, along with the record validity
+	/// (`false` if the peer served a record which cannot be converted into a cell).
+	/// Records served from the local store are returned without a peer.
+	///
+	/// # Arguments
+	///
+	/// * `block_number` - Block number
+	/// * `positions` - Cell positions to fetch
+	pub async fn fetch_cells_from_dht_with_peers(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+	) -> (Vec<Cell>, Vec<Position>, Vec<(Option<PeerId>, bool)>) {
 		let mut cells = Vec::<Option<Cell>>::with_capacity(positions.len());
+		let mut peers = vec![];
 
 		for positions in positions.chunks(self.dht_parallelization_limit) {
 			let fetch = |&position| self.fetch_cell_from_dht(block_number, position);
 			let results = join_all(positions.iter().map(fetch)).await;
-			cells.extend(results.into_iter().collect::<Vec<_>>());
+			for (cell, peer) in results {
+				cells.push(cell);
+				peers.extend(peer);
+			}
 		}
 
 		let unfetched = cells
@@ -537,7 +616,7 @@ impl Client {
 
 		let fetched = cells.into_iter().flatten().collect();
 
-		(fetched, unfetched)
+		(fetched, unfetched, peers)
 	}
 
 	/// Fetches rows from DHT.
@@ -619,3 +698,50 @@ impl Client {
 		self.insert_into_dht(records, block).await
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tokio::sync::mpsc::unbounded_channel;
+
+	fn peer_record(peer: PeerId, key: &str, value: Vec<u8>) -> PeerRecord {
+		PeerRecord {
+			peer: Some(peer),
+			record: Record::new(RecordKey::new(&key), value),
+		}
+	}
+
+	#[test]
+	fn test_allowed_peer_answers_after_other_peer() {
+		let (command_sender, _command_receiver) = unbounded_channel();
+		let (allowed, other) = (PeerId::random(), PeerId::random());
+		let client =
+			Client::new(command_sender, 1, Duration::from_secs(60)).with_allowed_peers([allowed]);
+
+		let position = Position { row: 0, col: 0 };
+		let reference = position.reference(1);
+		let content = vec![1; COMMITMENT_SIZE + CHUNK_SIZE];
+
+		// Both peers hold the cell, but only the first answer is used, so the cell is not found
+		let first = peer_record(other, &reference, content.clone());
+		let (cell, served_by) = client.cell_from_record(position, &reference, first);
+		assert!(cell.is_none());
+		assert_eq!(served_by, None);
+
+		let first = peer_record(allowed, &reference, content.clone());
+		let (cell, served_by) = client.cell_from_record(position, &reference, first);
+		assert_eq!(cell.map(|cell| cell.content.to_vec()), Some(content));
+		assert_eq!(served_by, Some((Some(allowed), true)));
+
+		let row_index = RowIndex(0);
+		let reference = row_index.reference(1);
+		let first = peer_record(other, &reference, vec![1; 32]);
+		assert_eq!(client.row_from_record(row_index, &reference, first), None);
+
+		let first = peer_record(allowed, &reference, vec![1; 32]);
+		assert_eq!(
+			client.row_from_record(row_index, &reference, first),
+			Some((0, vec![1; 32]))
+		);
+	}
+}
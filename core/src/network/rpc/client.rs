@@ -872,6 +872,20 @@ impl<D: Database> Client<D> {
 		Ok(res)
 	}
 
+	/// Requests the availability of the block reported by the node with the given RPC `method`,
+	/// as the fraction of the block cells the node considers available
+	pub async fn request_block_availability(&self, method: &str, block_hash: H256) -> Result<f64> {
+		self.with_retries(|client| async move {
+			client
+				.rpc
+				.client
+				.request(method, rpc_params![block_hash])
+				.await
+				.map_err(Into::into)
+		})
+		.await
+	}
+
 	pub async fn get_genesis_hash(&self) -> Result<H256> {
 		let gen_hash = self.current_client().await.api.genesis_hash();
 
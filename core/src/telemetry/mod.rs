@@ -18,6 +18,7 @@ pub enum MetricCounter {
 	IncomingPutRecord,
 	IncomingGetRecord,
 	EventLoopEvent,
+	MetricsFailures,
 }
 
 pub trait MetricName {
@@ -38,13 +39,15 @@ impl MetricName for MetricCounter {
 			IncomingPutRecord => "light.incoming_put_record",
 			IncomingGetRecord => "light.incoming_get_record",
 			EventLoopEvent => "light.event_loop_event",
+			MetricsFailures => "light.crawl.metrics_failures",
 		}
 	}
 }
 
 impl MetricCounter {
 	fn is_buffered(&self) -> bool {
-		!matches!(self, MetricCounter::Starts)
+		// Failures are counted immediately, since the buffers may be lost with the failing flush
+		!matches!(self, MetricCounter::Starts | MetricCounter::MetricsFailures)
 	}
 
 	fn as_last(&self) -> bool {
@@ -3,7 +3,7 @@ use crate::{
 	telemetry::MetricName,
 	types::{Origin, ProjectName},
 };
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
 use opentelemetry::{
 	global,
 	metrics::{Counter, Meter},
@@ -16,7 +16,11 @@ use opentelemetry_sdk::{
 	Resource,
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, time::Duration};
+use std::{
+	collections::HashMap,
+	time::{Duration, Instant},
+};
+use tracing::{debug, info, warn};
 
 // NOTE: Buffers are less space efficient, as opposed to the solution with in place compute.
 // That can be optimized by using dedicated data structure with proper bounds.
@@ -25,25 +29,73 @@ pub struct Metrics {
 	meter: Meter,
 	project_name: ProjectName,
 	origin: Origin,
+	record_prefix: Option<String>,
+	genesis_hash: Option<String>,
 	counters: HashMap<&'static str, Counter<u64>>,
 	metric_buffer: Vec<Record>,
 	counter_buffer: Vec<MetricCounter>,
+	min_flush_interval: Option<Duration>,
+	last_flush: Option<Instant>,
 }
 
 impl Metrics {
-	fn gauge_name(&self, name: &'static str) -> String {
-		format!("{project_name}.{name}", project_name = self.project_name)
+	fn record_name(&self, name: &'static str) -> String {
+		record_name(&self.project_name, self.record_prefix.as_deref(), name)
+	}
+
+	/// Sets the prefix of the recorded metric and counter names, used to disambiguate
+	/// multiple deployments reporting to the same collector.
+	pub fn with_record_prefix(mut self, prefix: Option<String>) -> Self {
+		self.record_prefix = prefix;
+		// Counters are named on creation, so they are created again with the prefix
+		self.counters = init_counters(
+			self.meter.clone(),
+			&self.origin,
+			&self.project_name,
+			self.record_prefix.as_deref(),
+		);
+		self
+	}
+
+	/// Sets the chain genesis hash, added as the `genesis_hash` attribute to all counters and recorded metrics,
+	/// so metrics of different chains are not mixed in the shared dashboards.
+	/// Genesis hash doesn't change, so it is expected to be fetched once on startup.
+	pub fn with_genesis_hash(mut self, genesis_hash: Option<String>) -> Self {
+		self.genesis_hash = genesis_hash;
+		self
+	}
+
+	/// Sets the minimum interval between the flushes, used to limit the metrics volume on fast chains.
+	/// Metrics recorded while the flush is throttled stay buffered, and they are aggregated
+	/// into the next flush the same way as the metrics recorded between the flushes
+	/// (averages are averaged, sums are summed and maximums are maximized).
+	pub fn with_min_flush_interval(mut self, interval: Option<Duration>) -> Self {
+		self.min_flush_interval = interval;
+		self
+	}
+
+	/// Returns `true` if the minimum flush interval since the last flush hasn't passed at `now`.
+	pub fn is_throttled(&self, now: Instant) -> bool {
+		let (Some(interval), Some(last_flush)) = (self.min_flush_interval, self.last_flush) else {
+			return false;
+		};
+		now.saturating_duration_since(last_flush) < interval
 	}
 
 	fn map_attributes(&self, attributes: Vec<(String, String)>) -> Vec<KeyValue> {
+		let genesis_hash = self
+			.genesis_hash
+			.clone()
+			.map(|genesis_hash| ("genesis_hash".to_string(), genesis_hash));
 		attributes
 			.into_iter()
+			.chain(genesis_hash)
 			.map(|(k, v)| KeyValue::new(k, v))
 			.collect()
 	}
 
 	fn record_u64(&self, name: &'static str, value: u64, attributes: Vec<KeyValue>) -> Result<()> {
-		let gauge_name = self.gauge_name(name);
+		let gauge_name = self.record_name(name);
 		self.meter
 			.u64_observable_gauge(gauge_name)
 			.with_callback(move |observer| {
@@ -54,7 +106,7 @@ impl Metrics {
 	}
 
 	fn record_f64(&self, name: &'static str, value: f64, attributes: Vec<KeyValue>) -> Result<()> {
-		let gauge_name = self.gauge_name(name);
+		let gauge_name = self.record_name(name);
 		self.meter
 			.f64_observable_gauge(gauge_name)
 			.with_callback(move |observer| {
@@ -91,6 +143,7 @@ impl Metrics {
 
 	/// Calculates counters and average metrics, and flushes buffers to the collector.
 	pub fn flush(&mut self, attributes: Vec<(String, String)>) -> Result<()> {
+		self.last_flush = Some(Instant::now());
 		let metric_attributes = self.map_attributes(attributes);
 		let counters = flatten_counters(&self.counter_buffer);
 		self.counter_buffer.clear();
@@ -102,15 +155,27 @@ impl Metrics {
 			self.counters[&counter].add(value, &metric_attributes);
 		}
 
-		// TODO: Aggregate errors instead of early return
+		// Failed metric is not returned early, so the rest of the metrics are still recorded
+		let mut errors = vec![];
 		for (metric, value) in metrics_u64.into_iter() {
-			self.record_u64(metric, value, metric_attributes.clone())?;
+			if let Err(error) = self.record_u64(metric, value, metric_attributes.clone()) {
+				errors.push(format!("{metric}: {error:#}"));
+			}
 		}
 
 		for (metric, value) in metrics_f64.into_iter() {
-			self.record_f64(metric, value, metric_attributes.clone())?;
+			if let Err(error) = self.record_f64(metric, value, metric_attributes.clone()) {
+				errors.push(format!("{metric}: {error:#}"));
+			}
 		}
 
+		if !errors.is_empty() {
+			return Err(eyre!(
+				"Failed to record {} metrics: {}",
+				errors.len(),
+				errors.join(", ")
+			));
+		}
 		Ok(())
 	}
 }
@@ -118,6 +183,7 @@ impl Metrics {
 #[derive(Debug)]
 pub enum Record {
 	MaxU64(&'static str, u64),
+	SumU64(&'static str, u64),
 	AvgF64(&'static str, f64),
 }
 
@@ -173,14 +239,16 @@ fn flatten_counters(buffer: &[MetricCounter]) -> HashMap<&'static str, u64> {
 
 /// Aggregates buffered metrics into `u64` or `f64` values, depending on the metric.
 /// Returned values are a `HashMap`s where the keys are the metric name,
-/// and values are the aggregations (avg, max, etc.) of those metrics.
+/// and values are the aggregations (avg, max, sum) of those metrics.
 fn flatten_metrics(buffer: &[Record]) -> (HashMap<&'static str, u64>, HashMap<&'static str, f64>) {
 	let mut u64_maximums: HashMap<&'static str, Vec<u64>> = HashMap::new();
+	let mut u64_sums: HashMap<&'static str, u64> = HashMap::new();
 	let mut f64_averages: HashMap<&'static str, Vec<f64>> = HashMap::new();
 
 	for value in buffer {
 		match value {
 			Record::MaxU64(name, number) => u64_maximums.entry(name).or_default().push(*number),
+			Record::SumU64(name, number) => *u64_sums.entry(name).or_default() += number,
 			Record::AvgF64(name, number) => f64_averages.entry(name).or_default().push(*number),
 		}
 	}
@@ -188,6 +256,7 @@ fn flatten_metrics(buffer: &[Record]) -> (HashMap<&'static str, u64>, HashMap<&'
 	let u64_metrics = u64_maximums
 		.into_iter()
 		.map(|(name, v)| (name, v.into_iter().max().unwrap_or(0)))
+		.chain(u64_sums)
 		.collect();
 
 	let f64_metrics = f64_averages
@@ -198,10 +267,18 @@ fn flatten_metrics(buffer: &[Record]) -> (HashMap<&'static str, u64>, HashMap<&'
 	(u64_metrics, f64_metrics)
 }
 
+fn record_name(project_name: &ProjectName, prefix: Option<&str>, name: &'static str) -> String {
+	match prefix {
+		Some(prefix) => format!("{project_name}.{prefix}.{name}"),
+		None => format!("{project_name}.{name}"),
+	}
+}
+
 fn init_counters(
 	meter: Meter,
 	origin: &Origin,
-	project_name: ProjectName,
+	project_name: &ProjectName,
+	prefix: Option<&str>,
 ) -> HashMap<&'static str, Counter<u64>> {
 	[
 		MetricCounter::Starts,
@@ -214,17 +291,74 @@ fn init_counters(
 		MetricCounter::IncomingPutRecord,
 		MetricCounter::IncomingGetRecord,
 		MetricCounter::EventLoopEvent,
+		MetricCounter::MetricsFailures,
 	]
 	.iter()
 	.filter(|counter| MetricCounter::is_allowed(counter, origin))
 	.map(|counter| {
-		let otel_counter_name = format!("{project_name}.{name}", name = counter.name());
+		let otel_counter_name = record_name(project_name, prefix, counter.name());
 		// Keep the `static str as the local buffer map key, but change the OTel counter name`
 		(counter.name(), meter.u64_counter(otel_counter_name).build())
 	})
 	.collect()
 }
 
+/// Tracks failures of flushing metrics to the collector.
+///
+/// While the collector is unreachable, every flush fails, so only the transition
+/// to the failing state is logged as a warning, and the recovery is logged once it succeeds again.
+#[derive(Debug, Default)]
+pub struct FlushFailures {
+	consecutive: u32,
+	total: u64,
+}
+
+impl FlushFailures {
+	/// Tracks the flush result, and returns `true` if the flush succeeded.
+	pub fn track(&mut self, result: Result<()>) -> bool {
+		match result {
+			Ok(()) => {
+				if self.consecutive > 0 {
+					info!(
+						total_failures = self.total,
+						"Metrics flush recovered after {} failed attempts", self.consecutive
+					);
+				}
+				self.consecutive = 0;
+				true
+			},
+			Err(error) => {
+				if self.consecutive == 0 {
+					warn!("Metrics flush is failing, further failures are logged on debug level: {error:#}");
+				} else {
+					debug!(
+						consecutive_failures = self.consecutive,
+						"Metrics flush failed: {error:#}"
+					);
+				}
+				self.consecutive = self.consecutive.saturating_add(1);
+				self.total = self.total.saturating_add(1);
+				false
+			},
+		}
+	}
+
+	/// Returns `true` if the last flush failed.
+	pub fn is_failing(&self) -> bool {
+		self.consecutive > 0
+	}
+
+	/// Number of failed flushes since the last successful one
+	pub fn consecutive(&self) -> u32 {
+		self.consecutive
+	}
+
+	/// Number of failed flushes since the start
+	pub fn total(&self) -> u64 {
+		self.total
+	}
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct OtelConfig {
@@ -275,14 +409,18 @@ pub fn initialize(
 	let meter = global::meter("avail_light_client");
 
 	// Initialize counters - they need to persist unlike Gauges that are recreated on every record
-	let counters = init_counters(meter.clone(), origin, project_name.clone());
+	let counters = init_counters(meter.clone(), origin, &project_name, None);
 	Ok(Metrics {
 		meter,
 		project_name,
 		origin: origin.clone(),
+		record_prefix: None,
+		genesis_hash: None,
 		counters,
 		metric_buffer: vec![],
 		counter_buffer: vec![],
+		min_flush_interval: None,
+		last_flush: None,
 	})
 }
 
@@ -396,4 +534,150 @@ mod tests {
 		assert_eq!(m_f64.get("light.block.confidence"), Some(&98.5));
 		assert_eq!(m_f64.get("light.dht.connected_peers"), Some(&85.0));
 	}
+
+	#[test]
+	fn test_flatten_sum_metrics() {
+		let buffer = vec![
+			Record::SumU64("light.sum", 1),
+			Record::MaxU64("light.max", 3),
+			Record::SumU64("light.sum", 1),
+			Record::MaxU64("light.max", 1),
+			Record::SumU64("light.sum", 5),
+		];
+		let (m_u64, m_f64) = super::flatten_metrics(&buffer);
+		assert!(m_f64.is_empty());
+		assert_eq!(m_u64.len(), 2);
+		assert_eq!(m_u64.get("light.sum"), Some(&7));
+		assert_eq!(m_u64.get("light.max"), Some(&3));
+	}
+
+	fn test_metrics(origin: Origin) -> Metrics {
+		Metrics {
+			meter: global::meter("test"),
+			project_name: ProjectName::new("avail".to_string()),
+			origin,
+			record_prefix: None,
+			genesis_hash: None,
+			counters: HashMap::new(),
+			metric_buffer: vec![],
+			counter_buffer: vec![],
+			min_flush_interval: None,
+			last_flush: None,
+		}
+	}
+
+	#[test]
+	fn test_record_prefix() {
+		let metrics = test_metrics(Origin::External);
+		let name = "light.crawl.cells_success_rate";
+		assert_eq!(
+			metrics.record_name(name),
+			"avail.light.crawl.cells_success_rate"
+		);
+		let counter = MetricCounter::Starts.name();
+		assert_eq!(metrics.record_name(counter), "avail.light.starts");
+
+		let metrics = metrics.with_record_prefix(Some("mainnet".to_string()));
+		assert_eq!(
+			metrics.record_name(name),
+			"avail.mainnet.light.crawl.cells_success_rate"
+		);
+		// Counters are created again with the prefixed names
+		assert_eq!(metrics.record_name(counter), "avail.mainnet.light.starts");
+		assert!(metrics.counters.contains_key(counter));
+	}
+
+	#[test]
+	fn test_genesis_hash() {
+		let attributes = vec![("role".to_string(), "crawler".to_string())];
+		let metrics = test_metrics(Origin::External);
+		assert_eq!(
+			metrics.map_attributes(attributes.clone()),
+			vec![KeyValue::new("role", "crawler")]
+		);
+
+		let genesis_hash = "0x6f09966420b2608d1947ccfb0f2a362450d1fc7fd902c29b67c906eaa965a7ae";
+		let metrics = metrics.with_genesis_hash(Some(genesis_hash.to_string()));
+		assert_eq!(
+			metrics.map_attributes(attributes),
+			vec![
+				KeyValue::new("role", "crawler"),
+				KeyValue::new("genesis_hash", genesis_hash)
+			]
+		);
+	}
+
+	#[test]
+	fn test_min_flush_interval() {
+		let mut metrics = test_metrics(Origin::Internal);
+		assert!(!metrics.is_throttled(Instant::now()));
+		metrics.flush(vec![]).unwrap();
+		assert!(!metrics.is_throttled(Instant::now()));
+
+		let interval = Duration::from_secs(60);
+		let mut metrics = metrics.with_min_flush_interval(Some(interval));
+		metrics.flush(vec![]).unwrap();
+		let flushed_at = metrics.last_flush.unwrap();
+
+		// Rapid blocks are recorded while the flush is throttled, and they are aggregated into the next flush
+		for (height, confidence) in [(1, 90.0), (2, 93.0), (3, 99.0)] {
+			metrics.record(MetricValue::BlockHeight(height));
+			metrics.record(MetricValue::BlockConfidence(confidence));
+			assert!(metrics.is_throttled(flushed_at + Duration::from_secs(u64::from(height))));
+		}
+		assert!(!metrics.is_throttled(flushed_at + interval));
+
+		let (m_u64, m_f64) = super::flatten_metrics(&metrics.metric_buffer);
+		assert_eq!(m_u64.get("light.block.height"), Some(&3));
+		assert_eq!(m_f64.get("light.block.confidence"), Some(&94.0));
+		metrics.flush(vec![]).unwrap();
+		assert!(metrics.metric_buffer.is_empty());
+	}
+
+	#[test]
+	fn test_metrics_failures_counter() {
+		let mut metrics = test_metrics(Origin::Internal).with_record_prefix(None);
+		let counter = MetricCounter::MetricsFailures.name();
+		assert_eq!(
+			metrics.record_name(counter),
+			"avail.light.crawl.metrics_failures"
+		);
+
+		// Failure is counted immediately, so it is not lost if the next flush fails too
+		metrics.record(MetricValue::BlockHeight(1));
+		metrics.count(MetricCounter::MetricsFailures, vec![]);
+		assert!(metrics.counter_buffer.is_empty());
+		assert_eq!(metrics.metric_buffer.len(), 1);
+	}
+
+	#[test]
+	fn test_flush_failures() {
+		// Sink which fails while the collector is unreachable
+		let flush = |reachable: bool| -> Result<()> {
+			if reachable {
+				Ok(())
+			} else {
+				Err(color_eyre::eyre::eyre!("Collector is unreachable"))
+			}
+		};
+
+		let mut failures = FlushFailures::default();
+		assert!(failures.track(flush(true)));
+		assert!(!failures.is_failing());
+
+		for _ in 0..3 {
+			assert!(!failures.track(flush(false)));
+		}
+		assert!(failures.is_failing());
+		assert_eq!(failures.consecutive(), 3);
+		assert_eq!(failures.total(), 3);
+
+		assert!(failures.track(flush(true)));
+		assert!(!failures.is_failing());
+		assert_eq!(failures.consecutive(), 0);
+
+		assert!(!failures.track(flush(false)));
+		assert_eq!(failures.consecutive(), 1);
+		assert_eq!(failures.total(), 4);
+	}
 }
@@ -318,6 +318,111 @@ pub mod block_matrix_partition_format {
 		let value = &String::deserialize(deserializer)?;
 		parse(value).map_err(serde::de::Error::custom)
 	}
+
+	/// Partition range format, which supports a single partition (e.g. `3/20`),
+	/// a range of consecutive partitions of the same fraction (e.g. `3-5/20`),
+	/// and a percentage range of the block matrix (e.g. `0%-5%`, which is the same as `1/20`).
+	pub mod range {
+		use avail_rust::kate_recovery::matrix::Partition;
+		use serde::{self, Deserialize, Deserializer, Serializer};
+
+		fn gcd(a: u8, b: u8) -> u8 {
+			if b == 0 {
+				a
+			} else {
+				gcd(b, a % b)
+			}
+		}
+
+		/// Parses percentage range into the range of the largest partitions with the same bounds
+		fn parse_percentage(value: &str) -> Result<Vec<Partition>, String> {
+			let invalid = || format!("Invalid partition percentage range: {value}");
+			let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+			let percentage = |value: &str| {
+				value
+					.strip_suffix('%')
+					.and_then(|value| value.parse::<u8>().ok())
+					.filter(|&value| value <= 100)
+					.ok_or_else(invalid)
+			};
+			let (start, end) = (percentage(start)?, percentage(end)?);
+			if start >= end {
+				return Err(invalid());
+			}
+
+			let size = gcd(gcd(start, end), 100);
+			let fraction = 100 / size;
+			Ok((start / size + 1..=end / size)
+				.map(|number| Partition { number, fraction })
+				.collect())
+		}
+
+		pub fn parse(value: &str) -> Result<Vec<Partition>, String> {
+			if value.ends_with('%') {
+				return parse_percentage(value);
+			}
+			let Some((numbers, fraction)) = value.split_once('/') else {
+				return Err(format!("Invalid partition parameter: {value:?}"));
+			};
+			let Some((start, end)) = numbers.split_once('-') else {
+				return super::parse(value).map(|partition| vec![partition]);
+			};
+
+			let Partition { number: start, .. } = super::parse(&format!("{start}/{fraction}"))?;
+			let Partition {
+				number: end,
+				fraction,
+			} = super::parse(&format!("{end}/{fraction}"))?;
+			if start > end {
+				return Err(format!("Invalid partition range: {value}"));
+			}
+
+			Ok((start..=end)
+				.map(|number| Partition { number, fraction })
+				.collect())
+		}
+
+		pub fn format(partitions: &[Partition]) -> Result<String, String> {
+			match partitions {
+				[] => Err("Partition range cannot be empty".to_string()),
+				[Partition { number, fraction }] => Ok(format!("{number}/{fraction}")),
+				[first, .., last] => {
+					let is_range =
+						partitions
+							.iter()
+							.zip(first.number..)
+							.all(|(partition, number)| {
+								partition.number == number && partition.fraction == first.fraction
+							});
+					if !is_range {
+						return Err(
+							"Partitions are not a range of consecutive partitions".to_string()
+						);
+					}
+					Ok(format!(
+						"{}-{}/{}",
+						first.number, last.number, first.fraction
+					))
+				},
+			}
+		}
+
+		pub fn serialize<S>(partitions: &[Partition], serializer: S) -> Result<S::Ok, S::Error>
+		where
+			S: Serializer,
+		{
+			let s = format(partitions).map_err(serde::ser::Error::custom)?;
+			serializer.serialize_str(&s)
+		}
+
+		pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Partition>, D::Error>
+		where
+			D: Deserializer<'de>,
+		{
+			let value = &String::deserialize(deserializer)?;
+			parse(value).map_err(serde::de::Error::custom)
+		}
+	}
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(try_from = "String")]
@@ -371,8 +476,13 @@ pub struct Delay(pub Option<Duration>);
 
 impl Delay {
 	pub fn sleep_duration(&self, from: Instant) -> Option<Duration> {
+		self.sleep_duration_at(from, Instant::now())
+	}
+
+	/// Returns the remaining delay since `from`, at the time `now`
+	pub fn sleep_duration_at(&self, from: Instant, now: Instant) -> Option<Duration> {
 		(self.0?)
-			.checked_sub(from.elapsed())
+			.checked_sub(now.saturating_duration_since(from))
 			.filter(|duration| !duration.is_zero())
 	}
 }
@@ -671,7 +781,9 @@ Project name must only contain alphanumeric characters.
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use avail_rust::kate_recovery::matrix::Partition;
 	use serde_json;
+	use test_case::test_case;
 
 	#[test]
 	fn test_project_name_patterns() {
@@ -693,4 +805,65 @@ mod tests {
 			Ok(_) => panic!("Deserialization should have failed"),
 		}
 	}
+
+	#[derive(Serialize, Deserialize)]
+	struct PartitionRange {
+		#[serde(with = "block_matrix_partition_format::range")]
+		partitions: Vec<Partition>,
+	}
+
+	#[test_case("\"1/1\"" => Ok(vec![(1, 1)]); "entire block")]
+	#[test_case("\"3/20\"" => Ok(vec![(3, 20)]); "single partition")]
+	#[test_case("\"3-5/20\"" => Ok(vec![(3, 20), (4, 20), (5, 20)]); "range")]
+	#[test_case("\"5-5/20\"" => Ok(vec![(5, 20)]); "single partition range")]
+	#[test_case("\"5-3/20\"" => Err(()); "reversed range")]
+	#[test_case("\"0-3/20\"" => Err(()); "range starting with 0")]
+	#[test_case("\"3-21/20\"" => Err(()); "range out of bounds")]
+	#[test_case("\"3-/20\"" => Err(()); "range without end")]
+	#[test_case("\"3-5\"" => Err(()); "range without fraction")]
+	#[test_case("\"0%-5%\"" => Ok(vec![(1, 20)]); "percentage range")]
+	#[test_case("\"10%-30%\"" => Ok(vec![(2, 10), (3, 10)]); "percentage range of partitions")]
+	#[test_case("\"0%-100%\"" => Ok(vec![(1, 1)]); "entire block percentage range")]
+	#[test_case("\"99%-100%\"" => Ok(vec![(100, 100)]); "last percent")]
+	#[test_case("\"3%-7%\"" => Ok((4..=7).map(|number| (number, 100)).collect()); "percents")]
+	#[test_case("\"5%-5%\"" => Err(()); "empty percentage range")]
+	#[test_case("\"7%-3%\"" => Err(()); "reversed percentage range")]
+	#[test_case("\"0%-101%\"" => Err(()); "percentage out of bounds")]
+	#[test_case("\"0-5%\"" => Err(()); "percentage without percent sign")]
+	#[test_case("\"5%\"" => Err(()); "percentage without range")]
+	fn test_partition_range_format(value: &str) -> Result<Vec<(u8, u8)>, ()> {
+		let json = format!("{{\"partitions\":{value}}}");
+		let range = serde_json::from_str::<PartitionRange>(&json).map_err(|_| ())?;
+		// Serialized range is the same as the deserialized one, except for the single partition ranges
+		let serialized = serde_json::to_string(&range).unwrap();
+		let reserialized = serde_json::from_str::<PartitionRange>(&serialized).unwrap();
+		assert_eq!(serialized, serde_json::to_string(&reserialized).unwrap());
+		Ok(range
+			.partitions
+			.iter()
+			.map(|partition| (partition.number, partition.fraction))
+			.collect())
+	}
+
+	#[test]
+	fn test_partition_range_serialization() {
+		let serialize = |partitions: Vec<(u8, u8)>| {
+			let partitions = partitions
+				.into_iter()
+				.map(|(number, fraction)| Partition { number, fraction })
+				.collect();
+			serde_json::to_string(&PartitionRange { partitions }).map_err(|_| ())
+		};
+		assert_eq!(
+			serialize(vec![(3, 20)]),
+			Ok(r#"{"partitions":"3/20"}"#.to_string())
+		);
+		assert_eq!(
+			serialize(vec![(3, 20), (4, 20), (5, 20)]),
+			Ok(r#"{"partitions":"3-5/20"}"#.to_string())
+		);
+		assert!(serialize(vec![]).is_err());
+		assert!(serialize(vec![(3, 20), (5, 20)]).is_err());
+		assert!(serialize(vec![(3, 20), (4, 10)]).is_err());
+	}
 }
@@ -1,25 +1,19 @@
-use std::fs;
+use std::{collections::BTreeMap, fs};
 
 use avail_light_core::{
-	crawl_client::CrawlMode,
+	crawl_client::CrawlConfig,
 	network::{
 		p2p::{configuration::LibP2PConfig, BOOTSTRAP_LIST_EMPTY_MESSAGE},
 		rpc::configuration::RPCConfig,
 		Network,
 	},
 	telemetry::otlp::OtelConfig,
-	types::{block_matrix_partition_format, tracing_level_format, Origin, PeerAddress},
+	types::{tracing_level_format, Origin, PeerAddress},
 };
-use avail_rust::kate_recovery::matrix::Partition;
 use clap::{command, Parser};
 use color_eyre::{eyre::eyre, Result};
 use serde::{Deserialize, Serialize};
-use tracing::Level;
-
-pub const ENTIRE_BLOCK: Partition = Partition {
-	number: 1,
-	fraction: 1,
-};
+use tracing::{info, Level};
 
 #[derive(Parser)]
 #[command(version)]
@@ -39,6 +33,9 @@ pub struct CliOpts {
 	/// Testnet or devnet selection.
 	#[arg(short, long, value_name = "network")]
 	pub network: Option<Network>,
+	/// Prints the manifest of the crawl metrics as JSON and exits.
+	#[arg(long)]
+	pub metrics_manifest: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -65,13 +62,49 @@ pub struct Config {
 	pub otel: OtelConfig,
 	/// Crawl block periodically to ensure availability. (default: false)
 	pub crawl_block: bool,
-	/// Crawl block delay. Increment to ensure large block crawling (default: 20)
-	pub crawl_block_delay: u64,
-	/// Crawl block mode. Available modes are "cells", "rows" and "both" (default: "cells")
-	pub crawl_block_mode: CrawlMode,
-	/// Fraction and number of the block matrix part to crawl (e.g. 2/20 means second 1/20 part of a matrix) (default: 1/1)
-	#[serde(with = "block_matrix_partition_format")]
-	pub crawl_block_matrix_partition: Partition,
+	/// Peers used to bootstrap the DHT instead of the network bootstrap peers (default: empty).
+	/// Crawled cells and rows are accepted only from these peers, or from the local store.
+	/// Results depend on which peer answers first, since the DHT can answer from any other peer,
+	/// whose record is then counted as not fetched.
+	/// Intended for testing and isolated deployments against a controlled DHT.
+	pub crawl_peers: Vec<PeerAddress>,
+	/// Prefix of the crawl metric names, e.g. network or instance name (default: none).
+	pub crawl_metrics_prefix: Option<String>,
+	/// Static attributes added to the crawl metrics (default: empty).
+	pub crawl_metrics_attributes: BTreeMap<String, String>,
+	/// Minimum interval in seconds between the crawl metrics flushes (default: none).
+	/// Metrics of the blocks crawled in between are aggregated into the next flush.
+	pub crawl_metrics_min_interval: Option<u64>,
+	/// Path of the file to periodically write crawl metrics in OpenMetrics text format to (default: none).
+	pub crawl_openmetrics_path: Option<String>,
+	/// Interval in seconds of writing the OpenMetrics file, at least 1 second (default: 60).
+	pub crawl_openmetrics_interval: u64,
+	/// Path of the Unix domain socket to stream crawl results to, as length-prefixed JSON (default: none).
+	pub crawl_results_socket: Option<String>,
+	/// Interval in seconds of reconnecting to the crawl results socket, at least 1 second (default: 5).
+	pub crawl_results_socket_reconnect: u64,
+	/// URL of the HTTP webhook to post crawl results to, as JSON (default: none).
+	pub crawl_results_webhook: Option<String>,
+	/// Post only the results of the unavailable blocks to the webhook (default: true).
+	pub crawl_results_webhook_unavailable_only: bool,
+	/// Timeout in seconds of the webhook requests, at least 1 second (default: 5).
+	pub crawl_results_webhook_timeout: u64,
+	/// Number of retries of the failed webhook requests (default: 3).
+	pub crawl_results_webhook_retries: u32,
+	/// Path of the file to append crawl results to as CSV, or "-" for the standard output (default: none).
+	pub crawl_results_csv: Option<String>,
+	/// URL of the coordinator to load the crawled partition assignment from at startup (default: none).
+	/// Configured partition is crawled if the assignment cannot be loaded.
+	pub crawl_partition_assignment_url: Option<String>,
+	/// Timeout in seconds of loading the partition assignment, at least 1 second (default: 5).
+	pub crawl_partition_assignment_timeout: u64,
+	/// RPC method of the full node which reports the block availability, to reconcile crawl results with (default: none).
+	/// Node responds with the fraction of the block cells it considers available, for the block hash parameter.
+	pub crawl_reconcile_method: Option<String>,
+	/// Difference between the crawled success rate and the reported availability logged as a discrepancy (default: 0.2).
+	pub crawl_reconcile_threshold: f64,
+	#[serde(flatten)]
+	pub crawl: CrawlConfig,
 }
 
 impl Default for Config {
@@ -87,9 +120,24 @@ impl Default for Config {
 			rpc: Default::default(),
 			otel: Default::default(),
 			crawl_block: false,
-			crawl_block_delay: 20,
-			crawl_block_mode: CrawlMode::Cells,
-			crawl_block_matrix_partition: ENTIRE_BLOCK,
+			crawl_peers: vec![],
+			crawl_metrics_prefix: None,
+			crawl_metrics_attributes: BTreeMap::new(),
+			crawl_metrics_min_interval: None,
+			crawl_openmetrics_path: None,
+			crawl_openmetrics_interval: 60,
+			crawl_results_socket: None,
+			crawl_results_socket_reconnect: 5,
+			crawl_results_webhook: None,
+			crawl_results_webhook_unavailable_only: true,
+			crawl_results_webhook_timeout: 5,
+			crawl_results_webhook_retries: 3,
+			crawl_results_csv: None,
+			crawl_partition_assignment_url: None,
+			crawl_partition_assignment_timeout: 5,
+			crawl_reconcile_method: None,
+			crawl_reconcile_threshold: 0.2,
+			crawl: Default::default(),
 		}
 	}
 }
@@ -114,6 +162,11 @@ pub fn load(opts: &CliOpts) -> Result<Config> {
 		config.genesis_hash = network.genesis_hash().to_string();
 	}
 
+	if !config.crawl_peers.is_empty() {
+		info!("Using crawl peers instead of the bootstrap peers");
+		config.libp2p.bootstraps = config.crawl_peers.clone();
+	}
+
 	if config.libp2p.bootstraps.is_empty() {
 		return Err(eyre!("{BOOTSTRAP_LIST_EMPTY_MESSAGE}"));
 	}
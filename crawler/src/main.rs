@@ -1,5 +1,9 @@
 use avail_light_core::{
-	crawl_client::{self, CrawlMetricValue, OutputEvent as CrawlerEvent},
+	crawl_client::{
+		self, manifest, openmetrics::Snapshot, webhook::WebhookOptions, CrawlChannels, CrawlConfig,
+		CrawlExtensions, CrawlMetricValue, CrawlResult, CrawlStatus, FailureCategory,
+		OutputEvent as CrawlerEvent, TokioClock,
+	},
 	data::{Database, LatestHeaderKey, RpcNodeKey, DB},
 	network::{
 		p2p::{self, OutputEvent as P2pEvent},
@@ -7,25 +11,33 @@ use avail_light_core::{
 	},
 	shutdown::Controller,
 	telemetry::{
-		otlp::{self, Metrics},
+		otlp::{self, FlushFailures, Metrics},
 		MetricCounter, MetricValue,
 	},
-	types::{BlockVerified, ProjectName},
+	types::ProjectName,
 	utils::{default_subscriber, install_panic_hooks, json_subscriber, spawn_in_span},
 };
+use avail_rust::kate_recovery::matrix::Partition;
 use clap::Parser;
 use color_eyre::{
 	eyre::{eyre, Context},
 	Result,
 };
 use config::Config;
+use libp2p::{Multiaddr, PeerId};
 use maintenance::OutputEvent as MaintenanceEvent;
-use std::{fs, path::Path};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	sync::{Arc, RwLock},
+	time::{Duration, Instant},
+};
 use tokio::{
 	select,
 	sync::{
 		broadcast,
 		mpsc::{self, UnboundedReceiver},
+		watch,
 	},
 };
 use tracing::{error, info, span, warn, Level};
@@ -44,6 +56,10 @@ fn clean_db_state(path: &str) -> Result<()> {
 pub async fn main() -> Result<()> {
 	let shutdown = Controller::new();
 	let opts = config::CliOpts::parse();
+	if opts.metrics_manifest {
+		println!("{}", manifest::encode()?);
+		return Ok(());
+	}
 	let config = config::load(&opts)?;
 
 	if config.log_format_json {
@@ -68,20 +84,78 @@ pub async fn main() -> Result<()> {
 	#[cfg(feature = "rocksdb")]
 	let db = DB::open(&config.avail_path)?;
 
-	let _ = spawn_in_span(run(config, db, shutdown)).await?;
+	spawn_in_span(run(config, opts.config, db, shutdown)).await??;
 
 	Ok(())
 }
 
-async fn run(config: Config, db: DB, shutdown: Controller<String>) -> Result<()> {
+/// Reloads crawl configuration from the configuration file on SIGHUP signal (Unix only).
+/// Partition assigned by the coordinator, if any, is kept instead of the reloaded partition.
+async fn reload_crawl_config(
+	path: String,
+	sender: watch::Sender<CrawlConfig>,
+	assigned_partition: Option<Vec<Partition>>,
+) {
+	#[cfg(not(unix))]
+	{
+		let _ = (path, sender, assigned_partition);
+		warn!("Crawl configuration reload is not supported on this platform");
+	}
+
+	#[cfg(unix)]
+	{
+		use tokio::signal::unix::{signal, SignalKind};
+
+		let mut hangup = match signal(SignalKind::hangup()) {
+			Ok(hangup) => hangup,
+			Err(error) => {
+				error!("Cannot listen to SIGHUP, crawl configuration reload is disabled: {error}");
+				return;
+			},
+		};
+
+		while hangup.recv().await.is_some() {
+			info!("Reloading crawl configuration from {path}...");
+			match confy::load_path::<Config>(&path) {
+				Ok(mut config) => match config.crawl.validate() {
+					Ok(()) => {
+						for warning in config.crawl.ignored_options() {
+							warn!("Invalid crawl configuration: {warning}");
+						}
+						if let Some(partition) = &assigned_partition {
+							config.crawl.crawl_block_matrix_partition = partition.clone();
+						}
+						sender.send_replace(config.crawl);
+					},
+					Err(problems) => {
+						error!("Cannot reload crawl configuration: {}", problems.join(", "))
+					},
+				},
+				Err(error) => error!("Cannot reload crawl configuration: {error}"),
+			}
+		}
+	}
+}
+
+async fn run(
+	mut config: Config,
+	config_path: Option<String>,
+	db: DB,
+	shutdown: Controller<String>,
+) -> Result<()> {
 	let version = clap::crate_version!();
 	let rev = env!("GIT_COMMIT_HASH");
 	info!(version, rev, "Running {}", clap::crate_name!());
 	info!("Using configuration: {config:?}");
+	config
+		.crawl
+		.validate()
+		.map_err(|problems| eyre!("Invalid crawl configuration: {}", problems.join(", ")))?;
+	for warning in config.crawl.ignored_options() {
+		warn!("Invalid crawl configuration: {warning}");
+	}
 
 	let (p2p_keypair, p2p_peer_id) = p2p::identity(&config.libp2p, db.clone())?;
-	let partition = config.crawl_block_matrix_partition;
-	let partition_size = format!("{}/{}", partition.number, partition.fraction);
 
 	let (p2p_client, p2p_event_loop, p2p_event_receiver) = p2p::init(
 		config.libp2p.clone(),
@@ -114,7 +188,7 @@ async fn run(config: Config, db: DB, shutdown: Controller<String>) -> Result<()>
 	}));
 
 	let (rpc_events_sender, _) = broadcast::channel(1000);
-	let (_, rpc_subscriptions) = rpc::init(
+	let (rpc_client, rpc_subscriptions) = rpc::init(
 		db.clone(),
 		&config.genesis_hash,
 		&config.rpc,
@@ -123,6 +197,16 @@ async fn run(config: Config, db: DB, shutdown: Controller<String>) -> Result<()>
 	)
 	.await?;
 
+	// Genesis hash doesn't change, so it is fetched once and attached to all crawl metrics
+	let genesis_hash = match rpc_client.get_genesis_hash().await {
+		Ok(genesis_hash) => format!("{genesis_hash:?}"),
+		Err(error) => {
+			warn!("Unable to get the genesis hash, using the configured genesis hash: {error:#}");
+			config.genesis_hash.clone()
+		},
+	};
+	info!(genesis_hash, "Crawling chain with the genesis hash");
+
 	let first_header_rpc_event_receiver = rpc_events_sender.subscribe();
 	let client_rpc_event_receiver = rpc_events_sender.subscribe();
 
@@ -152,28 +236,121 @@ async fn run(config: Config, db: DB, shutdown: Controller<String>) -> Result<()>
 
 	db.put(LatestHeaderKey, block_header.number);
 
-	let (block_tx, block_rx) = broadcast::channel::<BlockVerified>(1 << 7);
+	// Crawler doesn't consume verified blocks, receiver is kept so forwarded blocks are not reported as failures
+	let (block_tx, _block_rx) = crawl_client::block_channel(1 << 7);
+	let (crawl_result_tx, crawl_result_rx) = broadcast::channel::<CrawlResult>(1 << 7);
+
+	#[cfg(unix)]
+	if let Some(path) = config.crawl_results_socket.clone() {
+		let reconnect = Duration::from_secs(config.crawl_results_socket_reconnect.max(1));
+		let results = crawl_result_tx.subscribe();
+		spawn_in_span(shutdown.with_cancel(crawl_client::socket::stream_results(
+			path, results, reconnect,
+		)));
+	}
+	#[cfg(not(unix))]
+	if config.crawl_results_socket.is_some() {
+		warn!("Crawl results socket is not supported on this platform");
+	}
+
+	if let Some(url) = &config.crawl_results_webhook {
+		let url =
+			crawl_client::webhook::parse_url(url).wrap_err("Invalid crawl results webhook")?;
+		let options = WebhookOptions {
+			unavailable_only: config.crawl_results_webhook_unavailable_only,
+			timeout: Duration::from_secs(config.crawl_results_webhook_timeout.max(1)),
+			retries: config.crawl_results_webhook_retries,
+			retry_delay: Duration::from_secs(1),
+		};
+		let results = crawl_result_tx.subscribe();
+		spawn_in_span(
+			shutdown.with_cancel(crawl_client::webhook::post_results(url, results, options)),
+		);
+	}
 
 	let (maintenance_sender, maintenance_receiver) = mpsc::unbounded_channel::<MaintenanceEvent>();
 	spawn_in_span(shutdown.with_cancel(maintenance::run(
 		config.otel.ot_flush_block_interval,
-		block_rx,
+		crawl_result_rx,
 		shutdown.clone(),
 		maintenance_sender,
 	)));
 
+	let mut assigned_partition = None;
+	if let Some(url) = &config.crawl_partition_assignment_url {
+		let url = crawl_client::assignment::parse_url(url)
+			.wrap_err("Invalid partition assignment URL")?;
+		let timeout = Duration::from_secs(config.crawl_partition_assignment_timeout.max(1));
+		assigned_partition = crawl_client::assignment::load_partition(&url, timeout).await;
+		if let Some(partition) = &assigned_partition {
+			config.crawl.crawl_block_matrix_partition = partition.clone();
+		}
+	}
+	// Partition is reported after the assignment, since the assigned partition is crawled instead of the configured one
+	let partition_size =
+		crawl_client::format_partitions(&config.crawl.crawl_block_matrix_partition);
+
+	let crawl_status = Arc::new(RwLock::new(CrawlStatus::new(&config.crawl)));
+	let (crawl_config_tx, crawl_config_rx) = watch::channel(config.crawl.clone());
+
+	if let Some(path) = &config.crawl_results_csv {
+		let writer = crawl_client::csv::open(path)
+			.wrap_err_with(|| format!("Unable to open crawl results CSV {path}"))?;
+		spawn_in_span(shutdown.with_cancel(crawl_client::csv::write_results(
+			writer,
+			crawl_result_tx.subscribe(),
+			crawl_config_tx.subscribe(),
+		)));
+	}
+
+	if let Some(path) = config_path {
+		spawn_in_span(shutdown.with_cancel(reload_crawl_config(
+			path,
+			crawl_config_tx,
+			assigned_partition,
+		)));
+	}
+
 	let (crawler_sender, crawler_receiver) = mpsc::unbounded_channel::<CrawlerEvent>();
-	let crawler = spawn_in_span(shutdown.with_cancel(crawl_client::run(
-		client_rpc_event_receiver,
-		p2p_client.clone(),
-		config.crawl_block_delay,
-		config.crawl_block_mode,
-		partition,
-		block_tx,
-		crawler_sender,
-	)));
 
-	let metric_attributes = vec![
+	if let Some(method) = config.crawl_reconcile_method.clone() {
+		spawn_in_span(
+			shutdown.with_cancel(crawl_client::reconcile::reconcile_results(
+				rpc_client.clone(),
+				method,
+				crawl_result_tx.subscribe(),
+				crawler_sender.clone(),
+				config.crawl_reconcile_threshold,
+			)),
+		);
+	}
+
+	// Cells and rows are accepted only from the crawl peers, so the success rates don't depend on the other peers
+	let mut crawl_network_client = p2p_client.clone();
+	if !config.crawl_peers.is_empty() {
+		let peers =
+			(config.crawl_peers.iter()).map(|address| <(PeerId, Multiaddr)>::from(address).0);
+		crawl_network_client = crawl_network_client.with_allowed_peers(peers);
+	}
+
+	let channels = CrawlChannels {
+		rpc_event_receiver: client_rpc_event_receiver,
+		config_receiver: crawl_config_rx,
+		block_sender: block_tx,
+		result_sender: crawl_result_tx,
+		event_sender: crawler_sender,
+	};
+	let crawler = crawl_client::spawn(
+		db.clone(),
+		channels,
+		crawl_network_client,
+		crawl_status.clone(),
+		CrawlExtensions::default(),
+		TokioClock,
+		shutdown.clone(),
+	);
+
+	let mut metric_attributes = vec![
 		("role".to_string(), "crawler".to_string()),
 		("origin".to_string(), config.origin.to_string()),
 		("version".to_string(), version.to_string()),
@@ -183,20 +360,35 @@ async fn run(config: Config, db: DB, shutdown: Controller<String>) -> Result<()>
 		("client_alias".to_string(), config.client_alias),
 		("operating_mode".to_string(), "client".to_string()),
 	];
+	metric_attributes.extend(config.crawl_metrics_attributes);
 
 	let metrics = otlp::initialize(
 		ProjectName::new("avail".to_string()),
 		&config.origin,
 		config.otel.clone(),
 	)
-	.wrap_err("Unable to initialize OpenTelemetry service")?;
+	.wrap_err("Unable to initialize OpenTelemetry service")?
+	.with_record_prefix(config.crawl_metrics_prefix)
+	.with_genesis_hash(Some(genesis_hash))
+	.with_min_flush_interval(config.crawl_metrics_min_interval.map(Duration::from_secs));
 
 	let rpc_host = db
 		.get(RpcNodeKey)
 		.map(|node| node.host)
 		.ok_or_else(|| eyre!("No connected host found"))?;
 
-	let mut state = CrawlerState::new(metrics, String::default(), rpc_host, metric_attributes);
+	let mut state = CrawlerState::new(
+		metrics,
+		String::default(),
+		rpc_host,
+		metric_attributes,
+		crawl_status,
+	);
+	state.openmetrics = config.crawl_openmetrics_path.map(|path| OpenMetricsFile {
+		path: PathBuf::from(path),
+		interval: Duration::from_secs(config.crawl_openmetrics_interval.max(1)),
+		snapshot: Snapshot::default(),
+	});
 
 	spawn_in_span(shutdown.with_cancel(async move {
 		state
@@ -204,15 +396,30 @@ async fn run(config: Config, db: DB, shutdown: Controller<String>) -> Result<()>
 			.await;
 	}));
 
-	crawler.await?.map_err(|message| eyre!(message))?;
+	if let Err(reason) = crawler.join().await? {
+		// Other tasks are stopped before the error exits the crawler, e.g. on the zero rate exit,
+		// so the orchestration can restart it
+		let _ = shutdown.trigger_shutdown(reason.clone());
+		return Err(eyre!(reason));
+	}
 	Ok(())
 }
 
+/// Crawl metrics periodically written to the file in OpenMetrics text format
+struct OpenMetricsFile {
+	path: PathBuf,
+	interval: Duration,
+	snapshot: Snapshot,
+}
+
 struct CrawlerState {
 	metrics: Metrics,
+	flush_failures: FlushFailures,
 	multiaddress: String,
 	rpc_host: String,
 	metric_attributes: Vec<(String, String)>,
+	openmetrics: Option<OpenMetricsFile>,
+	crawl_status: Arc<RwLock<CrawlStatus>>,
 }
 
 impl CrawlerState {
@@ -221,12 +428,52 @@ impl CrawlerState {
 		multiaddress: String,
 		rpc_host: String,
 		metric_attributes: Vec<(String, String)>,
+		crawl_status: Arc<RwLock<CrawlStatus>>,
 	) -> Self {
 		CrawlerState {
 			metrics,
+			flush_failures: FlushFailures::default(),
 			multiaddress,
 			rpc_host,
 			metric_attributes,
+			openmetrics: None,
+			crawl_status,
+		}
+	}
+
+	/// Flushes metrics, and returns `true` if flushing succeeded.
+	/// Failure is recorded as the last metrics error of the crawl status,
+	/// and it is counted by the metrics failures counter and the crawl status.
+	fn flush_metrics(&mut self) -> bool {
+		let result = self.metrics.flush(self.attributes());
+		let failed = result.as_ref().err().map(|error| error.to_string());
+		let flushed = self.flush_failures.track(result);
+		if let Some(error) = failed {
+			self.metrics
+				.count(MetricCounter::MetricsFailures, self.attributes());
+			let mut status = self.crawl_status.write().expect("Lock acquired");
+			status.record_error(FailureCategory::Metrics, error);
+			status.metrics_failures = self.flush_failures.total();
+		}
+		flushed
+	}
+
+	fn record(&mut self, value: CrawlMetricValue) {
+		if let Some(openmetrics) = self.openmetrics.as_mut() {
+			openmetrics.snapshot.record(value.clone());
+		}
+		self.metrics.record(value);
+	}
+
+	fn write_openmetrics(&self) {
+		let Some(OpenMetricsFile { path, snapshot, .. }) = &self.openmetrics else {
+			return;
+		};
+		if let Err(error) = snapshot.write(path) {
+			error!(
+				"Failed to write OpenMetrics file {}: {error}",
+				path.display()
+			);
 		}
 	}
 
@@ -234,6 +481,15 @@ impl CrawlerState {
 		self.multiaddress = value;
 	}
 
+	fn update_partition_size(&mut self, partitions: &[Partition]) {
+		let partition_size = crawl_client::format_partitions(partitions);
+		for (key, value) in self.metric_attributes.iter_mut() {
+			if key == "partition_size" {
+				*value = partition_size.clone();
+			}
+		}
+	}
+
 	fn attributes(&self) -> Vec<(String, String)> {
 		let mut attrs = vec![
 			("multiaddress".to_string(), self.multiaddress.clone()),
@@ -251,6 +507,12 @@ impl CrawlerState {
 		mut crawler_receiver: UnboundedReceiver<CrawlerEvent>,
 	) {
 		self.metrics.count(MetricCounter::Starts, self.attributes());
+		// Interval is polled only if writing the OpenMetrics file is enabled
+		let period = self
+			.openmetrics
+			.as_ref()
+			.map_or(Duration::from_secs(60), |openmetrics| openmetrics.interval);
+		let mut openmetrics_interval = tokio::time::interval(period);
 		loop {
 			select! {
 				Some(p2p_event) = p2p_receiver.recv() => {
@@ -284,12 +546,9 @@ impl CrawlerState {
 				Some(maintenance_event) = maintenance_receiver.recv() => {
 					match maintenance_event {
 						MaintenanceEvent::FlushMetrics(block_num) => {
-							if let Err(error) = self.metrics.flush(self.attributes()) {
-								error!(
-									block_num,
-									"Could not handle Flush Maintenance event properly: {error}"
-								);
-							} else {
+							if self.metrics.is_throttled(Instant::now()) {
+								info!(block_num, "Flushing metrics is throttled");
+							} else if self.flush_metrics() {
 								info!(block_num, "Flushing metrics finished");
 							};
 						},
@@ -301,17 +560,114 @@ impl CrawlerState {
 				Some(crawler_event) = crawler_receiver.recv() => {
 					match crawler_event {
 						CrawlerEvent::RecordBlockDelay(delay) => {
-							self.metrics.record(CrawlMetricValue::BlockDelay(delay));
+							self.record(CrawlMetricValue::BlockDelay(delay));
 						},
 						CrawlerEvent::RecordCellSuccessRate(success_rate)=> {
-							self.metrics.record(CrawlMetricValue::CellsSuccessRate(success_rate));
+							self.record(CrawlMetricValue::CellsSuccessRate(success_rate));
 
 						}
 						CrawlerEvent::RecordRowsSuccessRate(success_rate) => {
-							self.metrics.record(CrawlMetricValue::RowsSuccessRate(success_rate));
+							self.record(CrawlMetricValue::RowsSuccessRate(success_rate));
+						}
+						CrawlerEvent::CountAvailableBlocks => {
+							self.record(CrawlMetricValue::AvailableBlocks);
+						}
+						CrawlerEvent::PartitionUpdate(partitions) => {
+							// Metrics of the previous partition are flushed before the attribute is updated
+							self.flush_metrics();
+							self.update_partition_size(&partitions);
+						}
+						CrawlerEvent::RecordCoverage(coverage) => {
+							if let Some(cells) = coverage.cells {
+								self.record(CrawlMetricValue::CellsCoverage(cells));
+							}
+							if let Some(rows) = coverage.rows {
+								self.record(CrawlMetricValue::RowsCoverage(rows));
+							}
+						}
+						CrawlerEvent::CountRefetchedCells(count) => {
+							self.record(CrawlMetricValue::RefetchedCells(count as u64));
+						}
+						CrawlerEvent::RecordReconstructable(reconstructable) => {
+							self.record(CrawlMetricValue::Reconstructable(reconstructable));
+						}
+						CrawlerEvent::RecordRowsDerivedRatio(ratio) => {
+							self.record(CrawlMetricValue::RowsDerivedRatio(ratio));
+						}
+						CrawlerEvent::CountDimensionAnomalies => {
+							self.record(CrawlMetricValue::DimensionAnomalies);
+						}
+						CrawlerEvent::RecordAvailabilityConfidence(confidence) => {
+							self.record(CrawlMetricValue::AvailabilityConfidence(confidence));
+						}
+						CrawlerEvent::CountServedCells { local, dht } => {
+							self.record(CrawlMetricValue::CellsServedLocally(local as u64));
+							self.record(CrawlMetricValue::CellsServedDht(dht as u64));
+						}
+						CrawlerEvent::CountTooOldBlocks => {
+							self.record(CrawlMetricValue::TooOldBlocks);
+						}
+						CrawlerEvent::RecordQueriesPerCell(ratio) => {
+							self.record(CrawlMetricValue::QueriesPerCell(ratio));
+						}
+						CrawlerEvent::CountDuplicateHeaders => {
+							self.record(CrawlMetricValue::DuplicateHeaders);
+						}
+						CrawlerEvent::CountClockSkew => {
+							self.record(CrawlMetricValue::ClockSkew);
+						}
+						CrawlerEvent::RecordRecentGapBlocks(gaps) => {
+							self.record(CrawlMetricValue::RecentGapBlocks(gaps as u64));
+						}
+						CrawlerEvent::CountFetchCacheHit => {
+							self.record(CrawlMetricValue::FetchCacheHits);
+						}
+						CrawlerEvent::CountFetchCacheMiss => {
+							self.record(CrawlMetricValue::FetchCacheMisses);
+						}
+						CrawlerEvent::CountEmptyPartitions => {
+							self.record(CrawlMetricValue::EmptyPartitions);
+						}
+						CrawlerEvent::CountDimensionChanges => {
+							self.record(CrawlMetricValue::DimensionChanges);
+						}
+						CrawlerEvent::RecordSampleRate(sample_rate) => {
+							self.record(CrawlMetricValue::SampleRate(sample_rate));
+						}
+						CrawlerEvent::CountEscalations => {
+							self.record(CrawlMetricValue::Escalations);
+						}
+						CrawlerEvent::CountReconstructionCells(cells) => {
+							self.record(CrawlMetricValue::ReconstructionCells(cells as u64));
+						}
+						CrawlerEvent::RecordBytesFetched(bytes) => {
+							self.record(CrawlMetricValue::BytesFetched(bytes as u64));
+						}
+						CrawlerEvent::CountFastForwardedBlocks(skipped) => {
+							self.record(CrawlMetricValue::FastForwardedBlocks(skipped as u64));
+						}
+						CrawlerEvent::RecordLoopTimes(times) => {
+							self.record(CrawlMetricValue::IdleTime(times.idle.as_millis() as u64));
+							self.record(CrawlMetricValue::SleepingTime(times.sleeping.as_millis() as u64));
+							self.record(CrawlMetricValue::FetchingTime(times.fetching.as_millis() as u64));
+						}
+						CrawlerEvent::CountDeniedBlocks => {
+							self.record(CrawlMetricValue::DeniedBlocks);
+						}
+						CrawlerEvent::CountZeroRateEscalations => {
+							self.record(CrawlMetricValue::ZeroRateEscalations);
+						}
+						CrawlerEvent::RecordIntraBlockRateVariance(variance) => {
+							self.record(CrawlMetricValue::IntraBlockRateVariance(variance));
+						}
+						CrawlerEvent::RecordReconciliationDelta(delta) => {
+							self.record(CrawlMetricValue::ReconciliationDelta(delta));
 						}
 					}
 				}
+				_ = openmetrics_interval.tick(), if self.openmetrics.is_some() => {
+					self.write_openmetrics();
+				}
 				// break the loop if all channels are closed
 				else => break,
 			}
@@ -320,7 +676,7 @@ impl CrawlerState {
 }
 
 mod maintenance {
-	use avail_light_core::{shutdown::Controller, types::BlockVerified};
+	use avail_light_core::{crawl_client::CrawlResult, shutdown::Controller};
 	use color_eyre::eyre::Report;
 	use tokio::sync::{broadcast, mpsc::UnboundedSender};
 	use tracing::{error, info};
@@ -332,16 +688,16 @@ mod maintenance {
 
 	pub async fn run(
 		ot_flush_block_interval: u32,
-		mut block_receiver: broadcast::Receiver<BlockVerified>,
+		mut result_receiver: broadcast::Receiver<CrawlResult>,
 		shutdown: Controller<String>,
 		event_sender: UnboundedSender<OutputEvent>,
 	) {
 		info!("Starting maintenance...");
 
 		loop {
-			match block_receiver.recv().await.map_err(Report::from) {
-				Ok(block) => {
-					let block_num = block.block_num;
+			match result_receiver.recv().await.map_err(Report::from) {
+				Ok(result) => {
+					let block_num = result.block_number;
 					if block_num % ot_flush_block_interval == 0 {
 						info!(block_num, "Flushing metrics...");
 						if let Err(error) = event_sender.send(OutputEvent::FlushMetrics(block_num))
@@ -361,7 +717,7 @@ mod maintenance {
 					}
 				},
 				Err(error) => {
-					let error_msg = format!("Error receiving block: {:#}", error);
+					let error_msg = format!("Error receiving crawl result: {:#}", error);
 					error!("{error_msg}");
 					_ = shutdown.trigger_shutdown(error_msg);
 					break;